@@ -0,0 +1,190 @@
+use cgmath::*;
+
+/// Statistics about the texture atlas a font caches its glyphs in.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct AtlasStats {
+    /// The number of atlas pages. Each page is a separate texture, and is drawn with a separate
+    /// draw call.
+    pub pages: usize,
+    /// The most pages of each kind, bitmap or distance field, that the atlas keeps between frames.
+    /// Glyphs are evicted a whole page at a time to stay within it.
+    pub max_pages: usize,
+    /// The size of each page in pixels.
+    pub page_size: Vector2<u32>,
+    /// The number of glyphs currently in the atlas.
+    pub glyphs: usize,
+    /// The number of pixels used by glyphs, summed over all pages.
+    pub used_area: u64,
+    /// The number of pixels in all pages.
+    pub total_area: u64,
+    /// The number of times a page was cleared to make room for new glyphs, or removed to bring
+    /// the atlas back down to `max_pages`.
+    pub evictions: u64,
+}
+
+impl AtlasStats {
+    /// Returns the fraction of the atlas that's used by glyphs, from 0 to 1.
+    pub fn usage(&self) -> f32 {
+        if self.total_area == 0 {
+            0.0
+        } else {
+            self.used_area as f32 / self.total_area as f32
+        }
+    }
+}
+
+struct SkylineNode {
+    x: u32,
+    y: u32,
+    width: u32,
+}
+
+/// Packs rectangles into a fixed size area using the skyline bottom-left heuristic.
+///
+/// The packer tracks the height of the packed area at each x coordinate, and places each rect as
+/// low as possible. This wastes much less space than packing rects in rows when they have
+/// different heights.
+pub(crate) struct SkylinePacker {
+    size: Vector2<u32>,
+    skyline: Vec<SkylineNode>,
+}
+
+impl SkylinePacker {
+    pub fn new(size: Vector2<u32>) -> Self {
+        Self { size, skyline: vec![SkylineNode { x: 0, y: 0, width: size.x }] }
+    }
+
+    /// Removes all packed rects.
+    pub fn clear(&mut self) {
+        *self = Self::new(self.size);
+    }
+
+    /// Finds space for a rect and returns its top-left corner, or `None` if it doesn't fit.
+    pub fn pack(&mut self, size: Vector2<u32>) -> Option<Point2<u32>> {
+        // The index of the node to place the rect at, the y coordinate it'd be placed at, and the
+        // width of the node. Lower rects are better, and narrower nodes break ties.
+        let mut best: Option<(usize, u32, u32)> = None;
+        for i in 0..self.skyline.len() {
+            if let Some(y) = self.fit(i, size) {
+                let node_width = self.skyline[i].width;
+                let is_better = match best {
+                    None => true,
+                    Some((_, best_y, best_width)) => {
+                        y < best_y || (y == best_y && node_width < best_width)
+                    }
+                };
+                if is_better {
+                    best = Some((i, y, node_width));
+                }
+            }
+        }
+        let (i, y, _) = best?;
+        let x = self.skyline[i].x;
+        self.add_node(i, SkylineNode { x, y: y + size.y, width: size.x });
+        Some(point2(x, y))
+    }
+
+    /// Returns the y coordinate a rect would be placed at if its left edge were at node `i`.
+    fn fit(&self, i: usize, size: Vector2<u32>) -> Option<u32> {
+        let x = self.skyline[i].x;
+        if x + size.x > self.size.x {
+            return None;
+        }
+        let mut width_left = size.x;
+        let mut y = 0;
+        for node in &self.skyline[i..] {
+            if width_left == 0 {
+                break;
+            }
+            y = y.max(node.y);
+            if y + size.y > self.size.y {
+                return None;
+            }
+            width_left = width_left.saturating_sub(node.width);
+        }
+        Some(y)
+    }
+
+    fn add_node(&mut self, i: usize, node: SkylineNode) {
+        let node_end = node.x + node.width;
+        self.skyline.insert(i, node);
+
+        // Shrink or remove the nodes the new one covers
+        while i + 1 < self.skyline.len() {
+            let next = &mut self.skyline[i + 1];
+            if next.x >= node_end {
+                break;
+            }
+            let overlap = node_end - next.x;
+            if overlap < next.width {
+                next.x += overlap;
+                next.width -= overlap;
+                break;
+            }
+            self.skyline.remove(i + 1);
+        }
+
+        // Merge adjacent nodes at the same height
+        let mut j = 0;
+        while j + 1 < self.skyline.len() {
+            if self.skyline[j].y == self.skyline[j + 1].y {
+                self.skyline[j].width += self.skyline[j + 1].width;
+                self.skyline.remove(j + 1);
+            } else {
+                j += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overlaps(a: (Point2<u32>, Vector2<u32>), b: (Point2<u32>, Vector2<u32>)) -> bool {
+        a.0.x < b.0.x + b.1.x
+            && b.0.x < a.0.x + a.1.x
+            && a.0.y < b.0.y + b.1.y
+            && b.0.y < a.0.y + a.1.y
+    }
+
+    #[test]
+    fn packed_rects_dont_overlap() {
+        let mut packer = SkylinePacker::new(vec2(128, 128));
+        let sizes =
+            [vec2(20, 10), vec2(30, 5), vec2(10, 25), vec2(14, 14), vec2(40, 8), vec2(7, 3)];
+        let mut packed = vec![];
+        for &size in sizes.iter().cycle().take(18) {
+            let pos = packer.pack(size).unwrap();
+            assert!(pos.x + size.x <= 128 && pos.y + size.y <= 128);
+            assert!(packed.iter().all(|&other| !overlaps((pos, size), other)));
+            packed.push((pos, size));
+        }
+    }
+
+    #[test]
+    fn pack_returns_none_when_full() {
+        let mut packer = SkylinePacker::new(vec2(32, 32));
+        for _ in 0..4 {
+            assert!(packer.pack(vec2(16, 16)).is_some());
+        }
+        assert_eq!(packer.pack(vec2(1, 1)), None);
+        assert_eq!(packer.pack(vec2(33, 1)), None);
+
+        packer.clear();
+        assert_eq!(packer.pack(vec2(32, 32)), Some(point2(0, 0)));
+    }
+
+    #[test]
+    fn nodes_at_the_same_height_merge() {
+        let mut packer = SkylinePacker::new(vec2(64, 64));
+        assert_eq!(packer.pack(vec2(16, 8)), Some(point2(0, 0)));
+        assert_eq!(packer.pack(vec2(16, 8)), Some(point2(16, 0)));
+        assert_eq!(packer.skyline.len(), 2);
+        assert_eq!((packer.skyline[0].x, packer.skyline[0].y, packer.skyline[0].width), (0, 8, 32));
+
+        // The merged node fits a rect as wide as both of the rects under it
+        assert_eq!(packer.pack(vec2(48, 4)), Some(point2(0, 8)));
+        assert_eq!(packer.pack(vec2(32, 4)), Some(point2(0, 12)));
+    }
+}
@@ -0,0 +1,83 @@
+use js_sys::{Function, Reflect};
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::JsCast;
+use web_sys::window;
+
+/// Somewhere text can be copied to and pasted from.
+pub trait Clipboard {
+    /// Replaces the contents of the clipboard.
+    fn set_text(&self, text: &str);
+
+    /// Returns the text on the clipboard, if there is any and it can be read right away. Clipboards
+    /// that can only be read when the user pastes return `None`, and send `Event::Paste` instead.
+    fn get_text(&self) -> Option<String>;
+}
+
+/// A clipboard that only exists within the program. Useful for tests.
+#[derive(Default)]
+pub struct MemoryClipboard {
+    text: RefCell<Option<String>>,
+}
+
+impl MemoryClipboard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Clipboard for MemoryClipboard {
+    fn set_text(&self, text: &str) {
+        *self.text.borrow_mut() = Some(text.to_owned());
+    }
+
+    fn get_text(&self) -> Option<String> {
+        self.text.borrow().clone()
+    }
+}
+
+/// The system clipboard, accessed through the browser.
+///
+/// Browsers only let pages read the clipboard when the user pastes, so `get_text` always returns
+/// `None`. The pasted text is sent to the active component as `Event::Paste` instead.
+#[derive(Default)]
+pub struct BrowserClipboard;
+
+impl BrowserClipboard {
+    pub fn new() -> Self {
+        BrowserClipboard
+    }
+}
+
+impl Clipboard for BrowserClipboard {
+    fn set_text(&self, text: &str) {
+        // `navigator.clipboard` is only available in secure contexts, and writing to it can fail
+        // if the page doesn't have permission
+        let navigator = window().unwrap().navigator();
+        if let Ok(clipboard) = Reflect::get(&navigator, &"clipboard".into()) {
+            if let Ok(write_text) = Reflect::get(&clipboard, &"writeText".into()) {
+                if let Some(write_text) = write_text.dyn_ref::<Function>() {
+                    let _ = write_text.call1(&clipboard, &text.into());
+                }
+            }
+        }
+    }
+
+    fn get_text(&self) -> Option<String> {
+        None
+    }
+}
+
+thread_local! {
+    static DEFAULT_CLIPBOARD: Rc<dyn Clipboard> = if cfg!(target_arch = "wasm32") {
+        Rc::new(BrowserClipboard::new())
+    } else {
+        Rc::new(MemoryClipboard::new())
+    };
+}
+
+/// Returns the clipboard components use unless they're given a different one: a
+/// `BrowserClipboard` on WebAssembly, and a `MemoryClipboard` elsewhere.
+pub fn default_clipboard() -> Rc<dyn Clipboard> {
+    DEFAULT_CLIPBOARD.with(|clipboard| clipboard.clone())
+}
@@ -7,6 +7,7 @@ use webgl_wrapper::*;
 use crate::color::*;
 use crate::shader_header::*;
 
+#[derive(Copy, Clone)]
 pub struct PlainVert {
     pub pos: Point2<f32>,
     pub color: Color4,
@@ -23,6 +24,19 @@ impl VertexComponent for PlainVert {
     }
 }
 
+impl ClipVert for PlainVert {
+    fn pos(&self) -> Point2<f32> {
+        self.pos
+    }
+
+    fn lerp(&self, other: &Self, amount: f32) -> Self {
+        PlainVert {
+            pos: self.pos + (other.pos - self.pos) * amount,
+            color: self.color.lerp(other.color, amount),
+        }
+    }
+}
+
 pub struct PlainUniforms {
     pub matrix: Matrix4<f32>,
     pub color: Color4,
@@ -50,6 +64,7 @@ impl GlUniforms for PlainUniformsGl {
     }
 }
 
+#[derive(Copy, Clone)]
 pub struct ImageVert {
     pub pos: Point2<f32>,
     pub uv: Point2<f32>,
@@ -68,6 +83,20 @@ impl VertexComponent for ImageVert {
     }
 }
 
+impl ClipVert for ImageVert {
+    fn pos(&self) -> Point2<f32> {
+        self.pos
+    }
+
+    fn lerp(&self, other: &Self, amount: f32) -> Self {
+        ImageVert {
+            pos: self.pos + (other.pos - self.pos) * amount,
+            uv: self.uv + (other.uv - self.uv) * amount,
+            color: self.color.lerp(other.color, amount),
+        }
+    }
+}
+
 pub struct ImageUniforms<'a> {
     pub matrix: Matrix4<f32>,
     pub color: Color4,
@@ -111,20 +140,20 @@ pub struct Draw2dPrograms {
 impl Draw2dPrograms {
     pub fn new(context: &GlContext) -> Self {
         let plain_program: GlProgram<PlainVert, PlainUniformsGl> = GlProgram::new_with_header(
-            &context,
+            context,
             include_str!("../shaders/plain_vert.glsl"),
             include_str!("../shaders/plain_frag.glsl"),
             true,
         );
         let image_program_srgb: GlProgram<ImageVert, ImageUniformsGl> = GlProgram::new_with_header(
-            &context,
+            context,
             include_str!("../shaders/image_vert.glsl"),
             include_str!("../shaders/image_frag.glsl"),
             true,
         );
         let image_program_linear: GlProgram<ImageVert, ImageUniformsGl> =
             GlProgram::new_with_header(
-                &context,
+                context,
                 include_str!("../shaders/image_vert.glsl"),
                 include_str!("../shaders/image_frag.glsl"),
                 false,
@@ -139,6 +168,7 @@ impl Draw2dPrograms {
 ///
 // TODO: many of the methods here should be on MeshBuilder<PlainVert, Triangles>
 pub struct Draw2d {
+    clip_stack: Vec<Rect<i32>>,
     triangle_mesh_builder: MeshBuilder<PlainVert, Triangles>,
     triangle_mesh: Mesh<PlainVert, PlainUniformsGl, Triangles>,
     image_mesh_builder: MeshBuilder<ImageVert, Triangles>,
@@ -162,6 +192,7 @@ impl Draw2d {
         let image_mesh_linear =
             Mesh::new(context, &programs.image_program_linear, DrawMode::Draw2D);
         Self {
+            clip_stack: vec![],
             triangle_mesh_builder,
             triangle_mesh,
             image_mesh_builder,
@@ -190,33 +221,71 @@ impl Draw2d {
         self.triangle_mesh_builder.clear();
     }
 
-    /// Draws a filled convex polygon.
-    pub fn fill_poly(&mut self, verts: &[Point2<f32>], color: Color4) {
-        assert!(verts.len() >= 3);
+    /// Restricts everything queued from now on to the given rect, intersected with the current
+    /// clip rect if there is one. Each call must be paired with a call to `pop_clip_rect`.
+    ///
+    /// Shapes are clipped on the CPU as they're queued, instead of with the scissor test, so
+    /// clipping doesn't split the queued shapes into multiple draw calls.
+    pub fn push_clip_rect(&mut self, rect: Rect<i32>) {
+        let rect = match self.clip_stack.last() {
+            Some(clip_rect) => intersect_rects(*clip_rect, rect),
+            None => rect,
+        };
+        self.clip_stack.push(rect);
+    }
+
+    /// Undoes the most recent call to `push_clip_rect`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there's no clip rect to pop.
+    pub fn pop_clip_rect(&mut self) {
+        self.clip_stack.pop().expect("pop_clip_rect called without a matching push_clip_rect");
+    }
+
+    /// Returns the rect that everything is currently clipped to, if any.
+    pub fn clip_rect(&self) -> Option<Rect<i32>> {
+        self.clip_stack.last().copied()
+    }
+
+    /// Queues a convex polygon, clipping it to the current clip rect.
+    fn queue_convex_poly(&mut self, verts: Vec<PlainVert>) {
+        let verts = match self.clip_stack.last() {
+            Some(clip_rect) => clip_convex_poly(verts, clip_rect.cast().unwrap()),
+            None => verts,
+        };
+        if verts.len() < 3 {
+            return;
+        }
         let mesh_builder = &mut self.triangle_mesh_builder;
-        let a = mesh_builder.vert(PlainVert { pos: verts[0], color });
-        let mut b = mesh_builder.vert(PlainVert { pos: verts[1], color });
-        for c in verts.iter().skip(2) {
-            let c = mesh_builder.vert(PlainVert { pos: *c, color });
+        let a = mesh_builder.vert(verts[0]);
+        let mut b = mesh_builder.vert(verts[1]);
+        for c in verts.into_iter().skip(2) {
+            let c = mesh_builder.vert(c);
             mesh_builder.triangle(a, b, c);
             b = c;
         }
     }
 
+    /// Draws a filled convex polygon.
+    pub fn fill_poly(&mut self, verts: &[Point2<f32>], color: Color4) {
+        assert!(verts.len() >= 3);
+        self.queue_convex_poly(verts.iter().map(|&pos| PlainVert { pos, color }).collect());
+    }
+
     /// Draws a line strip.
     // TODO: change all coords to i32? Then ensure that all lines are rendered in a pixel-perfect way; the coordinates will have to be adjusted by half for either even or odd line widths; I don't remember which. But first, check how quantizing this affects hex grid rendering; does it make it better or worse?
     pub fn draw_line_strip(&mut self, verts: &[Point2<f32>], color: Color4, width: f32) {
         assert!(verts.len() >= 2);
-        let mesh_builder = &mut self.triangle_mesh_builder;
         let half_width = width * 0.5;
         for (a, b) in verts.iter().zip(verts.iter().skip(1)) {
             let perp = ccw_perp(*b - *a).normalize();
-            let vert_a = mesh_builder.vert(PlainVert { pos: *a + perp * half_width, color });
-            let vert_b = mesh_builder.vert(PlainVert { pos: *a - perp * half_width, color });
-            let vert_c = mesh_builder.vert(PlainVert { pos: *b + perp * half_width, color });
-            let vert_d = mesh_builder.vert(PlainVert { pos: *b - perp * half_width, color });
-            mesh_builder.triangle(vert_a, vert_b, vert_c);
-            mesh_builder.triangle(vert_b, vert_c, vert_d);
+            self.queue_convex_poly(vec![
+                PlainVert { pos: *a + perp * half_width, color },
+                PlainVert { pos: *b + perp * half_width, color },
+                PlainVert { pos: *b - perp * half_width, color },
+                PlainVert { pos: *a - perp * half_width, color },
+            ]);
         }
     }
 
@@ -253,6 +322,8 @@ impl Draw2d {
     }
 
     /// Draws an image. Unlike the other functions on `Draw2d`, this draws the image immediately.
+    ///
+    /// The image is clipped to the current clip rect, if any.
     pub fn draw_image(
         &mut self,
         surface: &(impl Surface + ?Sized),
@@ -265,28 +336,35 @@ impl Draw2d {
             * ortho(0.0, surface_size.x as f32, 0.0, surface_size.y as f32, 0.0, 1.0)
             * Matrix4::from_nonuniform_scale(scale, scale, 1.0);
 
-        let a = self.image_mesh_builder.vert(ImageVert {
-            pos,
-            uv: point2(0.0, 0.0),
-            color: Color4::WHITE,
-        });
-        let b = self.image_mesh_builder.vert(ImageVert {
-            pos: pos + vec2(tex.size().x as f32, 0.0),
-            uv: point2(1.0, 0.0),
-            color: Color4::WHITE,
-        });
-        let c = self.image_mesh_builder.vert(ImageVert {
-            pos: pos + vec2(0.0, tex.size().y as f32),
-            uv: point2(0.0, 1.0),
-            color: Color4::WHITE,
-        });
-        let d = self.image_mesh_builder.vert(ImageVert {
-            pos: pos + vec2(tex.size().x as f32, tex.size().y as f32),
-            uv: point2(1.0, 1.0),
-            color: Color4::WHITE,
-        });
-        self.image_mesh_builder.triangle(a, b, c);
-        self.image_mesh_builder.triangle(b, c, d);
+        let size = vec2(tex.size().x as f32, tex.size().y as f32);
+        let verts = vec![
+            ImageVert { pos, uv: point2(0.0, 0.0), color: Color4::WHITE },
+            ImageVert { pos: pos + vec2(size.x, 0.0), uv: point2(1.0, 0.0), color: Color4::WHITE },
+            ImageVert { pos: pos + size, uv: point2(1.0, 1.0), color: Color4::WHITE },
+            ImageVert { pos: pos + vec2(0.0, size.y), uv: point2(0.0, 1.0), color: Color4::WHITE },
+        ];
+        // The positions are scaled by the matrix, so the clip rect must be unscaled to match
+        let verts = match self.clip_stack.last() {
+            Some(clip_rect) => {
+                let clip_rect: Rect<f32> = clip_rect.cast().unwrap();
+                let clip_rect = Rect::new(
+                    Point2::from_vec(clip_rect.start.to_vec() / scale),
+                    Point2::from_vec(clip_rect.end.to_vec() / scale),
+                );
+                clip_convex_poly(verts, clip_rect)
+            }
+            None => verts,
+        };
+        if verts.len() < 3 {
+            return;
+        }
+        let a = self.image_mesh_builder.vert(verts[0]);
+        let mut b = self.image_mesh_builder.vert(verts[1]);
+        for c in verts.into_iter().skip(2) {
+            let c = self.image_mesh_builder.vert(c);
+            self.image_mesh_builder.triangle(a, b, c);
+            b = c;
+        }
 
         let image_mesh =
             if tex.is_srgb() { &mut self.image_mesh_srgb } else { &mut self.image_mesh_linear };
@@ -297,8 +375,105 @@ impl Draw2d {
     }
 }
 
+/// A vertex whose attributes can be interpolated, so that polygons made of it can be clipped.
+pub(crate) trait ClipVert: Clone {
+    fn pos(&self) -> Point2<f32>;
+    fn lerp(&self, other: &Self, amount: f32) -> Self;
+}
+
+/// Clips a convex polygon to a rect, using the Sutherland-Hodgman algorithm. The result is also
+/// convex, and is empty if the polygon is entirely outside of the rect.
+pub(crate) fn clip_convex_poly<V: ClipVert>(verts: Vec<V>, rect: Rect<f32>) -> Vec<V> {
+    let verts = clip_to_edge(verts, |pos| pos.x - rect.start.x);
+    let verts = clip_to_edge(verts, |pos| rect.end.x - pos.x);
+    let verts = clip_to_edge(verts, |pos| pos.y - rect.start.y);
+    clip_to_edge(verts, |pos| rect.end.y - pos.y)
+}
+
+/// Clips a convex polygon to the half-plane where `dist` is non-negative.
+fn clip_to_edge<V: ClipVert>(verts: Vec<V>, dist: impl Fn(Point2<f32>) -> f32) -> Vec<V> {
+    let mut res = Vec::with_capacity(verts.len() + 1);
+    for (i, a) in verts.iter().enumerate() {
+        let b = &verts[(i + 1) % verts.len()];
+        let dist_a = dist(a.pos());
+        let dist_b = dist(b.pos());
+        if dist_a >= 0.0 {
+            res.push(a.clone());
+        }
+        if (dist_a >= 0.0) != (dist_b >= 0.0) {
+            res.push(a.lerp(b, dist_a / (dist_a - dist_b)));
+        }
+    }
+    res
+}
+
+/// Returns the overlap between two rects. If they don't overlap, the result has a size of zero.
+pub fn intersect_rects(a: Rect<i32>, b: Rect<i32>) -> Rect<i32> {
+    let start = point2(a.start.x.max(b.start.x), a.start.y.max(b.start.y));
+    let end = point2(a.end.x.min(b.end.x).max(start.x), a.end.y.min(b.end.y).max(start.y));
+    Rect::new(start, end)
+}
+
 /// Returns the vector 90 degrees counterclockwise from the given vector.
 #[inline]
 fn ccw_perp<T: Neg<Output = T>>(x: Vector2<T>) -> Vector2<T> {
     vec2(x.y, -x.x)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x: f32, y: f32, w: f32, h: f32) -> Rect<f32> {
+        Rect::new(point2(x, y), point2(x + w, y + h))
+    }
+
+    /// A 10x10 square whose UVs are its positions divided by 10.
+    fn square() -> Vec<ImageVert> {
+        [(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)]
+            .iter()
+            .map(|&(x, y)| ImageVert {
+                pos: point2(x, y),
+                uv: point2(x / 10.0, y / 10.0),
+                color: Color4::WHITE,
+            })
+            .collect()
+    }
+
+    fn positions(verts: &[ImageVert]) -> Vec<(f32, f32)> {
+        verts.iter().map(|vert| (vert.pos.x, vert.pos.y)).collect()
+    }
+
+    #[test]
+    fn polygons_inside_the_rect_are_unchanged() {
+        let clipped = clip_convex_poly(square(), rect(-5.0, 0.0, 20.0, 10.0));
+        assert_eq!(positions(&clipped), positions(&square()));
+    }
+
+    #[test]
+    fn polygons_outside_the_rect_are_removed() {
+        assert!(clip_convex_poly(square(), rect(20.0, 0.0, 10.0, 10.0)).is_empty());
+        assert!(clip_convex_poly(square(), rect(0.0, -20.0, 10.0, 10.0)).is_empty());
+    }
+
+    #[test]
+    fn clipping_interpolates_attributes() {
+        let clipped = clip_convex_poly(square(), rect(5.0, 2.0, 20.0, 20.0));
+        assert_eq!(positions(&clipped), vec![(10.0, 2.0), (10.0, 10.0), (5.0, 10.0), (5.0, 2.0)]);
+        for vert in &clipped {
+            assert!(vert.uv.abs_diff_eq(&(vert.pos / 10.0), 1e-6));
+        }
+
+        let triangle = vec![
+            PlainVert { pos: point2(0.0, 0.0), color: Color4::BLACK },
+            PlainVert { pos: point2(8.0, 0.0), color: Color4::WHITE },
+            PlainVert { pos: point2(0.0, 8.0), color: Color4::BLACK },
+        ];
+        let clipped = clip_convex_poly(triangle, rect(2.0, 0.0, 10.0, 10.0));
+        let colors: Vec<_> = clipped.iter().map(|vert| (vert.pos, vert.color.r)).collect();
+        assert_eq!(
+            colors,
+            vec![(point2(2.0, 0.0), 0.25), (point2(8.0, 0.0), 1.0), (point2(2.0, 6.0), 0.25)]
+        );
+    }
+}
@@ -9,7 +9,10 @@ pub enum Event {
     KeyUp(Key),
     MouseDown(MouseButton, Point2<i32>),
     MouseUp(MouseButton, Point2<i32>),
-    MouseMove { pos: Point2<i32>, movement: Vector2<i32> },
+    MouseMove {
+        pos: Point2<i32>,
+        movement: Vector2<i32>,
+    },
     MouseEnter,
     MouseLeave,
     FocusGained,
@@ -18,6 +21,8 @@ pub enum Event {
     PointerLocked,
     PointerUnlocked,
     Scroll(f64),
+    /// Text pasted from the system clipboard. Browsers send this after the key press that pastes.
+    Paste(String),
 }
 
 pub type Keycode = String;
@@ -39,6 +44,44 @@ pub struct Key {
 // TODO: add more
 const MODIFIERS: &[&str] = &["Alt", "Control", "Shift"];
 
+/// `KeyboardEvent.key` values of keys that don't type anything. See
+/// [this page](https://developer.mozilla.org/en-US/docs/Web/API/KeyboardEvent/key/Key_Values).
+#[rustfmt::skip]
+const NAMED_KEYS: &[&str] = &[
+    "Unidentified", "Dead", "Process",
+    // Modifiers
+    "Alt", "AltGraph", "CapsLock", "Control", "Fn", "FnLock", "Hyper", "Meta", "NumLock",
+    "ScrollLock", "Shift", "Super", "Symbol", "SymbolLock", "OS",
+    // Whitespace, navigation and editing
+    "Enter", "Tab", "ArrowDown", "ArrowLeft", "ArrowRight", "ArrowUp", "End", "Home", "PageDown",
+    "PageUp", "Backspace", "Clear", "Copy", "CrSel", "Cut", "Delete", "EraseEof", "ExSel",
+    "Insert", "Paste", "Redo", "Undo",
+    // UI
+    "Accept", "Again", "Attn", "Cancel", "ContextMenu", "Escape", "Execute", "Find", "Finish",
+    "Help", "Pause", "Play", "Props", "Select", "ZoomIn", "ZoomOut",
+    // Devices
+    "BrightnessDown", "BrightnessUp", "Eject", "LogOff", "Power", "PowerOff", "PrintScreen",
+    "Hibernate", "Standby", "WakeUp",
+    // IME and composition
+    "AllCandidates", "Alphanumeric", "CodeInput", "Compose", "Convert", "FinalMode",
+    "GroupFirst", "GroupLast", "GroupNext", "GroupPrevious", "ModeChange", "NextCandidate",
+    "NonConvert", "PreviousCandidate", "SingleCandidate", "HangulMode", "HanjaMode",
+    "JunjaMode", "Eisu", "Hankaku", "Hiragana", "HiraganaKatakana", "KanaMode", "KanjiMode",
+    "Katakana", "Romaji", "Zenkaku", "ZenkakuHankaku",
+    // Function keys
+    "F1", "F2", "F3", "F4", "F5", "F6", "F7", "F8", "F9", "F10", "F11", "F12", "F13", "F14",
+    "F15", "F16", "F17", "F18", "F19", "F20", "Soft1", "Soft2", "Soft3", "Soft4",
+    // Media and browser
+    "MediaPlayPause", "MediaStop", "MediaTrackNext", "MediaTrackPrevious", "MediaPlay",
+    "MediaPause", "MediaRecord", "MediaRewind", "MediaFastForward", "AudioVolumeDown",
+    "AudioVolumeUp", "AudioVolumeMute", "MicrophoneVolumeMute", "BrowserBack",
+    "BrowserFavorites", "BrowserForward", "BrowserHome", "BrowserRefresh", "BrowserSearch",
+    "BrowserStop", "LaunchApplication1", "LaunchApplication2", "LaunchCalculator",
+    "LaunchCalendar", "LaunchMail", "LaunchMediaPlayer", "LaunchMusicPlayer",
+    "LaunchMyComputer", "LaunchPhone", "LaunchScreenSaver", "LaunchSpreadsheet",
+    "LaunchWebBrowser", "LaunchWebCam", "LaunchWordProcessor",
+];
+
 impl Key {
     pub(crate) fn from_js(js_key: &KeyboardEvent) -> Self {
         Self {
@@ -53,6 +96,22 @@ impl Key {
     pub fn is_modifier(&self) -> bool {
         MODIFIERS.contains(&self.key.as_ref())
     }
+
+    /// Returns the text typed by this key press, if any. This is usually a single character, but
+    /// IME and compose input can produce several at once. Named keys such as "ArrowLeft" and key
+    /// presses with Ctrl or Alt held down don't type anything.
+    pub fn text(&self) -> Option<&str> {
+        if self.key.is_empty()
+            || NAMED_KEYS.contains(&self.key.as_ref())
+            || self.key.chars().any(char::is_control)
+            || self.ctrl
+            || self.alt
+        {
+            None
+        } else {
+            Some(&self.key)
+        }
+    }
 }
 
 #[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
@@ -105,3 +164,30 @@ pub fn get_window_size() -> Vector2<u32> {
         window.inner_height().unwrap().as_f64().unwrap() as u32,
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(key: &str, ctrl: bool) -> Key {
+        Key { key: key.to_owned(), code: String::new(), shift: false, ctrl, alt: false }
+    }
+
+    #[test]
+    fn key_text() {
+        for text in
+            &["a", "é", "e\u{301}", "日", "日本語", "👩\u{200d}👩\u{200d}👧", " ", "abc", "F1x"]
+        {
+            assert_eq!(key(text, false).text(), Some(*text));
+        }
+        for named in
+            &["ArrowLeft", "Backspace", "Enter", "F1", "F12", "Dead", "Unidentified", "Process"]
+        {
+            assert_eq!(key(named, false).text(), None);
+        }
+        assert_eq!(key("", false).text(), None);
+        assert_eq!(key("\t", false).text(), None);
+        assert_eq!(key("a", true).text(), None);
+        assert_eq!(key("日", true).text(), None);
+    }
+}
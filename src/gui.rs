@@ -1,6 +1,8 @@
 use cgmath::*;
 use collect_mac::*;
 use fnv::*;
+use log::*;
+use std::hash::{Hash, Hasher};
 use std::mem;
 use uid::*;
 use webgl_wrapper::*;
@@ -22,14 +24,121 @@ pub type WidgetId = Id<WidgetId_>;
 pub struct Theme {
     pub font: Font,
     pub label_color: Color4,
+    /// An outline drawn around the text of labels, unless they have their own.
+    pub label_outline: Option<TextOutline>,
+    /// A drop shadow drawn behind the text of labels, unless they have their own.
+    pub label_shadow: Option<TextShadow>,
     pub button_text_color: Color4,
     pub button_fill_color: Color4,
     pub button_border_color: Color4,
     pub button_selected_fill_color: Color4,
     pub button_active_fill_color: Color4,
+    pub selection_color: Color4,
     pub padding: i32,
 }
 
+impl Theme {
+    /// Hashes the parts of the theme that widgets' sizes depend on, so that a `LayoutCache` can
+    /// tell when they change.
+    fn layout_hash(&self) -> u64 {
+        let outline = self.label_outline.map(|outline| outline.width.to_bits());
+        let shadow =
+            self.label_shadow.map(|shadow| (shadow.offset.x.to_bits(), shadow.offset.y.to_bits()));
+        hash_layout(&(self.font.layout_hash(), self.padding, outline, shadow))
+    }
+
+    /// Creates a theme with the given font, white labels, grey buttons, and 4 pixels of padding.
+    ///
+    /// Fields can be changed afterwards, so that adding a field to `Theme` doesn't break code that
+    /// creates one.
+    pub fn new(font: Font) -> Self {
+        Theme {
+            font,
+            label_color: Color4::WHITE,
+            label_outline: None,
+            label_shadow: None,
+            button_text_color: Color4::WHITE,
+            button_fill_color: Color4::from_grayscale_srgb(0.25),
+            button_border_color: Color4::from_grayscale_srgb(0.6),
+            button_selected_fill_color: Color4::from_grayscale_srgb(0.35),
+            button_active_fill_color: Color4::from_grayscale_srgb(0.15),
+            selection_color: Color4::from_srgba(0.2, 0.4, 0.8, 0.5),
+            padding: 4,
+        }
+    }
+}
+
+/// The range of sizes a widget can have, passed down to it by its parent during layout.
+///
+/// A maximum of `BoxConstraints::UNBOUNDED` means the widget can be as large as it wants in that
+/// direction, such as the height of the contents of a `ScrollArea`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct BoxConstraints {
+    pub min: Vector2<i32>,
+    pub max: Vector2<i32>,
+}
+
+impl BoxConstraints {
+    pub const UNBOUNDED: i32 = i32::MAX;
+
+    pub fn new(min: Vector2<i32>, max: Vector2<i32>) -> Self {
+        BoxConstraints { min, max }
+    }
+
+    /// Constraints that only allow the given size.
+    pub fn tight(size: Vector2<i32>) -> Self {
+        Self::new(size, size)
+    }
+
+    /// Constraints that allow any size up to `max`.
+    pub fn loose(max: Vector2<i32>) -> Self {
+        Self::new(Vector2::zero(), max)
+    }
+
+    /// Constraints that allow any size.
+    pub fn unbounded() -> Self {
+        Self::loose(vec2(Self::UNBOUNDED, Self::UNBOUNDED))
+    }
+
+    /// Constraints that only allow the given width, and any height.
+    pub fn tight_width(width: i32) -> Self {
+        Self::new(vec2(width, 0), vec2(width, Self::UNBOUNDED))
+    }
+
+    /// Returns the size within the constraints that's closest to `size`.
+    pub fn constrain(&self, size: Vector2<i32>) -> Vector2<i32> {
+        vec2(size.x.max(self.min.x).min(self.max.x), size.y.max(self.min.y).min(self.max.y))
+    }
+
+    /// Returns the same constraints with no minimum size.
+    pub fn loosen(&self) -> Self {
+        Self::loose(self.max)
+    }
+
+    /// Shrinks the constraints by `amount`, such as to leave room for padding around a child.
+    pub fn deflate(&self, amount: Vector2<i32>) -> Self {
+        let deflate = |max: i32, amount: i32| {
+            if max == Self::UNBOUNDED {
+                max
+            } else {
+                (max - amount).max(0)
+            }
+        };
+        Self::new(
+            vec2((self.min.x - amount.x).max(0), (self.min.y - amount.y).max(0)),
+            vec2(deflate(self.max.x, amount.x), deflate(self.max.y, amount.y)),
+        )
+    }
+
+    pub fn has_bounded_width(&self) -> bool {
+        self.max.x != Self::UNBOUNDED
+    }
+
+    pub fn has_bounded_height(&self) -> bool {
+        self.max.y != Self::UNBOUNDED
+    }
+}
+
 /// Components store persistent data about a widget or group of widgets. They
 /// are typically used for widgets that provide user input.
 pub trait Component: Widget {
@@ -58,6 +167,7 @@ pub trait Widget {
 
     /// Does *not* need to draw its children. Its children will be automatically drawn after
     /// this widget is drawn.
+    #[allow(clippy::too_many_arguments)]
     fn draw(
         &self,
         context: &GlContext,
@@ -69,58 +179,685 @@ pub trait Widget {
         is_active: bool,
     );
 
+    /// Returns the smallest size the widget can be laid out at. Containers use it to divide up
+    /// their space before asking their children to lay themselves out with `layout`.
+    ///
     /// By the time this is called, min_sizes will contain the min size of each
     /// child.
+    ///
+    /// The default implementation is the size `layout` returns without any constraints, so a
+    /// widget only needs to implement one of the two. A widget that implements neither has a min
+    /// size of zero.
+    ///
+    /// This doesn't have access to a `GlContext`, so that layouts can be computed without one.
     fn min_size(
         &self,
-        context: &GlContext,
         theme: &Theme,
         min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
-        window_size: Vector2<i32>,
-    ) -> Vector2<i32>;
+        _window_size: Vector2<i32>,
+    ) -> Vector2<i32> {
+        self.layout(BoxConstraints::unbounded(), theme, min_sizes)
+    }
+
+    /// Returns the size the widget takes up within the constraints passed down by its parent.
+    /// Layout is driven from the root, which is given constraints that only allow the window
+    /// size, and each container passes constraints down to its children with `widget_layout`.
+    ///
+    /// This should be overridden by widgets whose size depends on the space they're given, such
+    /// as wrapped text whose height depends on its width, and by containers of such widgets. The
+    /// default implementation is the min size, clamped to the constraints, so widgets that only
+    /// implement `min_size` are laid out as they always were.
+    ///
+    /// By the time this is called, min_sizes will contain the min size of every widget. Use
+    /// `widget_layout` rather than calling this directly on children.
+    fn layout(
+        &self,
+        constraints: BoxConstraints,
+        _theme: &Theme,
+        min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+    ) -> Vector2<i32> {
+        constraints.constrain(min_size_of(self.id(), min_sizes))
+    }
+
+    /// Returns the distance from the top of the widget to the baseline of its first line of text,
+    /// which `Flex` uses to line up text in neighboring widgets. Widgets without text return
+    /// `None`.
+    fn baseline(&self, _theme: &Theme) -> Option<i32> {
+        None
+    }
+
+    /// Returns a hash of everything about the widget that affects its layout, other than its ID
+    /// and its children, such as the text of a label. Widgets with the same hash whose children
+    /// have the same hashes must have the same min size and lay out their children the same way,
+    /// so the hash should also identify the type of widget. `hash_layout` can be used to compute
+    /// it.
+    ///
+    /// `Gui` uses this to reuse the layout of parts of the widget tree that haven't changed since
+    /// the last frame. Returning `None`, the default, means the widget and every widget
+    /// containing it are laid out again every frame.
+    fn layout_hash(&self) -> Option<u64> {
+        None
+    }
 
     /// Returns a reference to each child.
     fn children(&self) -> Vec<&dyn Widget> {
         vec![]
     }
 
-    /// This must add the widget's `Rect` and call itself recursively for each
-    /// child. It must be overridden if the widget has any children.
+    /// If this returns true, everything the widget and its children draw is clipped to the
+    /// widget's `Rect`. This is useful for widgets whose contents can be larger than the space
+    /// they're given, such as text.
+    fn clips_to_rect(&self) -> bool {
+        false
+    }
+
+    /// If this returns a `Rect`, the widget's children are clipped to it: anything they draw
+    /// outside of it is hidden, and they don't receive mouse events outside of it.
+    ///
+    /// `rect` is the widget's own `Rect`. This is called after `compute_rects`, which should give
+    /// the children their `Rect`s within `WidgetRects::with_bounds` with the same rect.
+    fn child_clip_rect(&self, _rect: Rect<i32>) -> Option<Rect<i32>> {
+        None
+    }
+
+    /// Returns true if the given child is an overlay, such as a tooltip in a `Stack`, which is
+    /// drawn over the rest of the GUI wherever it's placed. `Layout::validate` allows overlays to
+    /// extend outside of their parent's `Rect`.
+    fn is_overlay(&self, _child: WidgetId) -> bool {
+        false
+    }
+
+    /// This must add the widget's `Rect` and call `widget_compute_rects` for each child. It must
+    /// be overridden if the widget has any children. Containers can use `widget_layout` to find
+    /// the size each child takes up in the space available.
+    ///
+    /// This isn't called for parts of the tree whose `Rect`s a `LayoutCache` reuses, so it
+    /// shouldn't change any state. Widgets that need to remember their layout should do so in
+    /// `laid_out`.
     fn compute_rects(
         &self,
         rect: Rect<i32>,
         _theme: &Theme,
         _min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
-        widget_rects: &mut FnvHashMap<WidgetId, Rect<i32>>,
+        widget_rects: &mut WidgetRects,
     ) {
         widget_rects.insert(self.id(), rect);
     }
+
+    /// Called with the `Rect` of every widget in the tree each time it's laid out, including when
+    /// they're reused from a `LayoutCache`. Components that keep state between frames, such as
+    /// the size of a `ScrollArea`, can record it here.
+    fn laid_out(&self, _widget_rects: &FnvHashMap<WidgetId, Rect<i32>>) {}
 }
 
 fn compute_widget_min_size(
     widget: &dyn Widget,
-    context: &GlContext,
     theme: &Theme,
     min_sizes: &mut FnvHashMap<WidgetId, Vector2<i32>>,
     window_size: Vector2<i32>,
 ) {
     for child in widget.children() {
-        compute_widget_min_size(child, context, theme, min_sizes, window_size);
+        compute_widget_min_size(child, theme, min_sizes, window_size);
     }
-    let min_size = widget.min_size(context, theme, min_sizes, window_size);
+    let min_size = widget.min_size(theme, min_sizes, window_size);
     min_sizes.insert(widget.id(), min_size);
 }
 
+/// Hashes the state of a widget that affects its layout, for `Widget::layout_hash`.
+pub fn hash_layout(state: &impl Hash) -> u64 {
+    let mut hasher = FnvHasher::default();
+    state.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Calls `f` on a widget and each of its descendants, in pre-order.
+fn for_each_widget<'a>(widget: &'a dyn Widget, f: &mut impl FnMut(&'a dyn Widget)) {
+    f(widget);
+    for child in widget.children() {
+        for_each_widget(child, f);
+    }
+}
+
+/// Adds the hash of each subtree whose widgets all have a `Widget::layout_hash` to `hashes`, by
+/// the ID of the subtree's root, and returns the hash of the whole tree.
+fn subtree_hash(widget: &dyn Widget, hashes: &mut FnvHashMap<WidgetId, u64>) -> Option<u64> {
+    let child_hashes: Vec<Option<u64>> =
+        widget.children().into_iter().map(|child| subtree_hash(child, hashes)).collect();
+    let child_hashes: Option<Vec<u64>> = child_hashes.into_iter().collect();
+    let hash = hash_layout(&(widget.layout_hash()?, child_hashes?));
+    hashes.insert(widget.id(), hash);
+    Some(hash)
+}
+
+/// Returns the min size of the widget with the given ID, or zero if it wasn't computed.
+///
+/// Min sizes are only computed for widgets returned by `Widget::children`, so a container that
+/// leaves one of its children out gets zero for it rather than panicking during layout.
+pub fn min_size_of(id: WidgetId, min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>) -> Vector2<i32> {
+    min_sizes.get(&id).copied().unwrap_or_else(Vector2::zero)
+}
+
+/// Returns the size a widget takes up within the given constraints. The size always satisfies
+/// the constraints, even if the widget's `layout` doesn't.
+pub fn widget_layout(
+    widget: &dyn Widget,
+    constraints: BoxConstraints,
+    theme: &Theme,
+    min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+) -> Vector2<i32> {
+    constraints.constrain(widget.layout(constraints, theme, min_sizes))
+}
+
+/// Returns the height a widget needs if it's given the specified width. This is never less than
+/// the height of its min size.
+pub fn widget_height_for_width(
+    widget: &dyn Widget,
+    width: i32,
+    theme: &Theme,
+    min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+) -> i32 {
+    let min_height = min_size_of(widget.id(), min_sizes).y;
+    let constraints =
+        BoxConstraints::new(vec2(width, min_height), vec2(width, BoxConstraints::UNBOUNDED));
+    widget_layout(widget, constraints, theme, min_sizes).y
+}
+
+/// The `Rect`s given to widgets by `Widget::compute_rects` so far.
+///
+/// It also tracks the area that widgets drawn over the rest of the GUI, such as tooltips in a
+/// `Stack`, are kept within: the window, or the visible part of it if an ancestor clips its
+/// children.
+pub struct WidgetRects {
+    rects: FnvHashMap<WidgetId, Rect<i32>>,
+    bounds: Rect<i32>,
+    // Only used when laying out with a `LayoutCache`
+    cache: Option<RectCache>,
+}
+
+impl WidgetRects {
+    pub fn new(bounds: Rect<i32>) -> Self {
+        WidgetRects { rects: collect![], bounds, cache: None }
+    }
+
+    pub fn insert(&mut self, id: WidgetId, rect: Rect<i32>) {
+        self.rects.insert(id, rect);
+    }
+
+    /// Returns the visible area that overlays are kept within.
+    pub fn bounds(&self) -> Rect<i32> {
+        self.bounds
+    }
+
+    /// Calls `f` with the visible area shrunk to `clip_rect`. Widgets that clip their children
+    /// with `Widget::child_clip_rect` should compute their children's `Rect`s within this.
+    pub fn with_bounds(&mut self, clip_rect: Rect<i32>, f: impl FnOnce(&mut Self)) {
+        let bounds = self.bounds;
+        self.bounds = intersect_rects(bounds, clip_rect);
+        f(self);
+        self.bounds = bounds;
+    }
+
+    pub fn into_map(self) -> FnvHashMap<WidgetId, Rect<i32>> {
+        self.rects
+    }
+
+    /// Returns the key that the `Rect`s of a subtree given `rect` are cached by, if they can be.
+    fn cache_key(&self, widget: &dyn Widget, rect: Rect<i32>) -> Option<RectKey> {
+        let hash = *self.cache.as_ref()?.hashes.get(&widget.id())?;
+        Some((hash, rect_corners(rect), rect_corners(self.bounds)))
+    }
+
+    /// Gives a subtree the `Rect`s it was given the last time it was laid out with the same key,
+    /// returning false if it wasn't.
+    fn reuse_cached(&mut self, widget: &dyn Widget, key: RectKey) -> bool {
+        let cache = match &mut self.cache {
+            Some(cache) => cache,
+            None => return false,
+        };
+        let cached = match cache.current.get(&key).cloned().or_else(|| cache.last.remove(&key)) {
+            Some(cached) => cached,
+            None => return false,
+        };
+        let mut cached_rects = cached.rects.iter();
+        let rects = &mut self.rects;
+        for_each_widget(widget, &mut |widget| {
+            if let Some(&Some(rect)) = cached_rects.next() {
+                rects.insert(widget.id(), rect);
+            }
+        });
+        // Keep the subtrees within this one too, in case only part of it changes later
+        for subtree in &cached.subtrees {
+            if let Some(subtree_rects) = cache.last.remove(subtree) {
+                cache.current.insert(*subtree, subtree_rects);
+            }
+        }
+        cache.keys.extend(cached.subtrees.iter().copied());
+        cache.keys.push(key);
+        cache.current.insert(key, cached);
+        true
+    }
+}
+
+/// The subtree hash, `Rect` and overlay bounds that the `Rect`s of a subtree are cached by. Rects
+/// are stored by their corners, since `Rect` can't be hashed.
+type RectKey = (u64, [i32; 4], [i32; 4]);
+
+fn rect_corners(rect: Rect<i32>) -> [i32; 4] {
+    [rect.start.x, rect.start.y, rect.end.x, rect.end.y]
+}
+
+/// The `Rect`s of a subtree, reused by a `LayoutCache` when the subtree is laid out the same way.
+#[derive(Clone)]
+struct CachedRects {
+    /// The `Rect` of each widget in the subtree, in pre-order.
+    rects: Vec<Option<Rect<i32>>>,
+    /// The keys of the cached subtrees within this one.
+    subtrees: Vec<RectKey>,
+}
+
+struct RectCache {
+    /// The hash of each subtree whose widgets all have a `Widget::layout_hash`, by its root's ID.
+    hashes: FnvHashMap<WidgetId, u64>,
+    last: FnvHashMap<RectKey, CachedRects>,
+    current: FnvHashMap<RectKey, CachedRects>,
+    /// The keys added to `current`, in order, so that each subtree can list the ones within it.
+    keys: Vec<RectKey>,
+    /// The number of widgets whose `Widget::compute_rects` was called.
+    computed: usize,
+}
+
+/// Gives a widget and its descendants their `Rect`s, with the widget's at `rect`. Containers
+/// should call this for each child rather than calling `Widget::compute_rects` directly.
+///
+/// When laying out with a `LayoutCache`, this reuses the `Rect`s of subtrees that are the same as
+/// in the last frame and are given the same `Rect`.
+pub fn widget_compute_rects(
+    widget: &dyn Widget,
+    rect: Rect<i32>,
+    theme: &Theme,
+    min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+    widget_rects: &mut WidgetRects,
+) {
+    let key = widget_rects.cache_key(widget, rect);
+    if let Some(key) = key {
+        if widget_rects.reuse_cached(widget, key) {
+            return;
+        }
+    }
+    let first_subtree = widget_rects.cache.as_ref().map_or(0, |cache| cache.keys.len());
+
+    if widget.clips_to_rect() {
+        widget_rects.with_bounds(rect, |widget_rects| {
+            widget.compute_rects(rect, theme, min_sizes, widget_rects)
+        });
+    } else {
+        widget.compute_rects(rect, theme, min_sizes, widget_rects);
+    }
+
+    let WidgetRects { rects, cache, .. } = widget_rects;
+    if let Some(cache) = cache {
+        cache.computed += 1;
+        if let Some(key) = key {
+            let mut subtree_rects = vec![];
+            for_each_widget(widget, &mut |widget| {
+                subtree_rects.push(rects.get(&widget.id()).copied())
+            });
+            let subtrees = cache.keys[first_subtree..].to_vec();
+            cache.current.insert(key, CachedRects { rects: subtree_rects, subtrees });
+            cache.keys.push(key);
+        }
+    }
+}
+
+/// The result of laying out a widget tree.
+pub struct Layout {
+    /// The min size of each widget.
+    pub min_sizes: FnvHashMap<WidgetId, Vector2<i32>>,
+    /// The `Rect` each widget was given.
+    pub widget_rects: FnvHashMap<WidgetId, Rect<i32>>,
+}
+
+/// Statistics about how much of a widget tree had to be laid out again, rather than reusing its
+/// layout from the last frame.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct LayoutStats {
+    /// The number of widgets in the tree.
+    pub widgets: usize,
+    /// The number of widgets whose min size was computed. Min sizes are reused for each subtree
+    /// that hasn't changed.
+    pub min_sizes_computed: usize,
+    /// The number of widgets whose `Rect` was computed. `Rect`s are reused for each subtree that
+    /// hasn't changed and is given the same `Rect` as in the last frame, so a change that moves
+    /// its siblings can mean computing more `Rect`s than min sizes.
+    pub rects_computed: usize,
+}
+
+impl LayoutStats {
+    /// Returns the number of widgets whose min size or `Rect` was computed.
+    pub fn relayout_count(&self) -> usize {
+        self.min_sizes_computed.max(self.rects_computed)
+    }
+}
+
+/// The layout of a widget tree from the last frame, so that the parts of the tree that haven't
+/// changed don't need to be laid out again.
+///
+/// Most widgets get a new ID every frame, so subtrees are matched by the hashes from
+/// `Widget::layout_hash` instead. The min sizes of subtrees that were in the last frame are
+/// reused, and so are the `Rect`s of those that are given the same `Rect` as in the last frame.
+///
+/// The cache is cleared when the window size or the parts of the `Theme` that affect layout
+/// change.
+#[derive(Default)]
+pub struct LayoutCache {
+    /// The min size of each widget in a subtree, in pre-order, by the hash of the subtree.
+    min_sizes: FnvHashMap<u64, Vec<Vector2<i32>>>,
+    /// The `Rect`s of each subtree, by its hash and the `Rect` and bounds it was given.
+    rects: FnvHashMap<RectKey, CachedRects>,
+    /// Min sizes can depend on the window size and theme, so the cache is cleared when either of
+    /// them changes.
+    window_size: Option<Vector2<i32>>,
+    theme_hash: Option<u64>,
+    stats: LayoutStats,
+}
+
+impl LayoutCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Lays out a widget tree like `compute_layout`, reusing what it can from the last time this
+    /// was called.
+    pub fn layout(
+        &mut self,
+        widget: &dyn Widget,
+        theme: &Theme,
+        window_size: Vector2<i32>,
+    ) -> Layout {
+        let theme_hash = theme.layout_hash();
+        if self.window_size != Some(window_size) || self.theme_hash != Some(theme_hash) {
+            self.clear();
+            self.window_size = Some(window_size);
+            self.theme_hash = Some(theme_hash);
+        }
+        self.stats = LayoutStats::default();
+        let mut hashes = collect![];
+        subtree_hash(widget, &mut hashes);
+        for_each_widget(widget, &mut |_| self.stats.widgets += 1);
+
+        // Only subtrees that are still in the tree are kept
+        let mut last_min_sizes = mem::replace(&mut self.min_sizes, collect![]);
+        let mut min_sizes = collect![];
+        self.compute_min_sizes(
+            widget,
+            theme,
+            window_size,
+            &hashes,
+            &mut last_min_sizes,
+            &mut min_sizes,
+        );
+
+        let mut widget_rects = WidgetRects::new(window_rect(window_size));
+        widget_rects.cache = Some(RectCache {
+            hashes,
+            last: mem::replace(&mut self.rects, collect![]),
+            current: collect![],
+            keys: vec![],
+            computed: 0,
+        });
+        compute_rects(widget, theme, &min_sizes, &mut widget_rects);
+        if let Some(cache) = widget_rects.cache.take() {
+            self.rects = cache.current;
+            self.stats.rects_computed = cache.computed;
+        }
+        Layout { min_sizes, widget_rects: widget_rects.into_map() }
+    }
+
+    /// Forgets every cached layout. Changes to the window size and `Theme` are noticed by
+    /// `layout`, so this is only needed if something else that widgets are measured with changes,
+    /// such as a font's fallbacks.
+    pub fn clear(&mut self) {
+        self.min_sizes.clear();
+        self.rects.clear();
+    }
+
+    /// Returns how much of the tree had to be laid out again the last time `layout` was called.
+    pub fn stats(&self) -> LayoutStats {
+        self.stats
+    }
+
+    fn compute_min_sizes(
+        &mut self,
+        widget: &dyn Widget,
+        theme: &Theme,
+        window_size: Vector2<i32>,
+        hashes: &FnvHashMap<WidgetId, u64>,
+        last_min_sizes: &mut FnvHashMap<u64, Vec<Vector2<i32>>>,
+        min_sizes: &mut FnvHashMap<WidgetId, Vector2<i32>>,
+    ) {
+        let hash = hashes.get(&widget.id()).copied();
+        let cached = hash.and_then(|hash| {
+            self.min_sizes.get(&hash).cloned().or_else(|| last_min_sizes.remove(&hash))
+        });
+        if let (Some(hash), Some(cached)) = (hash, cached) {
+            let mut cached_min_sizes = cached.iter();
+            let subtrees = &mut self.min_sizes;
+            for_each_widget(widget, &mut |widget| {
+                if let Some(&min_size) = cached_min_sizes.next() {
+                    min_sizes.insert(widget.id(), min_size);
+                }
+                // Keep the subtrees within this one too, in case only part of it changes later
+                if let Some(subtree_hash) = hashes.get(&widget.id()) {
+                    if let Some(subtree) = last_min_sizes.remove(subtree_hash) {
+                        subtrees.insert(*subtree_hash, subtree);
+                    }
+                }
+            });
+            self.min_sizes.insert(hash, cached);
+            return;
+        }
+
+        for child in widget.children() {
+            self.compute_min_sizes(child, theme, window_size, hashes, last_min_sizes, min_sizes);
+        }
+        let min_size = widget.min_size(theme, min_sizes, window_size);
+        min_sizes.insert(widget.id(), min_size);
+        self.stats.min_sizes_computed += 1;
+        if let Some(hash) = hash {
+            let mut subtree_min_sizes = vec![];
+            for_each_widget(widget, &mut |widget| {
+                subtree_min_sizes.push(min_size_of(widget.id(), min_sizes));
+            });
+            self.min_sizes.insert(hash, subtree_min_sizes);
+        }
+    }
+}
+
+/// A way in which a laid out widget tree breaks the rules of layout, found by `Layout::validate`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LayoutError {
+    /// More than one widget in the tree has this ID.
+    DuplicateId(WidgetId),
+    /// The widget's min size wasn't computed.
+    MissingMinSize(WidgetId),
+    /// The widget wasn't given a `Rect` by its parent's `compute_rects`.
+    MissingRect(WidgetId),
+    /// A widget's `Rect` extends outside of its parent's.
+    ChildOutsideParent {
+        parent: WidgetId,
+        parent_rect: Rect<i32>,
+        child: WidgetId,
+        child_rect: Rect<i32>,
+    },
+}
+
+impl Layout {
+    /// Checks that the layout of a widget tree follows the rules of layout, returning each
+    /// violation found.
+    ///
+    /// The children of widgets that clip their children with `Widget::clips_to_rect` or
+    /// `Widget::child_clip_rect`, such as the contents of a `ScrollArea`, and overlays from
+    /// `Widget::is_overlay` are allowed to extend outside of them.
+    pub fn validate(&self, widget: &dyn Widget) -> Vec<LayoutError> {
+        let mut errors = vec![];
+        let mut ids = FnvHashSet::default();
+        self.validate_widget(widget, &mut ids, &mut errors);
+        errors
+    }
+
+    fn validate_widget(
+        &self,
+        widget: &dyn Widget,
+        ids: &mut FnvHashSet<WidgetId>,
+        errors: &mut Vec<LayoutError>,
+    ) {
+        let id = widget.id();
+        if !ids.insert(id) {
+            errors.push(LayoutError::DuplicateId(id));
+        }
+        if !self.min_sizes.contains_key(&id) {
+            errors.push(LayoutError::MissingMinSize(id));
+        }
+        let rect = self.widget_rects.get(&id).copied();
+        if rect.is_none() {
+            errors.push(LayoutError::MissingRect(id));
+        }
+        let clips_children = rect.and_then(|rect| child_clip_rect(widget, rect)).is_some();
+        for child in widget.children() {
+            let may_overflow = clips_children || widget.is_overlay(child.id());
+            if let (Some(parent_rect), Some(&child_rect), false) =
+                (rect, self.widget_rects.get(&child.id()), may_overflow)
+            {
+                let inside = child_rect.start.x >= parent_rect.start.x
+                    && child_rect.start.y >= parent_rect.start.y
+                    && child_rect.end.x <= parent_rect.end.x
+                    && child_rect.end.y <= parent_rect.end.y;
+                if !inside {
+                    errors.push(LayoutError::ChildOutsideParent {
+                        parent: id,
+                        parent_rect,
+                        child: child.id(),
+                        child_rect,
+                    });
+                }
+            }
+            self.validate_widget(child, ids, errors);
+        }
+    }
+}
+
+/// Lays out a widget tree to fill a window of the given size.
+///
+/// The min size of each widget is computed from the leaves up, and then the root is laid out with
+/// constraints that only allow the window size, and gives each of its children its `Rect`.
+///
+/// This doesn't need a `GlContext`; if `theme.font` was created with `Font::new_headless` or
+/// `Font::new_fixed`, it can be called from tests.
+pub fn compute_layout(widget: &dyn Widget, theme: &Theme, window_size: Vector2<i32>) -> Layout {
+    let mut min_sizes = collect![];
+    compute_widget_min_size(widget, theme, &mut min_sizes, window_size);
+    let mut widget_rects = WidgetRects::new(window_rect(window_size));
+    compute_rects(widget, theme, &min_sizes, &mut widget_rects);
+    Layout { min_sizes, widget_rects: widget_rects.into_map() }
+}
+
+fn window_rect(window_size: Vector2<i32>) -> Rect<i32> {
+    Rect::new(Point2::origin(), Point2::from_vec(window_size))
+}
+
+/// Gives the root of a widget tree the whole window, since it's laid out with constraints that
+/// only allow the window size, and gives its descendants their `Rect`s. Then tells each widget
+/// its layout with `Widget::laid_out`.
+fn compute_rects(
+    widget: &dyn Widget,
+    theme: &Theme,
+    min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+    widget_rects: &mut WidgetRects,
+) {
+    let rect = widget_rects.bounds();
+    widget_compute_rects(widget, rect, theme, min_sizes, widget_rects);
+    let rects = &widget_rects.rects;
+    for_each_widget(widget, &mut |widget| widget.laid_out(rects));
+}
+
+/// Returns the position of a mouse event that has one.
+fn event_pos(event: &Event) -> Option<Point2<i32>> {
+    match *event {
+        Event::MouseDown(_, pos) => Some(pos),
+        Event::MouseUp(_, pos) => Some(pos),
+        Event::MouseMove { pos, .. } => Some(pos),
+        _ => None,
+    }
+}
+
+/// Converts a mouse event's position to be relative to the given rect.
+fn event_relative_to(event: Event, rect: Rect<i32>) -> Event {
+    match event {
+        Event::MouseDown(button, pos) => Event::MouseDown(button, pos - rect.start.to_vec()),
+        Event::MouseUp(button, pos) => Event::MouseUp(button, pos - rect.start.to_vec()),
+        Event::MouseMove { pos, movement } => {
+            Event::MouseMove { pos: pos - rect.start.to_vec(), movement }
+        }
+        event => event,
+    }
+}
+
+/// Returns the rect that a widget's children are clipped to, if any.
+fn child_clip_rect(widget: &dyn Widget, rect: Rect<i32>) -> Option<Rect<i32>> {
+    match (widget.clips_to_rect(), widget.child_clip_rect(rect)) {
+        (true, Some(clip_rect)) => Some(intersect_rects(rect, clip_rect)),
+        (true, None) => Some(rect),
+        (false, clip_rect) => clip_rect,
+    }
+}
+
 fn widget_handle_event(
     widget: &dyn Widget,
     event: &Event,
     widget_rects: &FnvHashMap<WidgetId, Rect<i32>>,
     events_out: &mut FnvHashMap<WidgetId, Vec<Event>>,
     active_component_id: &mut Option<WidgetId>,
-    selectable_components: &FnvHashSet<WidgetId>,
+    cursor_pos: Option<Point2<i32>>,
 ) -> bool {
+    let rect = match widget_rects.get(&widget.id()) {
+        Some(&rect) => rect,
+        None => return false,
+    };
+
+    // Mouse events and scrolling are offered to the children of a component (such as a
+    // `ScrollArea`) before the component itself, so they go to the deepest component under the
+    // pointer and bubble up from there; all other events are offered to the component first.
+    let pos = match event {
+        Event::Scroll(_) => cursor_pos,
+        _ => event_pos(event),
+    };
+    let children_first = pos.is_some();
+    let handle_children = |events_out: &mut _, active_component_id: &mut _| {
+        if let (Some(clip_rect), Some(pos)) = (child_clip_rect(widget, rect), pos) {
+            if !clip_rect.contains_point(pos) {
+                return false;
+            }
+        }
+        for child in widget.children() {
+            if widget_handle_event(
+                child,
+                event,
+                widget_rects,
+                events_out,
+                active_component_id,
+                cursor_pos,
+            ) {
+                return true;
+            }
+        }
+        false
+    };
+
+    if children_first && handle_children(events_out, active_component_id) {
+        return true;
+    }
+
     if widget.is_component() {
-        let rect = widget_rects[&widget.id()];
         let is_active = *active_component_id == Some(widget.id());
 
         let event = event.clone();
@@ -139,26 +876,33 @@ fn widget_handle_event(
                     None
                 }
             }
+            Event::Paste(_) => {
+                if is_active {
+                    Some(event)
+                } else {
+                    None
+                }
+            }
             Event::MouseDown(button, pos) => {
                 if rect.contains_point(pos) {
                     if button == MouseButton::Left {
                         *active_component_id = Some(widget.id());
                     }
-                    Some(Event::MouseDown(button, pos - rect.start.to_vec()))
+                    Some(event_relative_to(event, rect))
                 } else {
                     None
                 }
             }
-            Event::MouseUp(button, pos) => {
+            Event::MouseUp(_, pos) => {
                 if rect.contains_point(pos) {
-                    Some(Event::MouseUp(button, pos - rect.start.to_vec()))
+                    Some(event_relative_to(event, rect))
                 } else {
                     None
                 }
             }
-            Event::MouseMove { pos, movement } => {
+            Event::MouseMove { pos, .. } => {
                 if rect.contains_point(pos) {
-                    Some(Event::MouseMove { pos: pos - rect.start.to_vec(), movement })
+                    Some(event_relative_to(event, rect))
                 } else {
                     None
                 }
@@ -170,29 +914,26 @@ fn widget_handle_event(
             Event::WindowResized(_) => Some(event),
             Event::PointerLocked => None,
             Event::PointerUnlocked => None,
-            Event::Scroll(_) => Some(event),
+            // Scrolling applies to the component under the cursor
+            Event::Scroll(_) => {
+                if pos.map(|pos| rect.contains_point(pos)).unwrap_or(true) {
+                    Some(event)
+                } else {
+                    None
+                }
+            }
         };
         if let Some(event2) = event2 {
-            let events = events_out.entry(widget.id()).or_insert_with(|| vec![]);
+            let events = events_out.entry(widget.id()).or_default();
             events.push(event2);
             return true;
         }
     }
-    for child in widget.children() {
-        if widget_handle_event(
-            child,
-            event,
-            widget_rects,
-            events_out,
-            active_component_id,
-            selectable_components,
-        ) {
-            return true;
-        }
-    }
-    false
+
+    !children_first && handle_children(events_out, active_component_id)
 }
 
+#[allow(clippy::too_many_arguments)]
 fn draw_widget(
     widget: &dyn Widget,
     context: &GlContext,
@@ -203,9 +944,23 @@ fn draw_widget(
     cursor_pos: Option<Point2<i32>>,
     active_widget_id: Option<WidgetId>,
 ) {
-    let rect = widget_rects[&widget.id()];
+    // A widget without a `Rect` is reported by `Layout::validate` rather than drawn
+    let rect = match widget_rects.get(&widget.id()) {
+        Some(&rect) => rect,
+        None => return,
+    };
     let is_active = active_widget_id == Some(widget.id());
+    let clips_to_rect = widget.clips_to_rect();
+    if clips_to_rect {
+        draw_2d.push_clip_rect(rect);
+        theme.font.push_clip_rect(rect);
+    }
     widget.draw(context, surface, rect, theme, draw_2d, cursor_pos, is_active);
+    let clip_rect = widget.child_clip_rect(rect);
+    if let Some(clip_rect) = clip_rect {
+        draw_2d.push_clip_rect(clip_rect);
+        theme.font.push_clip_rect(clip_rect);
+    }
     for child in widget.children() {
         draw_widget(
             child,
@@ -218,10 +973,20 @@ fn draw_widget(
             active_widget_id,
         );
     }
+    if clip_rect.is_some() {
+        draw_2d.pop_clip_rect();
+        theme.font.pop_clip_rect();
+    }
+    if clips_to_rect {
+        draw_2d.pop_clip_rect();
+        theme.font.pop_clip_rect();
+    }
 }
 
 pub struct GuiResult {
     rendered_size: Vector2<i32>,
+    layout_errors: Vec<LayoutError>,
+    layout_stats: LayoutStats,
 }
 
 pub struct GuiEventResult {
@@ -236,6 +1001,18 @@ impl GuiResult {
     pub fn rendered_size(&self) -> Vector2<i32> {
         self.rendered_size
     }
+
+    /// Problems found with the layout of the GUI by `Layout::validate`. This is only checked in
+    /// debug builds, so it's always empty in release builds.
+    pub fn layout_errors(&self) -> &[LayoutError] {
+        &self.layout_errors
+    }
+
+    /// How much of the GUI had to be laid out again, rather than reusing the layout of the last
+    /// frame.
+    pub fn layout_stats(&self) -> LayoutStats {
+        self.layout_stats
+    }
 }
 
 impl GuiEventResult {
@@ -245,13 +1022,13 @@ impl GuiEventResult {
         theme: &Theme,
         component: &mut Box<C>,
     ) -> C::Res {
-        let events = self.component_events.remove(&component.id()).unwrap_or_else(|| vec![]);
+        let events = self.component_events.remove(&component.id()).unwrap_or_default();
         component.update(theme, events)
     }
 
     /// Returns all events that weren't handled by any `Component`.
     pub fn unhandled_events(&mut self) -> Vec<Event> {
-        mem::replace(&mut self.unhandled_events, vec![])
+        mem::take(&mut self.unhandled_events)
     }
 }
 
@@ -259,7 +1036,12 @@ pub struct Gui {
     // None if there are no components
     // The Id is that of the component
     active_component: Option<(i32, WidgetId)>,
+    // The component that the left mouse button was pressed on, if it's still held. It receives
+    // all mouse movement until the button is released, even outside of its `Rect`.
+    pointer_capture: Option<WidgetId>,
+    cursor_pos: Option<Point2<i32>>,
     last_render: Option<RenderedGui>,
+    layout_cache: LayoutCache,
 }
 
 struct RenderedGui {
@@ -269,7 +1051,13 @@ struct RenderedGui {
 
 impl Gui {
     pub fn new() -> Self {
-        Self { active_component: None, last_render: None }
+        Self {
+            active_component: None,
+            pointer_capture: None,
+            cursor_pos: None,
+            last_render: None,
+            layout_cache: LayoutCache::new(),
+        }
     }
 
     /// Draws the GUI.
@@ -282,21 +1070,15 @@ impl Gui {
         cursor_pos: Option<Point2<i32>>,
         widget: Box<dyn Widget>,
     ) -> GuiResult {
-        println!("Computing widget rects");
-        let mut min_sizes = collect![];
-        let mut widget_rects = collect![];
-        compute_widget_min_size(
-            &*widget,
-            context,
-            theme,
-            &mut min_sizes,
-            surface.size().cast().unwrap(),
-        );
-        let rect = Rect::new(Point2::origin(), Point2::from_vec(surface.size().cast().unwrap()));
-        widget.compute_rects(rect, theme, &min_sizes, &mut widget_rects);
+        self.cursor_pos = cursor_pos;
+        let layout = self.layout_cache.layout(&*widget, theme, surface.size().cast().unwrap());
+        let layout_errors = if cfg!(debug_assertions) { layout.validate(&*widget) } else { vec![] };
+        for error in &layout_errors {
+            warn!("Invalid layout: {:?}", error);
+        }
+        let Layout { widget_rects, .. } = layout;
 
         let active_component_id = self.active_component.map(|(_a, b)| b);
-        println!("Drawing main widget");
         draw_widget(
             &*widget,
             context,
@@ -308,14 +1090,24 @@ impl Gui {
             active_component_id,
         );
 
-        let res = GuiResult { rendered_size: widget_rects[&widget.id()].size() };
-
-        println!("Setting last_render");
+        let rendered_size =
+            widget_rects.get(&widget.id()).map_or(Vector2::zero(), |rect| rect.size());
+        let layout_stats = self.layout_cache.stats();
+        let res = GuiResult { rendered_size, layout_errors, layout_stats };
         self.last_render = Some(RenderedGui { widget, widget_rects });
+        res
+    }
 
-        println!("Done drawing GUI");
+    /// Forgets the layout of the last frame, so that the next frame is laid out from scratch. See
+    /// `LayoutCache::clear`.
+    pub fn clear_layout_cache(&mut self) {
+        self.layout_cache.clear();
+    }
 
-        res
+    /// Returns the `Rect` a widget was given when the GUI was last drawn, if it was drawn. This
+    /// can be used to put a widget next to another one with `Positioned::beside`.
+    pub fn widget_rect(&self, id: WidgetId) -> Option<Rect<i32>> {
+        self.last_render.as_ref().and_then(|render| render.widget_rects.get(&id).copied())
     }
 
     /// Handles events by applying them to the most recently rendered output.
@@ -328,7 +1120,7 @@ impl Gui {
         ordered_components: &[WidgetId],
     ) -> GuiEventResult {
         if let Some(RenderedGui { widget, widget_rects }) = &self.last_render {
-            let mut events_out = collect![];
+            let mut events_out: FnvHashMap<WidgetId, Vec<Event>> = collect![];
             let mut unhandled_events = vec![];
             let mut active_component_id = self.active_component.map(|(_a, b)| b);
 
@@ -339,15 +1131,34 @@ impl Gui {
 
             for event in events {
                 let old_active_component_id = active_component_id;
-                if widget_handle_event(
+                match *event {
+                    Event::MouseMove { pos, .. } => self.cursor_pos = Some(pos),
+                    Event::MouseLeave => self.cursor_pos = None,
+                    _ => (),
+                }
+                let captured_rect = match event {
+                    Event::MouseMove { .. } | Event::MouseUp(..) => self
+                        .pointer_capture
+                        .and_then(|id| widget_rects.get(&id).map(|rect| (id, *rect))),
+                    _ => None,
+                };
+                if let Some((captured_id, captured_rect)) = captured_rect {
+                    let events = events_out.entry(captured_id).or_default();
+                    events.push(event_relative_to(event.clone(), captured_rect));
+                } else if widget_handle_event(
                     &**widget,
-                    &event,
-                    &widget_rects,
+                    event,
+                    widget_rects,
                     &mut events_out,
                     &mut active_component_id,
-                    &ordered_components.iter().copied().collect(),
+                    self.cursor_pos,
                 ) {
-                    // continue;
+                    if let Event::MouseDown(MouseButton::Left, _) = event {
+                        self.pointer_capture = active_component_id;
+                    }
+                }
+                if let Event::MouseUp(MouseButton::Left, _) | Event::MouseLeave = event {
+                    self.pointer_capture = None;
                 }
                 if active_component_id != old_active_component_id {
                     let active_component_id = active_component_id.unwrap();
@@ -361,25 +1172,22 @@ impl Gui {
                 if let Some((ref mut active_component_index, ref mut active_component_id)) =
                     &mut self.active_component
                 {
-                    match event {
-                        Event::KeyDown(key) => {
-                            if key.key == "Tab" && !key.shift {
-                                *active_component_index = (*active_component_index + 1)
-                                    % (ordered_components.len() as i32);
-                                *active_component_id =
-                                    ordered_components[*active_component_index as usize];
-                                continue;
-                            } else if key.key == "Tab" && key.shift {
-                                // Workaround for mod_euc not yet being stable
-                                *active_component_index = (*active_component_index - 1
-                                    + ordered_components.len() as i32)
-                                    % (ordered_components.len() as i32);
-                                *active_component_id =
-                                    ordered_components[*active_component_index as usize];
-                                continue;
-                            }
+                    if let Event::KeyDown(key) = event {
+                        if key.key == "Tab" && !key.shift {
+                            *active_component_index =
+                                (*active_component_index + 1) % (ordered_components.len() as i32);
+                            *active_component_id =
+                                ordered_components[*active_component_index as usize];
+                            continue;
+                        } else if key.key == "Tab" && key.shift {
+                            // Workaround for mod_euc not yet being stable
+                            *active_component_index = (*active_component_index - 1
+                                + ordered_components.len() as i32)
+                                % (ordered_components.len() as i32);
+                            *active_component_id =
+                                ordered_components[*active_component_index as usize];
+                            continue;
                         }
-                        _ => (),
                     }
                 }
                 unhandled_events.push(event.clone());
@@ -391,3 +1199,53 @@ impl Gui {
         }
     }
 }
+
+impl Default for Gui {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::widgets::*;
+
+    /// Returns the events each component gets when `event` happens with the cursor at
+    /// `cursor_pos`.
+    fn handle_event(
+        widget: &dyn Widget,
+        event: Event,
+        cursor_pos: Point2<i32>,
+    ) -> FnvHashMap<WidgetId, Vec<Event>> {
+        let theme = Theme::new(Font::new_fixed(8.0, 16));
+        let layout = compute_layout(widget, &theme, vec2(100, 50));
+        let mut events_out = collect![];
+        widget_handle_event(
+            widget,
+            &event,
+            &layout.widget_rects,
+            &mut events_out,
+            &mut None,
+            Some(cursor_pos),
+        );
+        events_out
+    }
+
+    #[test]
+    fn scrolling_goes_to_the_deepest_component_under_the_cursor() {
+        let scroll_area = ScrollArea::new();
+        let text_area = TextArea::new("a", 2);
+        let text_area_id = text_area.id();
+        let content =
+            Col::new().child(0.0, text_area).child(0.0, EmptyWidget::with_size(vec2(0, 100)));
+        let widget = scroll_area.view(content);
+
+        let events_out = handle_event(&*widget, Event::Scroll(1.0), point2(10, 10));
+        assert_eq!(events_out.keys().collect::<Vec<_>>(), vec![&text_area_id]);
+
+        // Past the text area, it bubbles up to the scroll area
+        let events_out = handle_event(&*widget, Event::Scroll(1.0), point2(10, 40));
+        assert_eq!(events_out.keys().collect::<Vec<_>>(), vec![&scroll_area.id()]);
+    }
+}
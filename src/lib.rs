@@ -6,20 +6,36 @@
 #![deny(bare_trait_objects)]
 
 mod assets;
+mod atlas;
+mod clipboard;
 mod color;
 mod draw_2d;
 mod event;
 pub mod gui;
 mod main_loop;
+mod rich_text;
+mod sdf;
+mod shaping;
 mod shader_header;
 mod text;
+mod text_cache;
+mod text_layout;
 pub mod widgets;
 
 pub use crate::assets::*;
+pub use crate::atlas::AtlasStats;
+pub use crate::clipboard::*;
 pub use crate::color::*;
 pub use crate::draw_2d::*;
 pub use crate::event::*;
 pub use crate::gui::*;
 pub use crate::main_loop::*;
+pub use crate::rich_text::*;
 pub use crate::shader_header::*;
-pub use crate::text::Font;
+pub use crate::shaping::{ShapedCluster, ShapedText};
+pub use crate::text::{
+    wrap_text, DecorationMetrics, Font, FontFamily, FontMetrics, SdfEffect, TextAlign,
+    TextDecoration, TextMeasurer, TextOutline, TextRenderer, TextShadow, TextStyle,
+};
+pub use crate::text_cache::TextCacheStats;
+pub use crate::text_layout::*;
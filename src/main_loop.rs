@@ -8,7 +8,7 @@ use std::rc::Rc;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
 use wasm_stopwatch::*;
-use web_sys::{window, KeyboardEvent, MouseEvent, WheelEvent};
+use web_sys::{window, ClipboardEvent, KeyboardEvent, MouseEvent, WheelEvent};
 
 use crate::event::*;
 
@@ -33,6 +33,7 @@ pub struct EventState {
 /// as applications for which `request_animation_frame` isn't the best way to schedule rendering.
 ///
 /// Returns a reference to the `EventState`, though this should never be modified, only read from.
+#[allow(clippy::type_complexity)]
 pub fn setup_event_callbacks(
     canvas_id: &str,
     callback: Box<dyn Fn(Event, &EventState)>,
@@ -92,6 +93,7 @@ pub fn setup_event_callbacks(
     let callback10 = callback.clone();
     let callback11 = callback.clone();
     let callback12 = callback.clone();
+    let callback13 = callback.clone();
 
     let window = window().unwrap();
     let document = window.document().unwrap();
@@ -184,7 +186,7 @@ pub fn setup_event_callbacks(
 
     let mouseleave_handler = Closure::wrap(Box::new(move |_e: MouseEvent| {
         event_state3.borrow_mut().cursor_pos = None;
-        (&mut callback9.borrow_mut())(Event::MouseLeave);
+        callback9.borrow_mut().deref_mut()(Event::MouseLeave);
     }) as Box<dyn FnMut(MouseEvent)>);
     canvas
         .add_event_listener_with_callback("mouseleave", mouseleave_handler.as_ref().unchecked_ref())
@@ -192,7 +194,7 @@ pub fn setup_event_callbacks(
     mouseleave_handler.forget();
 
     let resize_handler = Closure::wrap(Box::new(move || {
-        (&mut callback10.borrow_mut())(Event::WindowResized(get_window_size()));
+        callback10.borrow_mut().deref_mut()(Event::WindowResized(get_window_size()));
     }) as Box<dyn FnMut()>);
     window
         .add_event_listener_with_callback("resize", resize_handler.as_ref().unchecked_ref())
@@ -200,7 +202,7 @@ pub fn setup_event_callbacks(
     resize_handler.forget();
 
     let pointer_lock_change_handler = Closure::wrap(Box::new(move || {
-        (&mut callback11.borrow_mut())(if document2.pointer_lock_element().is_some() {
+        callback11.borrow_mut().deref_mut()(if document2.pointer_lock_element().is_some() {
             Event::PointerLocked
         } else {
             Event::PointerUnlocked
@@ -224,6 +226,16 @@ pub fn setup_event_callbacks(
         .unwrap();
     wheel_handler.forget();
 
+    let paste_handler = Closure::wrap(Box::new(move |e: ClipboardEvent| {
+        if let Some(text) = e.clipboard_data().and_then(|data| data.get_data("text").ok()) {
+            callback13.borrow_mut().deref_mut()(Event::Paste(text));
+        }
+    }) as Box<dyn FnMut(ClipboardEvent)>);
+    document
+        .add_event_listener_with_callback("paste", paste_handler.as_ref().unchecked_ref())
+        .unwrap();
+    paste_handler.forget();
+
     event_state4
 }
 
@@ -282,7 +294,7 @@ pub fn start_main_loop(canvas_id: &str, app: Box<dyn App>) {
     *closure.borrow_mut() = Some(Closure::wrap(Box::new(move || {
         let mut queued_events = queued_events2.borrow_mut();
         let event_state = event_state.borrow_mut();
-        let events = std::mem::replace(&mut *queued_events, vec![]);
+        let events = std::mem::take(&mut *queued_events);
         let dt = stopwatch.get_time();
         stopwatch.reset();
         app3.borrow_mut().render_frame(events, &event_state, dt);
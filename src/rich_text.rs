@@ -0,0 +1,314 @@
+use std::ops::Range;
+
+use crate::color::*;
+use crate::text::*;
+
+/// A run of text with a single style.
+#[derive(Clone)]
+pub struct Span {
+    pub text: String,
+    /// If this is `None`, the default color of whatever draws the text is used.
+    pub color: Option<Color4>,
+    /// If this is `None`, the default font is used. The size of the text is the size of its font;
+    /// `Font::with_size` creates fonts of other sizes that are rendered together.
+    pub font: Option<Font>,
+    pub underline: bool,
+    pub strikethrough: bool,
+}
+
+impl Span {
+    pub fn new(text: &str) -> Self {
+        Span {
+            text: text.to_owned(),
+            color: None,
+            font: None,
+            underline: false,
+            strikethrough: false,
+        }
+    }
+
+    pub fn color(mut self, color: Color4) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    pub fn font(mut self, font: &Font) -> Self {
+        self.font = Some(font.clone());
+        self
+    }
+
+    pub fn underline(mut self) -> Self {
+        self.underline = true;
+        self
+    }
+
+    pub fn strikethrough(mut self) -> Self {
+        self.strikethrough = true;
+        self
+    }
+}
+
+/// Text made up of spans with different colors, fonts and decorations.
+#[derive(Clone, Default)]
+pub struct RichText {
+    pub spans: Vec<Span>,
+}
+
+impl RichText {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn span(mut self, span: Span) -> Self {
+        self.spans.push(span);
+        self
+    }
+
+    /// Appends a span that uses the default style.
+    pub fn text(self, text: &str) -> Self {
+        self.span(Span::new(text))
+    }
+
+    /// Returns the font used by a span.
+    pub fn span_font<'a>(&'a self, span: usize, default_font: &'a Font) -> &'a Font {
+        self.spans[span].font.as_ref().unwrap_or(default_font)
+    }
+
+    /// Splits the text into lines that are at most `max_width` pixels wide, breaking at whitespace
+    /// where possible, and always breaking at '\n'. If `max_width` is `None`, lines are only broken
+    /// at '\n'.
+    ///
+    /// Unlike `wrap_text`, words that are too wide to fit on a line aren't broken.
+    pub fn layout(&self, default_font: &Font, max_width: Option<f32>) -> Vec<RichTextLine> {
+        let mut lines = vec![];
+        let mut line = RichTextLine::new();
+        let mut x = 0.0;
+        // True if the line can be broken before the next piece of text
+        let mut can_break = false;
+        // The index of the fragment that starts the current word, which may continue across spans
+        let mut word_start = 0;
+        for (span_index, span) in self.spans.iter().enumerate() {
+            let font = self.span_font(span_index, default_font);
+            for (i, paragraph) in split_lines(&span.text).into_iter().enumerate() {
+                if i > 0 {
+                    lines.push(line.finish(default_font));
+                    line = RichTextLine::new();
+                    x = 0.0;
+                    can_break = false;
+                    word_start = 0;
+                }
+                for word in split_words(&span.text, paragraph) {
+                    if can_break {
+                        word_start = line.fragments.len();
+                    }
+                    let text = &span.text[word.clone()];
+                    let visible_width = advance_width(font, text.trim_end(), x);
+                    let too_wide = max_width.map(|max| x + visible_width > max).unwrap_or(false);
+                    if too_wide && word_start > 0 {
+                        // Move the whole word to the next line, including the parts of it in
+                        // earlier spans
+                        let fragments = std::mem::take(&mut line.fragments);
+                        let word_x =
+                            fragments.get(word_start).map(|fragment| fragment.x).unwrap_or(x);
+                        let mut next_line = RichTextLine::new();
+                        line = RichTextLine::new();
+                        for (i, mut fragment) in fragments.into_iter().enumerate() {
+                            let font = self.span_font(fragment.span, default_font);
+                            if i < word_start {
+                                line.add_fragment(fragment, font);
+                            } else {
+                                // Only the last fragment of a word can contain whitespace, so
+                                // the moved ones don't contain tabs and keep their widths
+                                fragment.x -= word_x;
+                                next_line.add_fragment(fragment, font);
+                            }
+                        }
+                        lines.push(line.finish(default_font));
+                        line = next_line;
+                        x -= word_x;
+                        word_start = 0;
+                    }
+                    // Tab stops are relative to the start of the line, not the span
+                    let width = advance_width(font, text, x);
+                    let visible_width = advance_width(font, text.trim_end(), x);
+                    line.add_fragment(
+                        RichTextFragment { span: span_index, range: word, x, width: visible_width },
+                        font,
+                    );
+                    x += width;
+                    can_break = text.ends_with(char::is_whitespace);
+                }
+            }
+        }
+        lines.push(line.finish(default_font));
+        lines
+    }
+}
+
+impl From<&str> for RichText {
+    fn from(text: &str) -> Self {
+        RichText::new().text(text)
+    }
+}
+
+/// A line of laid out `RichText`.
+#[derive(Clone, Debug)]
+pub struct RichTextLine {
+    pub fragments: Vec<RichTextFragment>,
+    /// The distance from the top of the line to the baseline shared by all of its fragments.
+    pub ascent: f32,
+    pub height: i32,
+}
+
+/// Part of a `RichTextLine` drawn with a single span's style.
+#[derive(Clone, Debug)]
+pub struct RichTextFragment {
+    /// The index of the span this is part of.
+    pub span: usize,
+    /// The byte range of the span's text.
+    pub range: Range<usize>,
+    /// The distance from the start of the line.
+    pub x: f32,
+    /// The width, not including trailing whitespace.
+    pub width: f32,
+}
+
+impl RichTextLine {
+    fn new() -> Self {
+        RichTextLine { fragments: vec![], ascent: 0.0, height: 0 }
+    }
+
+    fn add_fragment(&mut self, fragment: RichTextFragment, font: &Font) {
+        let descent = self.height as f32 - self.ascent;
+        let font_descent = font.advance_y() as f32 - font.ascent();
+        self.ascent = self.ascent.max(font.ascent());
+        self.height = (self.ascent + descent.max(font_descent)).ceil() as i32;
+        self.fragments.push(fragment);
+    }
+
+    /// Gives empty lines the height of the default font.
+    fn finish(mut self, default_font: &Font) -> Self {
+        if self.fragments.is_empty() {
+            self.ascent = default_font.ascent();
+            self.height = default_font.advance_y();
+        }
+        self
+    }
+
+    /// Returns the width of the line, not including trailing whitespace.
+    pub fn width(&self) -> f32 {
+        self.fragments.last().map(|x| x.x + x.width).unwrap_or(0.0)
+    }
+}
+
+/// Returns the width of text that starts `x` pixels from the start of the line.
+fn advance_width(font: &Font, text: &str, x: f32) -> f32 {
+    let mut end = x;
+    for (i, piece) in text.split('\t').enumerate() {
+        if i > 0 {
+            end = font.next_tab_stop(end);
+        }
+        end += font.string_width(piece);
+    }
+    end - x
+}
+
+/// Splits text at '\n', returning the byte range of each line without the '\n'.
+fn split_lines(text: &str) -> Vec<Range<usize>> {
+    let mut start = 0;
+    text.split('\n')
+        .map(|line| {
+            let range = start..start + line.len();
+            start = range.end + 1;
+            range
+        })
+        .collect()
+}
+
+/// Splits a range of text into words, each including the whitespace after it.
+fn split_words(text: &str, range: Range<usize>) -> Vec<Range<usize>> {
+    let mut words = vec![];
+    let mut word_start = range.start;
+    let mut prev_whitespace = false;
+    for (i, c) in text[range.clone()].char_indices() {
+        let i = range.start + i;
+        if prev_whitespace && !c.is_whitespace() {
+            words.push(word_start..i);
+            word_start = i;
+        }
+        prev_whitespace = c.is_whitespace();
+    }
+    if word_start < range.end {
+        words.push(word_start..range.end);
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tab_stops_are_relative_to_the_line() {
+        let font = Font::new_fixed(8.0, 16);
+        let text = RichText::new().text("abc").text("\tx");
+        let lines = text.layout(&font, None);
+        // The tab after "abc" ends at the first stop, 8 spaces from the start of the line
+        assert_eq!(lines[0].fragments.last().unwrap().x, 64.0);
+        assert_eq!(lines[0].width(), 72.0);
+    }
+
+    fn fragments<'a>(text: &'a RichText, line: &RichTextLine) -> Vec<(&'a str, f32)> {
+        line.fragments
+            .iter()
+            .map(|fragment| (&text.spans[fragment.span].text[fragment.range.clone()], fragment.x))
+            .collect()
+    }
+
+    #[test]
+    fn fonts_on_a_line_share_a_baseline() {
+        let font = Font::new_fixed(8.0, 16);
+        let big = Font::new_fixed(16.0, 32);
+        let text = RichText::new().text("ab ").span(Span::new("CD").font(&big)).text(" ef\ngh");
+        let lines = text.layout(&font, None);
+        assert_eq!(
+            fragments(&text, &lines[0]),
+            vec![("ab ", 0.0), ("CD", 24.0), (" ", 56.0), ("ef", 64.0)]
+        );
+        // The big font has the larger ascent and descent
+        assert_eq!((lines[0].ascent, lines[0].height), (big.ascent(), 32));
+        assert_eq!((lines[1].ascent, lines[1].height), (font.ascent(), 16));
+    }
+
+    #[test]
+    fn lines_wrap_between_spans() {
+        let font = Font::new_fixed(8.0, 16);
+        let big = Font::new_fixed(16.0, 32);
+        let text = RichText::new().text("abc ").span(Span::new("de ").font(&big)).text("g");
+        let lines = text.layout(&font, Some(60.0));
+        assert_eq!(lines.len(), 2);
+        assert_eq!(fragments(&text, &lines[0]), vec![("abc ", 0.0)]);
+        assert_eq!(fragments(&text, &lines[1]), vec![("de ", 0.0), ("g", 48.0)]);
+        assert_eq!(lines[0].height, 16);
+        assert_eq!((lines[1].ascent, lines[1].height), (big.ascent(), 32));
+    }
+
+    #[test]
+    fn words_across_spans_wrap_together() {
+        let font = Font::new_fixed(8.0, 16);
+        let big = Font::new_fixed(16.0, 32);
+        let text = RichText::new().text("ab cd").span(Span::new("EF gh").font(&big));
+        let lines = text.layout(&font, Some(48.0));
+        assert_eq!(fragments(&text, &lines[0]), vec![("ab ", 0.0)]);
+        assert_eq!(fragments(&text, &lines[1]), vec![("cd", 0.0), ("EF ", 16.0)]);
+        assert_eq!(fragments(&text, &lines[2]), vec![("gh", 0.0)]);
+        // The first line no longer contains any of the big font
+        assert_eq!((lines[0].ascent, lines[0].height), (font.ascent(), 16));
+        assert_eq!(lines[1].height, 32);
+
+        // Without room to move the word, it isn't broken
+        let lines =
+            RichText::new().text("ab").span(Span::new("CD").font(&big)).layout(&font, Some(8.0));
+        assert_eq!(lines.len(), 1);
+    }
+}
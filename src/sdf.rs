@@ -0,0 +1,173 @@
+use cgmath::*;
+use rusttype::{Contour, Segment};
+
+/// The number of line segments each curve is split into.
+const CURVE_STEPS: usize = 8;
+
+/// A signed distance field of a glyph.
+pub(crate) struct SdfBitmap {
+    /// One byte per pixel. 128 is the edge of the glyph, higher values are inside it, and lower
+    /// values are outside it. Values change by 128 over `range` pixels.
+    pub data: Vec<u8>,
+    pub size: Vector2<u32>,
+    /// The position of the top-left of the bitmap relative to the glyph's origin.
+    pub left: i32,
+    pub top: i32,
+}
+
+/// Computes a signed distance field from a glyph's outline, with `range` pixels of padding around
+/// it. Returns `None` if the outline is empty.
+///
+/// The outline must be in pixels, with the y axis pointing up, as returned by
+/// `rusttype::ScaledGlyph::shape`.
+pub(crate) fn generate_sdf(contours: &[Contour], range: f32) -> Option<SdfBitmap> {
+    let edges = flatten(contours);
+    if edges.is_empty() {
+        return None;
+    }
+
+    let (mut min, mut max) = (edges[0].0, edges[0].0);
+    for &(a, _) in &edges {
+        min = point2(min.x.min(a.x), min.y.min(a.y));
+        max = point2(max.x.max(a.x), max.y.max(a.y));
+    }
+    let padding = range.ceil() as i32;
+    let left = min.x.floor() as i32 - padding;
+    let top = min.y.floor() as i32 - padding;
+    let size = vec2(
+        (max.x.ceil() as i32 + padding - left) as u32,
+        (max.y.ceil() as i32 + padding - top) as u32,
+    );
+
+    let mut data = Vec::with_capacity((size.x * size.y) as usize);
+    for y in 0..size.y {
+        for x in 0..size.x {
+            let p = point2((left + x as i32) as f32 + 0.5, (top + y as i32) as f32 + 0.5);
+            let mut min_dist2 = f32::INFINITY;
+            let mut winding = 0;
+            for &(a, b) in &edges {
+                min_dist2 = min_dist2.min(dist2_to_segment(p, a, b));
+                // Count crossings of a ray to the right of the point, using the nonzero rule
+                if (a.y <= p.y) != (b.y <= p.y) {
+                    let cross_x = a.x + (p.y - a.y) / (b.y - a.y) * (b.x - a.x);
+                    if cross_x > p.x {
+                        winding += if b.y > a.y { 1 } else { -1 };
+                    }
+                }
+            }
+            let dist = if winding != 0 { min_dist2.sqrt() } else { -min_dist2.sqrt() };
+            let value = (0.5 + dist / (2.0 * range)).clamp(0.0, 1.0);
+            data.push((value * 255.0).round() as u8);
+        }
+    }
+
+    Some(SdfBitmap { data, size, left, top })
+}
+
+/// Converts contours to line segments, flipping them so the y axis points down.
+fn flatten(contours: &[Contour]) -> Vec<(Point2<f32>, Point2<f32>)> {
+    let to_point = |p: rusttype::Point<f32>| point2(p.x, -p.y);
+    let mut edges = vec![];
+    for contour in contours {
+        for segment in &contour.segments {
+            match segment {
+                Segment::Line(line) => edges.push((to_point(line.p[0]), to_point(line.p[1]))),
+                Segment::Curve(curve) => {
+                    let [a, control, b] = [
+                        to_point(curve.p[0]).to_vec(),
+                        to_point(curve.p[1]).to_vec(),
+                        to_point(curve.p[2]).to_vec(),
+                    ];
+                    let mut prev = a;
+                    for i in 1..=CURVE_STEPS {
+                        let t = i as f32 / CURVE_STEPS as f32;
+                        let next =
+                            a * (1.0 - t) * (1.0 - t) + control * 2.0 * t * (1.0 - t) + b * t * t;
+                        edges.push((Point2::from_vec(prev), Point2::from_vec(next)));
+                        prev = next;
+                    }
+                }
+            }
+        }
+    }
+    edges
+}
+
+fn dist2_to_segment(p: Point2<f32>, a: Point2<f32>, b: Point2<f32>) -> f32 {
+    let ab = b - a;
+    let len2 = ab.magnitude2();
+    let t = if len2 == 0.0 { 0.0 } else { ((p - a).dot(ab) / len2).clamp(0.0, 1.0) };
+    (a + ab * t - p).magnitude2()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusttype::{point, Curve, Line};
+
+    /// A contour through the given points, with the y axis pointing up.
+    fn polygon(points: &[(f32, f32)]) -> Contour {
+        let segments = (0..points.len())
+            .map(|i| {
+                let (a, b) = (points[i], points[(i + 1) % points.len()]);
+                Segment::Line(Line { p: [point(a.0, a.1), point(b.0, b.1)] })
+            })
+            .collect();
+        Contour { segments }
+    }
+
+    #[test]
+    fn square_is_positive_inside_and_negative_outside() {
+        let square = polygon(&[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)]);
+        let sdf = generate_sdf(&[square], 4.0).unwrap();
+        // The square covers x in 0..10 and y in -10..0 after flipping, padded by 4 pixels
+        assert_eq!((sdf.left, sdf.top), (-4, -14));
+        assert_eq!(sdf.size, vec2(18, 18));
+
+        let value = |x: i32, y: i32| {
+            let (x, y) = ((x - sdf.left) as u32, (y - sdf.top) as u32);
+            sdf.data[(y * sdf.size.x + x) as usize]
+        };
+        assert_eq!(value(5, -5), 255);
+        assert_eq!(value(-4, -14), 0);
+        assert_eq!(value(13, -14), 0);
+        assert_eq!(value(13, -5), 16);
+        // The pixels on either side of the left edge are half a pixel from it, so they average 128
+        let (inside, outside) = (value(0, -5), value(-1, -5));
+        assert_eq!((inside, outside), (143, 112));
+        assert_eq!(inside as i32 + outside as i32, 255);
+    }
+
+    #[test]
+    fn empty_outlines_have_no_sdf() {
+        assert!(generate_sdf(&[], 4.0).is_none());
+        assert!(generate_sdf(&[Contour { segments: vec![] }], 4.0).is_none());
+    }
+
+    #[test]
+    fn flattening_flips_y_and_splits_curves() {
+        let contour = Contour {
+            segments: vec![
+                Segment::Line(Line { p: [point(0.0, 0.0), point(4.0, 2.0)] }),
+                Segment::Curve(Curve { p: [point(4.0, 2.0), point(8.0, 2.0), point(8.0, 6.0)] }),
+            ],
+        };
+        let edges = flatten(&[contour]);
+        assert_eq!(edges.len(), 1 + CURVE_STEPS);
+        assert_eq!(edges[0], (point2(0.0, 0.0), point2(4.0, -2.0)));
+        assert_eq!(edges[1].0, point2(4.0, -2.0));
+        assert_eq!(edges[CURVE_STEPS].1, point2(8.0, -6.0));
+        for pair in edges.windows(2) {
+            assert_eq!(pair[0].1, pair[1].0);
+        }
+    }
+
+    #[test]
+    fn distance_is_to_the_closest_point_of_the_segment() {
+        let (a, b) = (point2(0.0, 0.0), point2(4.0, 0.0));
+        assert_eq!(dist2_to_segment(point2(2.0, 3.0), a, b), 9.0);
+        assert_eq!(dist2_to_segment(point2(7.0, 4.0), a, b), 25.0);
+        assert_eq!(dist2_to_segment(point2(-1.0, 0.0), a, b), 1.0);
+        assert_eq!(dist2_to_segment(point2(1.0, 1.0), a, a), 2.0);
+    }
+}
@@ -0,0 +1,350 @@
+use cgmath::*;
+use rusttype::GlyphId;
+use std::ops::Range;
+
+#[cfg(feature = "shaping")]
+use crate::text::*;
+
+/// A glyph positioned by shaping.
+#[derive(Clone, Debug)]
+pub(crate) struct ShapedGlyph {
+    /// The index of the font the glyph is from, out of a font and its fallbacks.
+    pub face: usize,
+    pub id: GlyphId,
+    /// The position of the glyph's origin relative to the start of the text's baseline.
+    pub pos: Vector2<f32>,
+}
+
+/// A group of characters that are drawn as a unit, such as a grapheme cluster or a ligature. The
+/// caret can only be placed at the edges of a cluster.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShapedCluster {
+    /// The byte range of the characters in the text.
+    pub range: Range<usize>,
+    /// The position of the left edge of the cluster, relative to the start of the text.
+    pub x: f32,
+    pub width: f32,
+    /// True if the cluster is part of right-to-left text, so its logical start is its right edge.
+    pub rtl: bool,
+}
+
+impl ShapedCluster {
+    /// Returns the x coordinate of the logical start of the cluster.
+    fn leading_x(&self) -> f32 {
+        if self.rtl {
+            self.x + self.width
+        } else {
+            self.x
+        }
+    }
+
+    /// Returns the x coordinate of the logical end of the cluster.
+    fn trailing_x(&self) -> f32 {
+        if self.rtl {
+            self.x
+        } else {
+            self.x + self.width
+        }
+    }
+}
+
+/// Text that has been converted to positioned glyphs by `Font::shape`.
+///
+/// With the `shaping` feature, text is reordered with the Unicode bidirectional algorithm and
+/// shaped with `rustybuzz`, so scripts such as Arabic and Devanagari are drawn correctly. Without
+/// it, each character is drawn with its own glyph from left to right, and clusters are grapheme
+/// clusters.
+#[derive(Clone, Debug)]
+pub struct ShapedText {
+    pub(crate) glyphs: Vec<ShapedGlyph>,
+    /// The clusters in visual order, from left to right.
+    pub clusters: Vec<ShapedCluster>,
+    pub width: f32,
+}
+
+impl ShapedText {
+    pub(crate) fn new() -> Self {
+        ShapedText { glyphs: vec![], clusters: vec![], width: 0.0 }
+    }
+
+    /// Returns the x coordinate of a caret before byte index `pos` of the text, relative to the
+    /// start of the text. If `pos` is the end of the text, the caret is after the last cluster.
+    pub fn caret_x(&self, pos: usize) -> f32 {
+        if let Some(cluster) = self.clusters.iter().find(|x| x.range.contains(&pos)) {
+            cluster.leading_x()
+        } else if let Some(cluster) = self.clusters.iter().max_by_key(|x| x.range.end) {
+            cluster.trailing_x()
+        } else {
+            0.0
+        }
+    }
+
+    /// Returns the caret position closest to `x`, relative to the start of the text.
+    pub fn pos_at_x(&self, x: f32) -> usize {
+        self.clusters
+            .iter()
+            .flat_map(|cluster| {
+                vec![
+                    (cluster.leading_x(), cluster.range.start),
+                    (cluster.trailing_x(), cluster.range.end),
+                ]
+            })
+            .min_by(|(a, _), (b, _)| (a - x).abs().partial_cmp(&(b - x).abs()).unwrap())
+            .map(|(_, pos)| pos)
+            .unwrap_or(0)
+    }
+
+    /// Returns the caret position one cluster to the left or right of `pos` on screen, or `None`
+    /// if the caret is already at that edge of the text. In right-to-left text, moving right moves
+    /// backwards through the text.
+    pub fn visual_neighbor(&self, pos: usize, right: bool) -> Option<usize> {
+        let x = self.caret_x(pos);
+        self.clusters
+            .iter()
+            .flat_map(|cluster| {
+                vec![
+                    (cluster.leading_x(), cluster.range.start),
+                    (cluster.trailing_x(), cluster.range.end),
+                ]
+            })
+            // Where the directions of the text change, two positions share an edge, but the caret
+            // is only drawn there for one of them
+            .filter(|&(stop_x, stop)| {
+                (if right { stop_x > x } else { stop_x < x }) && self.caret_x(stop) == stop_x
+            })
+            .min_by(|(a, _), (b, _)| (a - x).abs().partial_cmp(&(b - x).abs()).unwrap())
+            .map(|(_, stop)| stop)
+    }
+
+    /// Returns the start of the cluster before `pos` in logical order, or 0 if there isn't one.
+    pub fn prev_cluster_boundary(&self, pos: usize) -> usize {
+        self.clusters
+            .iter()
+            .map(|cluster| cluster.range.start)
+            .filter(|&start| start < pos)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Returns the end of the cluster after `pos` in logical order, or `pos` if there isn't one.
+    pub fn next_cluster_boundary(&self, pos: usize) -> usize {
+        self.clusters
+            .iter()
+            .map(|cluster| cluster.range.end)
+            .filter(|&end| end > pos)
+            .min()
+            .unwrap_or(pos)
+    }
+
+    /// Returns the horizontal spans covered by the clusters in a byte range of the text, from left
+    /// to right. Bidirectional text can need more than one span.
+    pub fn range_spans(&self, range: Range<usize>) -> Vec<Range<f32>> {
+        let mut spans: Vec<Range<f32>> = vec![];
+        for cluster in &self.clusters {
+            if cluster.range.start < range.start || cluster.range.end > range.end {
+                continue;
+            }
+            match spans.last_mut() {
+                Some(span) if span.end == cluster.x => span.end = cluster.x + cluster.width,
+                _ => spans.push(cluster.x..cluster.x + cluster.width),
+            }
+        }
+        spans
+    }
+}
+
+/// Reorders text with the Unicode bidirectional algorithm and shapes each run with `rustybuzz`.
+#[cfg(feature = "shaping")]
+pub(crate) fn shape_complex(metrics: &FontMetrics, text: &str) -> ShapedText {
+    use rustybuzz::{Direction, UnicodeBuffer};
+    use unicode_bidi::BidiInfo;
+
+    let mut shaped = ShapedText::new();
+    let bidi = BidiInfo::new(text, None);
+    for paragraph in &bidi.paragraphs {
+        let (levels, runs) = bidi.visual_runs(paragraph, paragraph.range.clone());
+        for run in runs {
+            let rtl = levels[run.start].is_rtl();
+            let mut pieces = face_runs(metrics, text, run);
+            if rtl {
+                pieces.reverse();
+            }
+            for (face_index, range) in pieces {
+                let face = &metrics.faces()[face_index];
+                // Parsing a face is cheap, and shaped text is cached by `Font::layout`
+                let rb_face = match rustybuzz::Face::from_slice(&face.data, 0) {
+                    Some(rb_face) => rb_face,
+                    None => {
+                        // The text is still drawn, with a glyph for each character
+                        append_simple(&mut shaped, metrics, text, range);
+                        continue;
+                    }
+                };
+                // rusttype scales fonts so that the ascent minus the descent is the font size
+                let v_metrics = face.font.v_metrics_unscaled();
+                let scale = metrics.size() as f32 / (v_metrics.ascent - v_metrics.descent);
+
+                let mut buffer = UnicodeBuffer::new();
+                buffer.push_str(&text[range.clone()]);
+                buffer.set_direction(if rtl {
+                    Direction::RightToLeft
+                } else {
+                    Direction::LeftToRight
+                });
+                buffer.guess_segment_properties();
+                let output = rustybuzz::shape(&rb_face, &[], buffer);
+
+                // The start of each cluster in logical order, to find where each one ends
+                let mut starts: Vec<usize> = output
+                    .glyph_infos()
+                    .iter()
+                    .map(|info| range.start + info.cluster as usize)
+                    .collect();
+                starts.sort_unstable();
+                starts.dedup();
+
+                let first_cluster = shaped.clusters.len();
+                for (info, pos) in output.glyph_infos().iter().zip(output.glyph_positions()) {
+                    let start = range.start + info.cluster as usize;
+                    let is_tab = text[start..].starts_with('\t');
+                    let (id, advance) = if is_tab {
+                        // Fonts often don't have a glyph for tabs, and a missing glyph is drawn
+                        // as a box
+                        let id = face.font.glyph(' ').id();
+                        (id, metrics.next_tab_stop(shaped.width) - shaped.width)
+                    } else {
+                        (GlyphId(info.glyph_id), pos.x_advance as f32 * scale)
+                    };
+                    shaped.glyphs.push(ShapedGlyph {
+                        face: face_index,
+                        id,
+                        pos: vec2(
+                            shaped.width + pos.x_offset as f32 * scale,
+                            -pos.y_offset as f32 * scale,
+                        ),
+                    });
+                    // Clusters from earlier pieces can't be extended
+                    let piece_clusters = &mut shaped.clusters[first_cluster..];
+                    match piece_clusters.last_mut() {
+                        Some(cluster) if cluster.range.start == start => cluster.width += advance,
+                        _ => {
+                            let end = match starts.binary_search(&start) {
+                                Ok(i) => starts.get(i + 1).copied().unwrap_or(range.end),
+                                Err(_) => range.end,
+                            };
+                            shaped.clusters.push(ShapedCluster {
+                                range: start..end,
+                                x: shaped.width,
+                                width: advance,
+                                rtl,
+                            });
+                        }
+                    }
+                    shaped.width += advance;
+                }
+            }
+        }
+    }
+    shaped
+}
+
+/// Adds a piece of text that the shaper can't shape to the end of `shaped`, with each character
+/// drawn with its own glyph from left to right.
+#[cfg(feature = "shaping")]
+fn append_simple(shaped: &mut ShapedText, metrics: &FontMetrics, text: &str, range: Range<usize>) {
+    let piece = metrics.shape_simple_from(&text[range.clone()], shaped.width);
+    shaped.glyphs.extend(piece.glyphs);
+    shaped.clusters.extend(piece.clusters.into_iter().map(|cluster| ShapedCluster {
+        range: range.start + cluster.range.start..range.start + cluster.range.end,
+        ..cluster
+    }));
+    shaped.width = piece.width;
+}
+
+/// Splits a range of text into pieces that are each drawn from a single font, in logical order.
+/// Each grapheme cluster is drawn from the font that has its first character.
+#[cfg(feature = "shaping")]
+fn face_runs(metrics: &FontMetrics, text: &str, range: Range<usize>) -> Vec<(usize, Range<usize>)> {
+    use unicode_segmentation::UnicodeSegmentation;
+
+    let mut pieces: Vec<(usize, Range<usize>)> = vec![];
+    for (i, grapheme) in text[range.clone()].grapheme_indices(true) {
+        let start = range.start + i;
+        let end = start + grapheme.len();
+        let face = metrics.face_index(grapheme.chars().next().unwrap());
+        match pieces.last_mut() {
+            Some((prev_face, prev_range)) if *prev_face == face => prev_range.end = end,
+            _ => pieces.push((face, start..end)),
+        }
+    }
+    pieces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cluster(range: Range<usize>, x: f32, rtl: bool) -> ShapedCluster {
+        ShapedCluster { range, x, width: 10.0, rtl }
+    }
+
+    /// "abאב", where the Hebrew letters are drawn from right to left after the Latin ones.
+    fn mixed_text() -> ShapedText {
+        ShapedText {
+            glyphs: vec![],
+            clusters: vec![
+                cluster(0..1, 0.0, false),
+                cluster(1..2, 10.0, false),
+                cluster(4..6, 20.0, true),
+                cluster(2..4, 30.0, true),
+            ],
+            width: 40.0,
+        }
+    }
+
+    #[test]
+    fn visual_neighbor_moves_across_direction_changes() {
+        let shaped = mixed_text();
+        let mut pos = 0;
+        let mut right = vec![];
+        while let Some(next) = shaped.visual_neighbor(pos, true) {
+            pos = next;
+            right.push((pos, shaped.caret_x(pos)));
+        }
+        assert_eq!(right, vec![(1, 10.0), (6, 20.0), (4, 30.0), (2, 40.0)]);
+
+        let mut left = vec![];
+        while let Some(next) = shaped.visual_neighbor(pos, false) {
+            pos = next;
+            left.push(pos);
+        }
+        assert_eq!(left, vec![4, 6, 1, 0]);
+    }
+
+    #[test]
+    fn cluster_boundaries_are_logical() {
+        let shaped = mixed_text();
+        assert_eq!(shaped.prev_cluster_boundary(6), 4);
+        assert_eq!(shaped.prev_cluster_boundary(2), 1);
+        assert_eq!(shaped.prev_cluster_boundary(0), 0);
+        assert_eq!(shaped.next_cluster_boundary(1), 2);
+        assert_eq!(shaped.next_cluster_boundary(2), 4);
+        assert_eq!(shaped.next_cluster_boundary(6), 6);
+    }
+
+    #[cfg(feature = "shaping")]
+    #[test]
+    fn text_the_shaper_cant_shape_is_still_laid_out() {
+        let metrics = FontMetrics::new_fixed(8.0, 16);
+        let text = "ab\tc";
+        let mut shaped = ShapedText::new();
+        append_simple(&mut shaped, &metrics, text, 0..1);
+        append_simple(&mut shaped, &metrics, text, 1..4);
+
+        assert_eq!(shaped.glyphs.len(), 4);
+        // The tab lines up with the tab stops of the whole line, not of the second piece
+        let clusters: Vec<_> = shaped.clusters.iter().map(|x| (x.range.clone(), x.x)).collect();
+        assert_eq!(clusters, vec![(0..1, 0.0), (1..2, 8.0), (2..3, 16.0), (3..4, 64.0)]);
+        assert_eq!(shaped.width, 72.0);
+    }
+}
@@ -1,16 +1,28 @@
 use cgmath::*;
 use fnv::*;
-use rusttype::{self, FontCollection, Scale};
-use std::cell::RefCell;
+use log::*;
+use rusttype::{self, FontCollection, GlyphId, Scale};
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
 use std::collections::hash_map::*;
-use std::iter;
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
 use std::rc::Rc;
+use std::sync::Arc;
+use uid::*;
+use unicode_segmentation::UnicodeSegmentation;
 use web_sys::*;
 use webgl_wrapper::uniforms::*;
 use webgl_wrapper::*;
 
+use crate::atlas::*;
 use crate::color::*;
+use crate::draw_2d::*;
+use crate::sdf::*;
 use crate::shader_header::*;
+use crate::shaping::*;
+use crate::text_cache::*;
+use crate::text_layout::*;
 
 struct TextCacheVert {
     pos: Vector2<f32>,
@@ -28,6 +40,7 @@ impl VertexData for TextCacheVert {
     const ATTRIBUTES: Attributes = &[("pos", 2), ("uv", 2)];
 }
 
+#[derive(Copy, Clone)]
 struct TextRenderVert {
     pos: Vector2<f32>,
     uv: Vector2<f32>,
@@ -46,6 +59,70 @@ impl VertexData for TextRenderVert {
     const ATTRIBUTES: Attributes = &[("pos", 2), ("uv", 2), ("color", 4)];
 }
 
+impl ClipVert for TextRenderVert {
+    fn pos(&self) -> Point2<f32> {
+        Point2::from_vec(self.pos)
+    }
+
+    fn lerp(&self, other: &Self, amount: f32) -> Self {
+        TextRenderVert {
+            pos: self.pos.lerp(other.pos, amount),
+            uv: self.uv.lerp(other.uv, amount),
+            color: self.color.lerp(other.color, amount),
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+struct SdfRenderVert {
+    pos: Vector2<f32>,
+    uv: Vector2<f32>,
+    color: Color4,
+    outline_color: Color4,
+    glow_color: Color4,
+    /// The outline width and glow radius, in the units of the distance field.
+    effect: Vector2<f32>,
+}
+
+impl VertexComponent for SdfRenderVert {
+    fn add_to_mesh(&self, f: &mut dyn FnMut(f32)) {
+        self.pos.add_to_mesh(f);
+        self.uv.add_to_mesh(f);
+        self.color.add_to_mesh(f);
+        self.outline_color.add_to_mesh(f);
+        self.glow_color.add_to_mesh(f);
+        self.effect.add_to_mesh(f);
+    }
+}
+
+impl VertexData for SdfRenderVert {
+    const ATTRIBUTES: Attributes = &[
+        ("pos", 2),
+        ("uv", 2),
+        ("color", 4),
+        ("outline_color", 4),
+        ("glow_color", 4),
+        ("effect", 2),
+    ];
+}
+
+impl ClipVert for SdfRenderVert {
+    fn pos(&self) -> Point2<f32> {
+        Point2::from_vec(self.pos)
+    }
+
+    fn lerp(&self, other: &Self, amount: f32) -> Self {
+        SdfRenderVert {
+            pos: self.pos.lerp(other.pos, amount),
+            uv: self.uv.lerp(other.uv, amount),
+            color: self.color.lerp(other.color, amount),
+            outline_color: self.outline_color.lerp(other.outline_color, amount),
+            glow_color: self.glow_color.lerp(other.glow_color, amount),
+            effect: self.effect.lerp(other.effect, amount),
+        }
+    }
+}
+
 struct TextCacheUniforms<'a> {
     matrix: Matrix4<f32>,
     tex: &'a Texture2d,
@@ -160,28 +237,837 @@ void main() {
   FragColor.rgb *= FragColor.a;
 }";
 
-struct FontInner {
+const SDF_RENDER_VERT_SHADER: &str = "
+in vec2 pos;
+in vec2 uv;
+in vec4 color;
+in vec4 outline_color;
+in vec4 glow_color;
+in vec2 effect;
+
+uniform mat4 matrix;
+
+out vec2 Uv;
+out vec4 Color;
+out vec4 OutlineColor;
+out vec4 GlowColor;
+out vec2 Effect;
+
+void main() {
+    gl_Position = matrix * vec4(pos, 0.0, 1.0);
+    Uv = uv;
+    Color = color;
+    OutlineColor = outline_color;
+    GlowColor = glow_color;
+    Effect = effect;
+}";
+
+const SDF_RENDER_FRAG_SHADER: &str = "
+in vec2 Uv;
+in vec4 Color;
+in vec4 OutlineColor;
+in vec4 GlowColor;
+in vec2 Effect;
+
+uniform sampler2D tex;
+
+out vec4 FragColor;
+
+void main() {
+    float dist = texture(tex, Uv).r;
+    // Antialias over about one screen pixel, whatever the scale
+    float smoothing = 0.5 * fwidth(dist);
+    float fill = smoothstep(0.5 - smoothing, 0.5 + smoothing, dist);
+    float outline_edge = 0.5 - Effect.x;
+    float outline = Effect.x > 0.0
+        ? smoothstep(outline_edge - smoothing, outline_edge + smoothing, dist)
+        : 0.0;
+    float glow = Effect.y > 0.0 ? smoothstep(outline_edge - Effect.y, outline_edge, dist) : 0.0;
+
+    // Premultiplied alpha, composited from back to front
+    vec4 color = vec4(GlowColor.rgb, 1.0) * GlowColor.a * glow;
+    vec4 outline_color = vec4(OutlineColor.rgb, 1.0) * OutlineColor.a * outline;
+    color = outline_color + color * (1.0 - outline_color.a);
+    vec4 fill_color = vec4(Color.rgb, 1.0) * Color.a * fill;
+    FragColor = fill_color + color * (1.0 - fill_color.a);
+}";
+
+/// Measures text for layout purposes.
+///
+/// This doesn't require a `GlContext`, so layouts can be computed without one.
+pub trait TextMeasurer {
+    /// Returns the width of a rendered string in pixels.
+    fn string_width(&self, str: &str) -> f32;
+
+    /// Returns the vertical distance between consecutive lines, in pixels.
+    fn advance_y(&self) -> i32;
+
+    /// Returns the size of a rendered string in pixels, with the width rounded up so that the
+    /// string fits in it.
+    fn string_size(&self, str: &str) -> Vector2<i32> {
+        vec2(self.string_width(str).ceil() as i32, self.advance_y())
+    }
+}
+
+/// How lines of text are positioned horizontally.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum TextAlign {
+    Left,
+    Center,
+    Right,
+    /// Lines that were wrapped are stretched to fill the whole width by widening the spaces
+    /// between words. The last line of each paragraph is aligned left.
+    Justify,
+}
+
+/// Splits text into lines that are at most `max_width` pixels wide, breaking at whitespace where
+/// possible, and always breaking at '\n'.
+///
+/// Each line is returned as a byte range of `text`, which doesn't include the '\n' that ends it.
+/// Whitespace at a line break is kept at the end of the line before the break, so if a line doesn't
+/// end with '\n', its range ends where the next line's begins. Words that are too wide to fit on
+/// one line are broken between grapheme clusters.
+pub fn wrap_text(
+    measurer: &(impl TextMeasurer + ?Sized),
+    text: &str,
+    max_width: f32,
+) -> Vec<Range<usize>> {
+    // Widths are added up a grapheme cluster at a time rather than measuring the whole line
+    // again for every character, so kerning between clusters is ignored
+    let tab_width = measurer.string_width("\t");
+    let mut lines = vec![];
+    let mut paragraph_start = 0;
+    for paragraph in text.split('\n') {
+        let paragraph_end = paragraph_start + paragraph.len();
+        let mut line_start = paragraph_start;
+        // The width of the text from `line_start` to the current cluster
+        let mut x = 0.0;
+        // The position after the most recent whitespace on the current line, and the width of the
+        // line up to it
+        let mut last_break = None;
+        for (i, cluster) in paragraph.grapheme_indices(true) {
+            let i = paragraph_start + i;
+            if cluster == "\t" {
+                // Tab stops are relative to the start of the line
+                x = if tab_width > 0.0 { ((x / tab_width).floor() + 1.0) * tab_width } else { x };
+                last_break = Some((i + 1, x));
+                continue;
+            }
+            let width = measurer.string_width(cluster);
+            if cluster.starts_with(char::is_whitespace) {
+                x += width;
+                last_break = Some((i + cluster.len(), x));
+                continue;
+            }
+            if x + width <= max_width {
+                x += width;
+                continue;
+            }
+            if let Some((last_break, break_x)) = last_break.take() {
+                lines.push(line_start..last_break);
+                line_start = last_break;
+                // There's no whitespace, and so no tab, after the break
+                x -= break_x;
+            }
+            if i > line_start && x + width > max_width {
+                lines.push(line_start..i);
+                line_start = i;
+                x = 0.0;
+            }
+            x += width;
+        }
+        lines.push(line_start..paragraph_end);
+        paragraph_start = paragraph_end + 1;
+    }
+    lines
+}
+
+#[derive(Eq, PartialEq, Hash, Copy, Clone, Debug)]
+struct FontId_(());
+
+/// Identifies a font at a particular size, for caching its glyphs.
+type FontId = Id<FontId_>;
+
+#[derive(Eq, PartialEq, Hash, Copy, Clone, Debug)]
+struct FaceId_(());
+
+/// Identifies a `Face`, which is shared by the fonts of every size made from it.
+type FaceId = Id<FaceId_>;
+
+/// One of the fonts a `FontMetrics` takes glyphs from.
+#[derive(Clone)]
+pub(crate) struct Face {
+    pub font: rusttype::Font<'static>,
+    /// The contents of the `ttf` file, which the shaper parses separately, and which tables that
+    /// `rusttype` doesn't expose are read from.
+    pub data: Arc<[u8]>,
+    id: FaceId,
+}
+
+impl Face {
+    fn new(data: Vec<u8>) -> Self {
+        let data: Arc<[u8]> = data.into();
+        let font = FontCollection::from_bytes(data.clone()).unwrap().into_font().unwrap();
+        Face { font, data, id: FaceId::new() }
+    }
+
+    fn has_glyph(&self, c: char) -> bool {
+        self.font.glyph(c).id().0 != 0
+    }
+
+    /// Returns the contents of a table in the `ttf` file, or `None` if the font doesn't have it.
+    fn table(&self, tag: &[u8; 4]) -> Option<&[u8]> {
+        let data = &self.data[..];
+        // A font collection starts with the offsets of its fonts' table directories
+        let dir = if data.get(0..4)? == b"ttcf" { read_u32(data, 12)? as usize } else { 0 };
+        let num_tables = read_u16(data, dir + 4)? as usize;
+        let record = (0..num_tables)
+            .map(|i| dir + 12 + i * 16)
+            .find(|&record| data.get(record..record + 4) == Some(&tag[..]))?;
+        let offset = read_u32(data, record + 8)? as usize;
+        let len = read_u32(data, record + 12)? as usize;
+        data.get(offset..offset + len)
+    }
+
+    /// Returns the position and thickness of a decoration in font units, with the position of the
+    /// top of the line measured up from the baseline.
+    fn decoration_units(&self, decoration: TextDecoration) -> Option<(i16, i16)> {
+        match decoration {
+            TextDecoration::Underline => {
+                let post = self.table(b"post")?;
+                Some((read_u16(post, 8)? as i16, read_u16(post, 10)? as i16))
+            }
+            TextDecoration::Strikethrough => {
+                let os2 = self.table(b"OS/2")?;
+                Some((read_u16(os2, 28)? as i16, read_u16(os2, 26)? as i16))
+            }
+        }
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset + 2)?;
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// A line drawn along text by `Font::draw_decoration`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum TextDecoration {
+    Underline,
+    Strikethrough,
+}
+
+/// The position and thickness of a `TextDecoration`, in pixels.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DecorationMetrics {
+    /// The distance from the baseline down to the center of the line. This is negative for lines
+    /// above the baseline.
+    pub offset: f32,
+    pub thickness: f32,
+}
+
+/// The default distance between tab stops, in spaces.
+const DEFAULT_TAB_SPACES: f32 = 8.0;
+
+/// Laid out text, keyed by the text and the bits of its maximum width.
+type LayoutCache = LruCache<(String, Option<u32>), Rc<TextLayout>>;
+
+/// The metrics of a TTF font at a particular size, computed with `rusttype` alone.
+///
+/// This is everything needed to measure text, and unlike `Font` it doesn't need a `GlContext`.
+pub struct FontMetrics {
+    id: FontId,
     size: u32,
-    font: rusttype::Font<'static>,
+    /// The primary font, followed by the fonts that glyphs are taken from when it doesn't have
+    /// them, in order.
+    faces: Vec<Face>,
     advance_y: i32,
     ascent: f32,
-    glyphs: FnvHashMap<char, CachedGlyph>,
-    kerning: FnvHashMap<(char, char), f32>,
-    framebuffer: Framebuffer<Texture2d>,
-    cur_x: u32,
-    cur_y: u32,
+    underline: DecorationMetrics,
+    strikethrough: DecorationMetrics,
+    /// The distance between tab stops in pixels.
+    tab_width: Cell<f32>,
+    advances: RefCell<FnvHashMap<char, f32>>,
+    kerning: RefCell<FnvHashMap<(char, char), f32>>,
+    scale: Scale,
+    /// The widths of recently measured strings.
+    widths: RefCell<LruCache<String, f32>>,
+    /// Recently laid out text.
+    layouts: RefCell<LayoutCache>,
+    /// The width of every character if the metrics were created with `new_fixed`, in which case
+    /// there are no faces.
+    fixed_advance: Option<f32>,
+}
+
+impl FontMetrics {
+    /// Creates a `FontMetrics` from a `Vec` containing the contents of a `ttf` file.
+    pub fn new(data: Vec<u8>, size: u32) -> Self {
+        Self::from_faces(vec![Face::new(data)], size)
+    }
+
+    /// Creates metrics in which every character is `advance_x` pixels wide and lines are `size`
+    /// pixels apart, without a `ttf` file.
+    ///
+    /// This is meant for tests, since the sizes of laid out text are easy to predict. Text isn't
+    /// kerned or shaped, and a `Font` with these metrics can't be drawn.
+    pub fn new_fixed(advance_x: f32, size: u32) -> Self {
+        let ascent = (size as f32 * 0.8).round();
+        Self {
+            id: FontId::new(),
+            size,
+            faces: vec![],
+            advance_y: size as i32,
+            ascent,
+            underline: guess_decoration_metrics(TextDecoration::Underline, size, ascent),
+            strikethrough: guess_decoration_metrics(TextDecoration::Strikethrough, size, ascent),
+            tab_width: Cell::new(advance_x * DEFAULT_TAB_SPACES),
+            advances: RefCell::new(FnvHashMap::default()),
+            kerning: RefCell::new(FnvHashMap::default()),
+            scale: Scale::uniform(size as f32),
+            widths: RefCell::new(LruCache::new(DEFAULT_TEXT_CACHE_CAPACITY)),
+            layouts: RefCell::new(LruCache::new(DEFAULT_TEXT_CACHE_CAPACITY)),
+            fixed_advance: Some(advance_x),
+        }
+    }
+
+    /// Creates a `FontMetrics` for the same font at a different size. This doesn't need to parse
+    /// the font again.
+    pub fn with_size(&self, size: u32) -> Self {
+        match self.fixed_advance {
+            Some(advance_x) => Self::new_fixed(advance_x * size as f32 / self.size as f32, size),
+            None => Self::from_faces(self.faces.clone(), size),
+        }
+    }
+
+    fn from_faces(faces: Vec<Face>, size: u32) -> Self {
+        let scale = Scale { x: size as f32, y: size as f32 };
+        let v_metrics = faces[0].font.v_metrics(scale);
+        let descent = v_metrics.descent;
+        let ascent = v_metrics.ascent;
+        let advance_y = ascent - descent;
+
+        // This is how `rusttype` converts font units to pixels
+        let v_metrics_unscaled = faces[0].font.v_metrics_unscaled();
+        let units_to_pixels =
+            size as f32 / (v_metrics_unscaled.ascent - v_metrics_unscaled.descent);
+        let decoration_metrics = |decoration| match faces[0].decoration_units(decoration) {
+            Some((position, thickness)) if thickness > 0 => {
+                let thickness = thickness as f32 * units_to_pixels;
+                DecorationMetrics {
+                    offset: -(position as f32 * units_to_pixels - thickness / 2.0),
+                    thickness: thickness.max(1.0),
+                }
+            }
+            // Some fonts don't specify their decorations, so guess
+            _ => guess_decoration_metrics(decoration, size, ascent),
+        };
+        let underline = decoration_metrics(TextDecoration::Underline);
+        let strikethrough = decoration_metrics(TextDecoration::Strikethrough);
+        let space_width = faces[0].font.glyph(' ').scaled(scale).h_metrics().advance_width;
+
+        Self {
+            id: FontId::new(),
+            size,
+            faces,
+            advance_y: advance_y as i32,
+            ascent,
+            underline,
+            strikethrough,
+            tab_width: Cell::new(space_width * DEFAULT_TAB_SPACES),
+            advances: RefCell::new(FnvHashMap::default()),
+            kerning: RefCell::new(FnvHashMap::default()),
+            scale,
+            widths: RefCell::new(LruCache::new(DEFAULT_TEXT_CACHE_CAPACITY)),
+            layouts: RefCell::new(LruCache::new(DEFAULT_TEXT_CACHE_CAPACITY)),
+            fixed_advance: None,
+        }
+    }
+
+    /// Returns the index of the first font with a glyph for `c`, where 0 is the primary font and
+    /// 1 onwards are the fallbacks. If no font has it, the primary font's notdef glyph is used.
+    pub(crate) fn face_index(&self, c: char) -> usize {
+        self.faces.iter().position(|face| face.has_glyph(c)).unwrap_or(0)
+    }
+
+    /// Returns true if this font or one of its fallbacks has a glyph for `c`.
+    pub fn has_glyph(&self, c: char) -> bool {
+        self.fixed_advance.is_some() || self.faces.iter().any(|face| face.has_glyph(c))
+    }
+
+    /// Returns the glyph for `c` from the first font that has it.
+    fn glyph(&self, c: char) -> rusttype::Glyph<'static> {
+        self.faces[self.face_index(c)].font.glyph(c)
+    }
+
+    fn get_kerning(&self, a: char, b: char) -> f32 {
+        match self.kerning.borrow_mut().entry((a, b)) {
+            Entry::Vacant(entry) => {
+                // Chars from different fonts aren't kerned
+                let face_index = self.face_index(a);
+                let kerning = if self.fixed_advance.is_none() && face_index == self.face_index(b) {
+                    self.faces[face_index].font.pair_kerning(self.scale, a, b)
+                } else {
+                    0.0
+                };
+                *entry.insert(kerning)
+            }
+            Entry::Occupied(entry) => *entry.get(),
+        }
+    }
+
+    fn horiz_advance_between(&self, a: char, b: char) -> f32 {
+        self.horiz_advance_after(a) + self.get_kerning(a, b)
+    }
+
+    /// Returns the position after drawing `c` at `x`, where `next` is the character after it.
+    fn advance_from(&self, x: f32, c: char, next: Option<char>) -> f32 {
+        if c == '\t' {
+            let tab_width = self.tab_width.get();
+            return ((x / tab_width).floor() + 1.0) * tab_width;
+        }
+        match next {
+            Some(next) => x + self.horiz_advance_between(c, next),
+            None => x + self.horiz_advance_after(c),
+        }
+    }
+
+    fn horiz_advance_after(&self, a: char) -> f32 {
+        if let Some(advance_x) = self.fixed_advance {
+            return advance_x;
+        }
+        match self.advances.borrow_mut().entry(a) {
+            Entry::Vacant(entry) => {
+                let advance_x = self.glyph(a).scaled(self.scale).h_metrics().advance_width;
+                *entry.insert(advance_x)
+            }
+            Entry::Occupied(entry) => *entry.get(),
+        }
+    }
+
+    /// Returns the font size.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Returns the distance from the top of a line to its baseline.
+    pub fn ascent(&self) -> f32 {
+        self.ascent
+    }
+
+    /// Returns where a decoration is drawn, from the font's `post` and `OS/2` tables.
+    pub fn decoration_metrics(&self, decoration: TextDecoration) -> DecorationMetrics {
+        match decoration {
+            TextDecoration::Underline => self.underline,
+            TextDecoration::Strikethrough => self.strikethrough,
+        }
+    }
+
+    /// Returns the distance between tab stops in pixels.
+    pub fn tab_width(&self) -> f32 {
+        self.tab_width.get()
+    }
+
+    /// Sets the distance between tab stops in pixels. A '\t' advances to the next multiple of this
+    /// distance from the start of the text it's measured or drawn in. Defaults to the width of 8
+    /// spaces.
+    ///
+    /// Widths less than 1 pixel, including NaN, are treated as 1 pixel.
+    pub fn set_tab_width(&self, tab_width: f32) {
+        self.tab_width.set(if tab_width.is_nan() { 1.0 } else { tab_width.clamp(1.0, f32::MAX) });
+        // Everything that was measured with the old tab stops is wrong now
+        self.widths.borrow_mut().clear();
+        self.layouts.borrow_mut().clear();
+    }
+
+    /// Returns the position of the next tab stop after `x`.
+    pub(crate) fn next_tab_stop(&self, x: f32) -> f32 {
+        self.advance_from(x, '\t', None)
+    }
+
+    #[cfg(feature = "shaping")]
+    pub(crate) fn faces(&self) -> &[Face] {
+        &self.faces
+    }
+
+    /// Converts a line of text to positioned glyphs, which can be used to place a caret or find the
+    /// character at a position. See `ShapedText`.
+    pub fn shape(&self, text: &str) -> ShapedText {
+        #[cfg(feature = "shaping")]
+        {
+            if self.fixed_advance.is_some() {
+                self.shape_simple(text)
+            } else {
+                shape_complex(self, text)
+            }
+        }
+        #[cfg(not(feature = "shaping"))]
+        {
+            self.shape_simple(text)
+        }
+    }
+
+    /// Gives each character its own glyph, from left to right, and groups them into grapheme
+    /// clusters.
+    fn shape_simple(&self, text: &str) -> ShapedText {
+        self.shape_simple_from(text, 0.0)
+    }
+
+    /// Like `shape_simple`, but for text that starts `start_x` pixels into a line, so that its tabs
+    /// line up with the tab stops of the line. The glyphs and clusters are positioned relative to
+    /// the start of the line.
+    pub(crate) fn shape_simple_from(&self, text: &str, start_x: f32) -> ShapedText {
+        let mut shaped = ShapedText::new();
+        shaped.width = start_x;
+        // This sums advances in the same order as `string_width`, so the text is exactly as wide
+        // as it was measured to be
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            let face = self.face_index(c);
+            // Fonts often don't have a glyph for tabs, and a missing glyph is drawn as a box
+            let id = match self.faces.get(face) {
+                Some(face) => face.font.glyph(if c == '\t' { ' ' } else { c }).id(),
+                None => GlyphId(0),
+            };
+            shaped.glyphs.push(ShapedGlyph { face, id, pos: vec2(shaped.width, 0.0) });
+            shaped.width = self.advance_from(shaped.width, c, chars.peek().copied());
+        }
+
+        let mut glyph_index = 0;
+        for (i, grapheme) in text.grapheme_indices(true) {
+            let x = shaped.glyphs[glyph_index].pos.x;
+            glyph_index += grapheme.chars().count();
+            if let Some(prev) = shaped.clusters.last_mut() {
+                prev.width = x - prev.x;
+            }
+            shaped.clusters.push(ShapedCluster {
+                range: i..i + grapheme.len(),
+                x,
+                width: shaped.width - x,
+                rtl: false,
+            });
+        }
+        shaped
+    }
+
+    /// Returns statistics about the cache of measured string widths.
+    pub fn width_cache_stats(&self) -> TextCacheStats {
+        self.widths.borrow().stats()
+    }
+
+    /// Returns statistics about the cache of text laid out by `Font::layout`.
+    pub fn layout_cache_stats(&self) -> TextCacheStats {
+        self.layouts.borrow().stats()
+    }
+
+    /// Sets the number of string widths and the number of layouts that are cached, evicting the
+    /// least recently used ones if there are too many. Defaults to 1024 of each.
+    pub fn set_text_cache_capacity(&self, capacity: usize) {
+        self.widths.borrow_mut().set_capacity(capacity);
+        self.layouts.borrow_mut().set_capacity(capacity);
+    }
+
+    #[cfg(feature = "shaping")]
+    fn measure_width(&self, str: &str) -> f32 {
+        self.shape(str).width
+    }
+
+    #[cfg(not(feature = "shaping"))]
+    fn measure_width(&self, str: &str) -> f32 {
+        let mut width = 0.0;
+        let mut chars = str.chars().peekable();
+        while let Some(c) = chars.next() {
+            width = self.advance_from(width, c, chars.peek().copied());
+        }
+        width
+    }
+}
+
+/// Guesses where a decoration is drawn, for fonts that don't specify it.
+fn guess_decoration_metrics(
+    decoration: TextDecoration,
+    size: u32,
+    ascent: f32,
+) -> DecorationMetrics {
+    let thickness = (size as f32 / 16.0).round().max(1.0);
+    let offset = match decoration {
+        TextDecoration::Underline => thickness,
+        TextDecoration::Strikethrough => -ascent * 0.3,
+    };
+    DecorationMetrics { offset, thickness }
+}
+
+/// Measures text without caching its width, for strings that are unlikely to be measured again,
+/// such as the prefixes of a line that's being wrapped.
+pub(crate) struct UncachedMeasurer<'a>(pub &'a FontMetrics);
+
+impl TextMeasurer for UncachedMeasurer<'_> {
+    fn string_width(&self, str: &str) -> f32 {
+        self.0.measure_width(str)
+    }
+
+    fn advance_y(&self) -> i32 {
+        self.0.advance_y
+    }
+}
+
+impl TextMeasurer for FontMetrics {
+    fn string_width(&self, str: &str) -> f32 {
+        if let Some(width) = self.widths.borrow_mut().get(str) {
+            return width;
+        }
+        let width = self.measure_width(str);
+        self.widths.borrow_mut().insert(str.to_owned(), width);
+        width
+    }
+
+    fn advance_y(&self) -> i32 {
+        self.advance_y
+    }
+}
+
+const ATLAS_PAGE_SIZE: u32 = 1024;
+const DEFAULT_MAX_ATLAS_PAGES: usize = 4;
+/// The font size signed distance field glyphs are generated at, regardless of the size they're
+/// drawn at.
+const SDF_SIZE: f32 = 32.0;
+/// The distance in pixels at `SDF_SIZE` over which a distance field goes from the edge of a glyph
+/// to fully outside it. This limits how far outlines and glows can extend.
+const SDF_RANGE: f32 = 8.0;
+/// The number of horizontal positions within a pixel that bitmap glyphs are rendered at. Glyphs
+/// are snapped to the nearest one, so they're positioned to within a quarter of a pixel.
+const SUBPIXEL_OFFSETS: u8 = 4;
+
+/// The different ways a glyph can be rendered into the cache.
+#[derive(Eq, PartialEq, Hash, Copy, Clone, Debug)]
+enum GlyphVariant {
+    /// A bitmap, rendered offset to the right by this many `SUBPIXEL_OFFSETS`ths of a pixel.
+    Bitmap(u8),
+    /// A bitmap rendered like `Bitmap`, then widened by the second number of pixels in every
+    /// direction to draw outlines with.
+    Dilated(u8, u8),
+    /// A signed distance field, which doesn't need subpixel offsets since it's interpolated.
+    Sdf,
+}
+
+impl GlyphVariant {
+    fn is_sdf(self) -> bool {
+        self == GlyphVariant::Sdf
+    }
+}
+
+/// Identifies a glyph in the cache.
+#[derive(Eq, PartialEq, Hash, Copy, Clone, Debug)]
+struct GlyphKey {
+    /// The font whose size the glyph is rendered at, or `None` for distance fields, which are
+    /// rendered at a fixed size and shared by every size of a face.
+    font: Option<FontId>,
+    face: FaceId,
+    id: GlyphId,
+    variant: GlyphVariant,
+}
+
+/// Effects drawn around the glyphs of a font that uses signed distance fields.
+///
+/// Widths are in pixels at the font's size, and scale with it. The outline width and glow radius
+/// together can't exceed a quarter of the font size; larger values are clamped.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SdfEffect {
+    pub outline_width: f32,
+    pub outline_color: Color4,
+    pub glow_radius: f32,
+    /// The color of the glow at the glyph's outline, which fades out to transparent.
+    pub glow_color: Color4,
+}
+
+impl Default for SdfEffect {
+    fn default() -> Self {
+        SdfEffect {
+            outline_width: 0.0,
+            outline_color: Color4::TRANSPARENT,
+            glow_radius: 0.0,
+            glow_color: Color4::TRANSPARENT,
+        }
+    }
+}
+
+impl SdfEffect {
+    /// Converts the outline width and glow radius to the units of the distance field, for a font
+    /// of the given size.
+    fn distances(&self, size: u32) -> Vector2<f32> {
+        let to_distance = |width: f32| width * SDF_SIZE / size as f32 / (2.0 * SDF_RANGE);
+        let outline = to_distance(self.outline_width).clamp(0.0, 0.5);
+        let glow = to_distance(self.glow_radius).clamp(0.0, 0.5 - outline);
+        vec2(outline, glow)
+    }
+}
+
+/// An outline drawn around text, to keep it readable on any background.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TextOutline {
+    /// The width in pixels. Fonts drawn with bitmaps round it to a whole number of pixels, and
+    /// fonts drawn with signed distance fields are limited to a quarter of the font size.
+    pub width: f32,
+    pub color: Color4,
+}
+
+/// A copy of text drawn behind it, offset from it.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TextShadow {
+    /// The offset in pixels. Positive values are to the right and down.
+    pub offset: Vector2<f32>,
+    pub color: Color4,
+}
+
+/// How text is drawn: its color, and optionally an outline and a drop shadow.
+///
+/// A `Color4` converts to a `TextStyle` with no outline or shadow, so it can be passed to any
+/// method that draws text.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TextStyle {
+    pub color: Color4,
+    pub outline: Option<TextOutline>,
+    /// If the text has an outline, the shadow includes it.
+    pub shadow: Option<TextShadow>,
+}
+
+impl TextStyle {
+    pub fn new(color: Color4) -> Self {
+        TextStyle { color, outline: None, shadow: None }
+    }
+
+    pub fn outline(mut self, width: f32, color: Color4) -> Self {
+        self.outline = Some(TextOutline { width, color });
+        self
+    }
+
+    pub fn shadow(mut self, offset: Vector2<f32>, color: Color4) -> Self {
+        self.shadow = Some(TextShadow { offset, color });
+        self
+    }
+
+    /// Returns how far the outline and shadow reach past the text, as the space they need before
+    /// it (to the left and above) and after it (to the right and below).
+    pub fn extent(&self) -> (Vector2<f32>, Vector2<f32>) {
+        let width = self.outline.map(|outline| outline.width.max(0.0)).unwrap_or(0.0);
+        let offset = self.shadow.map(|shadow| shadow.offset).unwrap_or_else(Vector2::zero);
+        let before = vec2(width + (-offset.x).max(0.0), width + (-offset.y).max(0.0));
+        let after = vec2(width + offset.x.max(0.0), width + offset.y.max(0.0));
+        (before, after)
+    }
+}
+
+impl From<Color4> for TextStyle {
+    fn from(color: Color4) -> Self {
+        TextStyle::new(color)
+    }
+}
+
+/// The layers styled text is drawn in, from back to front.
+///
+/// The glyphs of each layer are rendered from every atlas page before the next layer is rendered,
+/// so one glyph's shadow or outline never covers another glyph, even if they're on different pages.
+#[derive(Copy, Clone, Debug)]
+enum TextLayer {
+    Shadow,
+    Outline,
+    Fill,
+}
+
+const NUM_TEXT_LAYERS: usize = 3;
+
+/// How a glyph is drawn in one layer of styled text.
+#[derive(Copy, Clone, Debug)]
+struct GlyphPaint {
+    color: Color4,
+    /// If this is `Some`, the glyph is widened by this many pixels in every direction, which is
+    /// drawn behind it as an outline.
+    outline_width: Option<f32>,
+    layer: TextLayer,
+}
+
+/// The GL resources used to cache and render the glyphs of every font that shares a
+/// `TextRenderer`.
+struct TextRendererInner {
+    clip_stack: Vec<Rect<i32>>,
+    glyphs: FnvHashMap<GlyphKey, CachedGlyph>,
+    pages: Vec<AtlasPage>,
+    max_pages: usize,
+    /// Incremented each time the queued characters are rendered.
+    frame: u64,
+    evictions: u64,
     cache_mesh_builder: MeshBuilder<TextCacheVert, Triangles>,
-    render_mesh_builder: MeshBuilder<TextRenderVert, Triangles>,
     cache_mesh: Mesh<TextCacheVert, TextCacheUniformsGl, Triangles>,
     render_mesh: Mesh<TextRenderVert, TextRenderUniformsGl, Triangles>,
-    scale: Scale,
+    sdf_render_mesh: Mesh<SdfRenderVert, TextRenderUniformsGl, Triangles>,
+}
+
+/// One texture of the glyph cache. Characters are queued separately for each page, so each page
+/// is rendered with one draw call.
+///
+/// A page holds either bitmap glyphs or signed distance field glyphs, since they're sampled with
+/// different filtering and rendered with different programs.
+struct AtlasPage {
+    framebuffer: Framebuffer<Texture2d>,
+    packer: SkylinePacker,
+    sdf: bool,
+    /// The glyphs queued in each `TextLayer`.
+    render_mesh_builders: [MeshBuilder<TextRenderVert, Triangles>; NUM_TEXT_LAYERS],
+    sdf_render_mesh_builders: [MeshBuilder<SdfRenderVert, Triangles>; NUM_TEXT_LAYERS],
+    /// Whether any glyphs have been queued in each `TextLayer` since the last render.
+    queued_layers: [bool; NUM_TEXT_LAYERS],
+    /// The last frame a glyph on this page was drawn in.
+    last_used: u64,
+    num_glyphs: usize,
+    used_area: u64,
+}
+
+impl AtlasPage {
+    fn new(context: &GlContext, sdf: bool) -> Self {
+        // Distance fields are interpolated so that they can be scaled smoothly
+        let (min_filter, mag_filter) = if sdf {
+            (MinFilter::Linear, MagFilter::Linear)
+        } else {
+            (MinFilter::Nearest, MagFilter::Nearest)
+        };
+        let framebuffer = Framebuffer::new_with_texture(
+            context,
+            vec2(ATLAS_PAGE_SIZE, ATLAS_PAGE_SIZE),
+            TextureFormat::Red,
+            min_filter,
+            mag_filter,
+            WrapMode::ClampToEdge,
+        );
+        framebuffer.clear(context, &[ClearBuffer::Color(Color4::TRANSPARENT.into())]);
+        Self {
+            framebuffer,
+            packer: SkylinePacker::new(vec2(ATLAS_PAGE_SIZE, ATLAS_PAGE_SIZE)),
+            sdf,
+            render_mesh_builders: [MeshBuilder::new(), MeshBuilder::new(), MeshBuilder::new()],
+            sdf_render_mesh_builders: [MeshBuilder::new(), MeshBuilder::new(), MeshBuilder::new()],
+            queued_layers: [false; NUM_TEXT_LAYERS],
+            last_used: 0,
+            num_glyphs: 0,
+            used_area: 0,
+        }
+    }
+
+    fn clear(&mut self, context: &GlContext) {
+        self.framebuffer.clear(context, &[ClearBuffer::Color(Color4::TRANSPARENT.into())]);
+        self.packer.clear();
+        self.num_glyphs = 0;
+        self.used_area = 0;
+    }
 }
 
 /// A glyph that has been generated but not yet added to the cache.
 struct PendingGlyph {
     // None for whitespace
     display: Option<PendingGlyphDisplay>,
-    advance_x: f32,
 }
 
 struct PendingGlyphDisplay {
@@ -194,134 +1080,146 @@ struct PendingGlyphDisplay {
 #[derive(Debug)]
 struct CachedGlyph {
     display: Option<CachedGlyphDisplay>,
-    advance_x: f32,
 }
 
 #[derive(Debug)]
 struct CachedGlyphDisplay {
+    page: usize,
     loc: Vector2<i32>,
     size: Vector2<i32>,
     left: i32,
     top: i32,
 }
 
-impl FontInner {
-    pub fn new(context: &GlContext, data: Vec<u8>, size: u32) -> Self {
-        let font = FontCollection::from_bytes(data).unwrap().into_font().unwrap();
-        let scale = Scale { x: size as f32, y: size as f32 };
-        let v_metrics = font.v_metrics(scale);
-        let descent = v_metrics.descent;
-        let ascent = v_metrics.ascent;
-        let advance_y = ascent - descent;
-
-        let framebuffer = Framebuffer::new_with_texture(
-            context,
-            vec2(1024, 1024),
-            TextureFormat::Red,
-            MinFilter::Nearest,
-            MagFilter::Nearest,
-            WrapMode::ClampToEdge,
-        );
-        framebuffer.clear(context, &[ClearBuffer::Color(Color4::TRANSPARENT.into())]);
-
-        // TODO: find a way to share these programs between all Font instances
+impl TextRendererInner {
+    pub fn new(context: &GlContext) -> Self {
         let cache_program =
             GlProgram::new_with_minimal_header(context, CACHE_VERT_SHADER, CACHE_FRAG_SHADER);
         let render_program =
             GlProgram::new_with_minimal_header(context, RENDER_VERT_SHADER, RENDER_FRAG_SHADER);
         let cache_mesh_builder = MeshBuilder::new();
-        let render_mesh_builder = MeshBuilder::new();
         let cache_mesh = Mesh::new(context, &cache_program, DrawMode::Draw2D);
         let render_mesh = Mesh::new(context, &render_program, DrawMode::Draw2D);
+        let sdf_render_program = GlProgram::new_with_minimal_header(
+            context,
+            SDF_RENDER_VERT_SHADER,
+            SDF_RENDER_FRAG_SHADER,
+        );
+        let sdf_render_mesh = Mesh::new(context, &sdf_render_program, DrawMode::Draw2D);
 
         Self {
-            size,
-            font,
-            advance_y: advance_y as i32,
-            ascent,
+            clip_stack: vec![],
             glyphs: FnvHashMap::default(),
-            kerning: FnvHashMap::default(),
-            framebuffer,
-            cur_x: 0,
-            cur_y: 0,
+            pages: vec![AtlasPage::new(context, false)],
+            max_pages: DEFAULT_MAX_ATLAS_PAGES,
+            frame: 1,
+            evictions: 0,
             cache_mesh_builder,
-            render_mesh_builder,
             cache_mesh,
-            render_mesh,
-            scale,
-        }
-    }
-
-    fn get_kerning(&mut self, a: char, b: char) -> f32 {
-        match self.kerning.entry((a, b)) {
-            Entry::Vacant(entry) => {
-                let kerning = self.font.pair_kerning(self.scale, a, b);
-                *entry.insert(kerning)
-            }
-            Entry::Occupied(entry) => *entry.get(),
-        }
-    }
-
-    // Renders a glyph and returns a glyph to be added to the cache.
-    fn load_glyph(&self, context: &GlContext, c: char) -> PendingGlyph {
-        let glyph = self.font.glyph(c).scaled(self.scale);
-        let advance_x = glyph.h_metrics().advance_width;
-        let positioned = glyph.positioned(rusttype::Point { x: 0.0, y: 0.0 });
-
-        let display = if c.is_whitespace() {
-            None
-        } else {
-            let mut bitmap = vec![];
-            positioned.draw(|_x, _y, pixel| {
-                bitmap.push((pixel * 255.0) as u8);
-            });
-            let bounding_box = positioned.pixel_bounding_box().unwrap();
-            let left = bounding_box.min.x;
-            let top = bounding_box.min.y;
-
-            // TODO: consider using glBufferSubData here
-            let texture = Texture2d::from_data(
-                context,
-                vec2(
-                    (bounding_box.max.x - bounding_box.min.x) as u32,
-                    (bounding_box.max.y - bounding_box.min.y) as u32,
-                ),
-                &bitmap,
-                TextureFormat::Red,
-                MinFilter::Nearest,
-                MagFilter::Nearest,
-                WrapMode::ClampToEdge,
-            );
+            render_mesh,
+            sdf_render_mesh,
+        }
+    }
 
-            Some(PendingGlyphDisplay { texture, left, top })
+    // Renders a glyph and returns a glyph to be added to the cache.
+    fn load_glyph(
+        &self,
+        context: &GlContext,
+        metrics: &FontMetrics,
+        face: &Face,
+        key: GlyphKey,
+    ) -> PendingGlyph {
+        let font = &face.font;
+        // Glyphs without an outline, such as spaces, have no display
+        let display = match key.variant {
+            GlyphVariant::Bitmap(offset) | GlyphVariant::Dilated(offset, _) => {
+                let glyph = font.glyph(key.id).scaled(metrics.scale);
+                let x = offset as f32 / SUBPIXEL_OFFSETS as f32;
+                let positioned = glyph.positioned(rusttype::Point { x, y: 0.0 });
+
+                positioned.pixel_bounding_box().map(|bounding_box| {
+                    let mut bitmap = vec![];
+                    positioned.draw(|_x, _y, pixel| {
+                        bitmap.push((pixel * 255.0) as u8);
+                    });
+                    let mut left = bounding_box.min.x;
+                    let mut top = bounding_box.min.y;
+                    let mut size = vec2(
+                        (bounding_box.max.x - bounding_box.min.x) as u32,
+                        (bounding_box.max.y - bounding_box.min.y) as u32,
+                    );
+                    if let GlyphVariant::Dilated(_, radius) = key.variant {
+                        bitmap = dilate(&bitmap, size, radius as u32);
+                        left -= radius as i32;
+                        top -= radius as i32;
+                        size += vec2(radius as u32 * 2, radius as u32 * 2);
+                    }
+
+                    // TODO: consider using glBufferSubData here
+                    let texture = Texture2d::from_data(
+                        context,
+                        size,
+                        &bitmap,
+                        TextureFormat::Red,
+                        MinFilter::Nearest,
+                        MagFilter::Nearest,
+                        WrapMode::ClampToEdge,
+                    );
+
+                    PendingGlyphDisplay { texture, left, top }
+                })
+            }
+            GlyphVariant::Sdf => {
+                let glyph = font.glyph(key.id).scaled(Scale::uniform(SDF_SIZE));
+                glyph.shape().and_then(|contours| generate_sdf(&contours, SDF_RANGE)).map(
+                    |bitmap| {
+                        let texture = Texture2d::from_data(
+                            context,
+                            bitmap.size,
+                            &bitmap.data,
+                            TextureFormat::Red,
+                            MinFilter::Nearest,
+                            MagFilter::Nearest,
+                            WrapMode::ClampToEdge,
+                        );
+                        PendingGlyphDisplay { texture, left: bitmap.left, top: bitmap.top }
+                    },
+                )
+            }
         };
 
-        PendingGlyph { display, advance_x }
+        PendingGlyph { display }
     }
 
-    fn cache_glyph(&mut self, context: &GlContext, c: char) {
-        if self.glyphs.contains_key(&c) {
+    fn cache_glyph(
+        &mut self,
+        context: &GlContext,
+        metrics: &FontMetrics,
+        face: &Face,
+        key: GlyphKey,
+    ) {
+        if self.glyphs.contains_key(&key) {
             return;
         }
 
-        let glyph = self.load_glyph(context, c);
-        let display = if let Some(display) = glyph.display {
-            let framebuffer_size = self.framebuffer.attachment.size();
+        let glyph = self.load_glyph(context, metrics, face, key);
+        let display = glyph.display.and_then(|display| {
             let glyph_texture_size = display.texture.size();
-            let line_out_of_space = self.cur_x + glyph_texture_size.x >= framebuffer_size.x;
-            let (x, y) = if line_out_of_space {
-                // Note: 1.0 was added to try to avoid overlap between chars
-                // TODO: see if there's a way to do that without the wasted space
-                (0, self.cur_y + self.advance_y as u32 + 1)
-            } else {
-                (self.cur_x, self.cur_y)
-            };
-            if y >= framebuffer_size.y {
-                panic!("Font cache full");
-            }
-            // Note: 1.0 was added to try to avoid overlap between chars
-            self.cur_x = x + glyph_texture_size.x + 1;
-            self.cur_y = y;
+            // 1 pixel of padding is added to avoid overlap between chars
+            let (page_index, loc) =
+                match self.allocate(context, glyph_texture_size + vec2(1, 1), key.variant.is_sdf())
+                {
+                    Some(allocation) => allocation,
+                    None => {
+                        warn!("Glyph {:?} is too large for the font cache", key.id);
+                        return None;
+                    }
+                };
+            let (x, y) = (loc.x, loc.y);
+            let page = &mut self.pages[page_index];
+            page.num_glyphs += 1;
+            page.used_area += glyph_texture_size.x as u64 * glyph_texture_size.y as u64;
+            let framebuffer_size = page.framebuffer.attachment.size();
 
             let mesh_builder = &mut self.cache_mesh_builder;
             mesh_builder.clear();
@@ -342,7 +1240,7 @@ impl FontInner {
             mesh_builder.triangle(1, 2, 3);
             self.cache_mesh.build_from(mesh_builder, MeshUsage::DynamicDraw);
             self.cache_mesh.draw(
-                &self.framebuffer,
+                &page.framebuffer,
                 &TextCacheUniforms {
                     matrix: ortho(
                         0.0,
@@ -357,20 +1255,109 @@ impl FontInner {
             );
 
             Some(CachedGlyphDisplay {
+                page: page_index,
                 loc: vec2(x as i32, y as i32),
                 size: glyph_texture_size.cast().unwrap(),
                 left: display.left,
                 top: display.top,
             })
-        } else {
-            None
+        });
+
+        self.glyphs.insert(key, CachedGlyph { display });
+    }
+
+    /// Finds space in the cache on a page of the given kind, and returns the index of the page and
+    /// the location on it.
+    ///
+    /// If no page has room, a page is added, or if there are already `max_pages` pages of that
+    /// kind, the least recently used one is cleared, evicting every glyph on it. Pages that have
+    /// been drawn from since the last call to `render_queued_chars` can't be cleared, so if they're
+    /// all in use a page is added anyway, and `trim_pages` removes it once the frame has been
+    /// rendered.
+    fn allocate(
+        &mut self,
+        context: &GlContext,
+        size: Vector2<u32>,
+        sdf: bool,
+    ) -> Option<(usize, Point2<u32>)> {
+        if size.x > ATLAS_PAGE_SIZE || size.y > ATLAS_PAGE_SIZE {
+            return None;
+        }
+        for (i, page) in self.pages.iter_mut().enumerate() {
+            if page.sdf != sdf {
+                continue;
+            }
+            if let Some(loc) = page.packer.pack(size) {
+                return Some((i, loc));
+            }
+        }
+
+        let frame = self.frame;
+        let lru_page = (0..self.pages.len())
+            .filter(|&i| self.pages[i].sdf == sdf && self.pages[i].last_used < frame)
+            .min_by_key(|&i| self.pages[i].last_used);
+        let kind_pages = self.pages.iter().filter(|page| page.sdf == sdf).count();
+        let page_index = match lru_page {
+            Some(i) if kind_pages >= self.max_pages => {
+                self.pages[i].clear(context);
+                self.glyphs.retain(|_, glyph| match &glyph.display {
+                    Some(display) => display.page != i,
+                    None => true,
+                });
+                self.evictions += 1;
+                i
+            }
+            _ => {
+                self.pages.push(AtlasPage::new(context, sdf));
+                self.pages.len() - 1
+            }
         };
+        let loc = self.pages[page_index].packer.pack(size).unwrap();
+        Some((page_index, loc))
+    }
+
+    /// Removes the least recently used pages, with their glyphs, until there are at most
+    /// `max_pages` of each kind. This must only be called right after rendering, when no glyphs
+    /// are queued.
+    fn trim_pages(&mut self) {
+        for &sdf in &[false, true] {
+            while self.pages.iter().filter(|page| page.sdf == sdf).count() > self.max_pages {
+                let lru_page = (0..self.pages.len())
+                    .filter(|&i| self.pages[i].sdf == sdf)
+                    .min_by_key(|&i| self.pages[i].last_used)
+                    .unwrap();
+                self.remove_page(lru_page);
+            }
+        }
+    }
 
-        self.glyphs.insert(c, CachedGlyph { display, advance_x: glyph.advance_x });
+    /// Removes a page and the glyphs on it, and moves the glyphs on later pages down an index.
+    fn remove_page(&mut self, index: usize) {
+        self.pages.remove(index);
+        self.glyphs.retain(|_, glyph| match &mut glyph.display {
+            Some(display) if display.page == index => false,
+            Some(display) => {
+                if display.page > index {
+                    display.page -= 1;
+                }
+                true
+            }
+            None => true,
+        });
+        self.evictions += 1;
     }
 
-    fn get_cached_glyph(&self, c: char) -> &CachedGlyph {
-        &self.glyphs[&c]
+    fn atlas_stats(&self) -> AtlasStats {
+        let page_area = ATLAS_PAGE_SIZE as u64 * ATLAS_PAGE_SIZE as u64;
+        AtlasStats {
+            pages: self.pages.len(),
+            max_pages: self.max_pages,
+            page_size: vec2(ATLAS_PAGE_SIZE, ATLAS_PAGE_SIZE),
+            glyphs: self.pages.iter().map(|page| page.num_glyphs).sum(),
+            used_area: self.pages.iter().map(|page| page.used_area).sum(),
+            total_area: self.pages.len() as u64 * page_area,
+            evictions: self.evictions,
+        }
     }
 
     pub fn render_queued_chars(&mut self, surface: &impl Surface) {
@@ -379,11 +1366,7 @@ impl FontInner {
         let matrix = Matrix4::from_nonuniform_scale(1.0, -1.0, 1.0)
             * ortho(0.0, surface_size.x as f32, 0.0, surface_size.y as f32, 0.0, 1.0);
 
-        self.render_mesh.build_from(&self.render_mesh_builder, MeshUsage::DynamicDraw);
-        self.render_mesh
-            .draw(surface, &TextRenderUniforms { matrix, tex: &self.framebuffer.attachment });
-
-        self.render_mesh_builder.clear();
+        self.render_queued_chars_custom_matrix(surface, matrix);
     }
 
     pub fn render_queued_chars_custom_matrix(
@@ -391,31 +1374,61 @@ impl FontInner {
         surface: &impl Surface,
         matrix: Matrix4<f32>,
     ) {
-        self.render_mesh.build_from(&self.render_mesh_builder, MeshUsage::DynamicDraw);
-        self.render_mesh
-            .draw(surface, &TextRenderUniforms { matrix, tex: &self.framebuffer.attachment });
-
-        self.render_mesh_builder.clear();
+        for layer in 0..NUM_TEXT_LAYERS {
+            for page in &mut self.pages {
+                if !page.queued_layers[layer] {
+                    continue;
+                }
+                page.queued_layers[layer] = false;
+                let uniforms = TextRenderUniforms { matrix, tex: &page.framebuffer.attachment };
+                if page.sdf {
+                    let mesh_builder = &mut page.sdf_render_mesh_builders[layer];
+                    self.sdf_render_mesh.build_from(mesh_builder, MeshUsage::DynamicDraw);
+                    self.sdf_render_mesh.draw(surface, &uniforms);
+                    mesh_builder.clear();
+                } else {
+                    let mesh_builder = &mut page.render_mesh_builders[layer];
+                    self.render_mesh.build_from(mesh_builder, MeshUsage::DynamicDraw);
+                    self.render_mesh.draw(surface, &uniforms);
+                    mesh_builder.clear();
+                }
+            }
+        }
+        self.trim_pages();
+        self.frame += 1;
     }
 
-    pub fn draw_string(
+    /// Queues shaped glyphs with their shadows and outlines, each in its own `TextLayer`.
+    pub fn draw_glyphs(
         &mut self,
         context: &GlContext,
-        str: &str,
+        font: &Font,
+        glyphs: &[ShapedGlyph],
         loc: Point2<f32>,
-        color: Color4,
+        style: &TextStyle,
         matrix: Matrix4<f32>,
     ) {
-        for c in str.chars() {
-            self.cache_glyph(context, c);
+        let outline_width = style.outline.map(|outline| outline.width);
+        let mut layers = vec![];
+        if let Some(shadow) = style.shadow {
+            let paint = GlyphPaint { color: shadow.color, outline_width, layer: TextLayer::Shadow };
+            layers.push((shadow.offset, paint));
+        }
+        if let Some(outline) = style.outline {
+            let paint = GlyphPaint {
+                color: outline.color,
+                outline_width: Some(outline.width),
+                layer: TextLayer::Outline,
+            };
+            layers.push((vec2(0.0, 0.0), paint));
         }
+        let paint = GlyphPaint { color: style.color, outline_width: None, layer: TextLayer::Fill };
+        layers.push((vec2(0.0, 0.0), paint));
 
-        let mut x_pos = 0;
-        for (a, b) in str.chars().zip(str.chars().skip(1).map(Some).chain(iter::once(None))) {
-            self.draw_char(context, a, loc + vec2(x_pos as f32, 0.0), color, matrix);
-            if let Some(b) = b {
-                // TODO: remove cast? Or else either floor/round
-                x_pos += self.horiz_advance_between(a, b) as i32;
+        for (offset, paint) in layers {
+            for glyph in glyphs {
+                let glyph_loc = loc + offset + glyph.pos;
+                self.draw_glyph(context, font, (glyph.face, glyph.id), glyph_loc, paint, matrix);
             }
         }
     }
@@ -423,107 +1436,254 @@ impl FontInner {
     pub fn draw_char(
         &mut self,
         context: &GlContext,
+        font: &Font,
         c: char,
         loc: Point2<f32>,
-        color: Color4,
+        style: &TextStyle,
         matrix: Matrix4<f32>,
     ) {
-        self.cache_glyph(context, c);
-        let glyph = self.get_cached_glyph(c);
+        let face = font.metrics.face_index(c);
+        let id = font.metrics.faces[face].font.glyph(c).id();
+        let glyph = ShapedGlyph { face, id, pos: vec2(0.0, 0.0) };
+        self.draw_glyphs(context, font, &[glyph], loc, style, matrix);
+    }
+
+    /// Queues a glyph, given as the index of the font it's from and its ID in that font.
+    fn draw_glyph(
+        &mut self,
+        context: &GlContext,
+        font: &Font,
+        (face, id): (usize, GlyphId),
+        loc: Point2<f32>,
+        paint: GlyphPaint,
+        matrix: Matrix4<f32>,
+    ) {
+        let metrics = &*font.metrics;
+        let color = paint.color;
+        // Shadows and outlines of distance field glyphs are drawn in a single color by the
+        // shader, without the font's own effect
+        let sdf = font.sdf.map(|effect| match paint.layer {
+            TextLayer::Fill => effect,
+            TextLayer::Shadow | TextLayer::Outline => SdfEffect {
+                outline_width: paint.outline_width.unwrap_or(0.0),
+                outline_color: color,
+                ..SdfEffect::default()
+            },
+        });
+        let sdf = sdf.as_ref();
+        let (loc, variant) = match sdf {
+            Some(_) => (vec2(loc.x, loc.y + metrics.ascent), GlyphVariant::Sdf),
+            None => {
+                // Bitmaps are drawn at whole pixels, with the fraction of a pixel that's left over
+                // rendered into the bitmap
+                let x = loc.x.floor();
+                let offset = ((loc.x - x) * SUBPIXEL_OFFSETS as f32).round() as u8;
+                let (x, offset) =
+                    if offset == SUBPIXEL_OFFSETS { (x + 1.0, 0) } else { (x, offset) };
+                let variant = match paint.outline_width {
+                    Some(width) => {
+                        GlyphVariant::Dilated(offset, width.round().clamp(1.0, 255.0) as u8)
+                    }
+                    None => GlyphVariant::Bitmap(offset),
+                };
+                (vec2(x, (loc.y + metrics.ascent).round()), variant)
+            }
+        };
+        let face = &metrics.faces[face];
+        let font_id = if variant.is_sdf() { None } else { Some(metrics.id) };
+        let key = GlyphKey { font: font_id, face: face.id, id, variant };
+        self.cache_glyph(context, metrics, face, key);
+        let glyph = &self.glyphs[&key];
         if let Some(display) = &glyph.display {
-            let loc = vec2(loc.x as f32, loc.y as f32 + self.ascent as f32);
-            let framebuffer_size = self.framebuffer.attachment.size();
+            let page = &mut self.pages[display.page];
+            page.last_used = self.frame;
+            let layer = paint.layer as usize;
+            page.queued_layers[layer] = true;
+            let framebuffer_size = page.framebuffer.attachment.size();
             let tex_start = display.loc;
             let tex_end = tex_start + display.size;
             let tex_start_x = (tex_start.x as f32) / framebuffer_size.x as f32;
             let tex_start_y = (tex_start.y as f32) / framebuffer_size.y as f32;
             let tex_end_x = (tex_end.x as f32) / framebuffer_size.x as f32;
             let tex_end_y = (tex_end.y as f32) / framebuffer_size.y as f32;
-            let left = display.left as f32;
-            let top = display.top as f32;
-            let size: Vector2<f32> = display.size.cast().unwrap();
+            // Distance fields are generated at a fixed size and scaled to the font's size
+            let scale = if sdf.is_some() { metrics.size as f32 / SDF_SIZE } else { 1.0 };
+            let left = display.left as f32 * scale;
+            let top = display.top as f32 * scale;
+            let size: Vector2<f32> = display.size.cast::<f32>().unwrap() * scale;
+
+            let corners = [
+                (vec2(left, top), vec2(tex_start_x, tex_start_y)),
+                (vec2(left + size.x, top), vec2(tex_end_x, tex_start_y)),
+                (vec2(left + size.x, top + size.y), vec2(tex_end_x, tex_end_y)),
+                (vec2(left, top + size.y), vec2(tex_start_x, tex_end_y)),
+            ];
+            let pos = |offset: Vector2<f32>| {
+                to_vec2(matrix.transform_point(point3(loc.x + offset.x, loc.y + offset.y, 0.0)))
+            };
+            let clip_rect = self.clip_stack.last();
+            match sdf {
+                None => {
+                    let verts = corners
+                        .iter()
+                        .map(|&(offset, uv)| TextRenderVert { pos: pos(offset), uv, color })
+                        .collect();
+                    queue_convex_poly(&mut page.render_mesh_builders[layer], verts, clip_rect);
+                }
+                Some(effect) => {
+                    let distances = effect.distances(metrics.size);
+                    let verts = corners
+                        .iter()
+                        .map(|&(offset, uv)| SdfRenderVert {
+                            pos: pos(offset),
+                            uv,
+                            color,
+                            outline_color: effect.outline_color,
+                            glow_color: effect.glow_color,
+                            effect: distances,
+                        })
+                        .collect();
+                    queue_convex_poly(&mut page.sdf_render_mesh_builders[layer], verts, clip_rect);
+                }
+            }
+        }
+    }
+}
 
-            let mesh_builder = &mut self.render_mesh_builder;
+/// Widens a glyph's bitmap by `radius` pixels in every direction, with round corners. The result
+/// is `2 * radius` pixels wider and taller.
+fn dilate(bitmap: &[u8], size: Vector2<u32>, radius: u32) -> Vec<u8> {
+    let r = radius as i32;
+    // The coverage of each pixel within the radius, antialiased over the last pixel
+    let mut kernel = vec![];
+    for dy in -r..=r {
+        for dx in -r..=r {
+            let dist = ((dx * dx + dy * dy) as f32).sqrt();
+            let coverage = (radius as f32 + 0.5 - dist).clamp(0.0, 1.0);
+            if coverage > 0.0 {
+                kernel.push((dx, dy, coverage));
+            }
+        }
+    }
 
-            let vert_a = mesh_builder.vert(TextRenderVert {
-                pos: to_vec2(matrix.transform_point(point3(loc.x + left, loc.y + top, 0.0))),
-                uv: vec2(tex_start_x, tex_start_y),
-                color,
-            });
-            let vert_b = mesh_builder.vert(TextRenderVert {
-                pos: to_vec2(matrix.transform_point(point3(
-                    loc.x + left + size.x,
-                    loc.y + top,
-                    0.0,
-                ))),
-                uv: vec2(tex_end_x, tex_start_y),
-                color,
-            });
-            let vert_c = mesh_builder.vert(TextRenderVert {
-                pos: to_vec2(matrix.transform_point(point3(
-                    loc.x + left,
-                    loc.y + top + size.y,
-                    0.0,
-                ))),
-                uv: vec2(tex_start_x, tex_end_y),
-                color,
-            });
-            let vert_d = mesh_builder.vert(TextRenderVert {
-                pos: to_vec2(matrix.transform_point(point3(
-                    loc.x + left + size.x,
-                    loc.y + top + size.y,
-                    0.0,
-                ))),
-                uv: vec2(tex_end_x, tex_end_y),
-                color,
-            });
-            mesh_builder.triangle(vert_a, vert_b, vert_c);
-            mesh_builder.triangle(vert_b, vert_c, vert_d);
+    let new_size = size + vec2(radius * 2, radius * 2);
+    let mut dilated = vec![0; (new_size.x * new_size.y) as usize];
+    for y in 0..size.y as i32 {
+        for x in 0..size.x as i32 {
+            let value = bitmap[(y * size.x as i32 + x) as usize];
+            if value == 0 {
+                continue;
+            }
+            for &(dx, dy, coverage) in &kernel {
+                let i = ((y + r + dy) * new_size.x as i32 + x + r + dx) as usize;
+                dilated[i] = dilated[i].max((value as f32 * coverage) as u8);
+            }
         }
     }
+    dilated
+}
 
-    // Note: this requires the chars to already be cached
-    fn horiz_advance_between(&mut self, a: char, b: char) -> f32 {
-        let kerning = self.get_kerning(a, b);
-        let glyph = self.get_cached_glyph(a);
-        glyph.advance_x + kerning
+/// Adds a convex polygon to a mesh, clipped to `clip_rect` if there is one.
+fn queue_convex_poly<V: ClipVert + VertexData + Copy>(
+    mesh_builder: &mut MeshBuilder<V, Triangles>,
+    verts: Vec<V>,
+    clip_rect: Option<&Rect<i32>>,
+) {
+    let verts = match clip_rect {
+        Some(clip_rect) => clip_convex_poly(verts, clip_rect.cast().unwrap()),
+        None => verts,
+    };
+    if verts.len() < 3 {
+        return;
     }
 
-    // Note: this requires the char to already be cached
-    fn horiz_advance_after(&self, a: char) -> f32 {
-        self.get_cached_glyph(a).advance_x
+    let a = mesh_builder.vert(verts[0]);
+    let mut b = mesh_builder.vert(verts[1]);
+    for c in verts.into_iter().skip(2) {
+        let c = mesh_builder.vert(c);
+        mesh_builder.triangle(a, b, c);
+        b = c;
     }
+}
 
-    // Note: for a single char, this is the same as horiz_advance_after
-    pub fn string_width(&mut self, context: &GlContext, str: &str) -> f32 {
-        for c in str.chars() {
-            self.cache_glyph(context, c);
-        }
-        if str.is_empty() {
-            return 0.0;
-        }
+/// Caches and renders the glyphs of any number of `Font`s.
+///
+/// Fonts that share a `TextRenderer` share its shader programs and glyph atlas, and everything
+/// they queue is rendered together, with one draw call per atlas page. Clip rects also apply to
+/// all of them.
+///
+/// This is cheap to clone; clones refer to the same renderer.
+#[derive(Clone)]
+pub struct TextRenderer {
+    inner: Rc<RefCell<TextRendererInner>>,
+}
 
-        let mut width = 0.0;
-        let mut iterator_a = str.chars();
-        let mut iterator_b = str.chars().skip(1);
-        loop {
-            match iterator_b.next() {
-                None => {
-                    width += self.horiz_advance_after(iterator_a.next().unwrap());
-                    break;
-                }
-                Some(b) => {
-                    let a = iterator_a.next().unwrap();
-                    width += self.horiz_advance_between(a, b);
-                }
-            }
-        }
-        width
+impl TextRenderer {
+    pub fn new(context: &GlContext) -> Self {
+        Self { inner: Rc::new(RefCell::new(TextRendererInner::new(context))) }
+    }
+
+    /// Renders all characters that have been queued by fonts using this renderer.
+    ///
+    /// This should typically be called once per frame to minimize the number of draw calls.
+    pub fn render_queued(&self, surface: &impl Surface) {
+        self.inner.borrow_mut().render_queued_chars(surface);
     }
 
-    // TODO: return Vec2<f32>? Or should string_width return i32?
-    pub fn string_size(&mut self, context: &GlContext, str: &str) -> Vector2<i32> {
-        vec2(self.string_width(context, str) as i32, self.advance_y)
+    /// Renders all characters that have been queued by fonts using this renderer.
+    ///
+    /// This allows a matrix to be specified which will be used instead of a standard orthographic
+    /// projection. This can be useful for rendering text in a game world rather than as part of a
+    /// GUI.
+    ///
+    /// This should typically be called once per frame to minimize the number of draw calls.
+    pub fn render_queued_custom_matrix(&self, surface: &impl Surface, matrix: Matrix4<f32>) {
+        self.inner.borrow_mut().render_queued_chars_custom_matrix(surface, matrix);
+    }
+
+    /// Returns statistics about the texture atlas glyphs are cached in.
+    pub fn atlas_stats(&self) -> AtlasStats {
+        self.inner.borrow().atlas_stats()
+    }
+
+    /// Sets the number of pages the glyph cache can grow to before it starts evicting glyphs. Each
+    /// page is a 1024x1024 texture. Bitmap and distance field glyphs are kept on separate pages,
+    /// and each kind can have this many. Defaults to 4.
+    ///
+    /// Glyphs are evicted a page at a time: when the cache is full, the least recently used page
+    /// is cleared, along with every glyph on it. If a single frame draws more glyphs than fit in
+    /// `max_pages` pages, extra pages are added for that frame and removed after it's rendered.
+    pub fn set_max_atlas_pages(&self, max_pages: usize) {
+        assert!(max_pages > 0);
+        self.inner.borrow_mut().max_pages = max_pages;
+    }
+
+    /// Restricts all characters queued from now on to the given rect, intersected with the current
+    /// clip rect if there is one. Each call must be paired with a call to `pop_clip_rect`.
+    ///
+    /// The rect is in the same coordinate space as the positions passed to `draw_string_f32` after
+    /// the matrix is applied.
+    pub fn push_clip_rect(&self, rect: Rect<i32>) {
+        let mut inner = self.inner.borrow_mut();
+        let rect = match inner.clip_stack.last() {
+            Some(clip_rect) => intersect_rects(*clip_rect, rect),
+            None => rect,
+        };
+        inner.clip_stack.push(rect);
+    }
+
+    /// Undoes the most recent call to `push_clip_rect`.
+    pub fn pop_clip_rect(&self) {
+        self.inner
+            .borrow_mut()
+            .clip_stack
+            .pop()
+            .expect("pop_clip_rect called without a matching push_clip_rect");
+    }
+
+    /// Returns true if both renderers are clones of the same renderer.
+    pub fn ptr_eq(&self, other: &TextRenderer) -> bool {
+        Rc::ptr_eq(&self.inner, &other.inner)
     }
 }
 
@@ -531,28 +1691,118 @@ impl FontInner {
 ///
 /// All distance units are pixels, from the top-left corner of the screen.
 ///
-/// Internally this uses a cache to store previously rendered characters.
-///
-/// This is expensive to create, so try to create only one instance per font/size combination.
+/// Glyphs are cached and rendered by a `TextRenderer`. To render several fonts or sizes with as
+/// few draw calls as possible, create them with `with_renderer` or `with_size` so they share one.
 #[derive(Clone)]
 pub struct Font {
-    inner: Rc<RefCell<FontInner>>,
+    metrics: Rc<FontMetrics>,
+    // None if the font was created with `new_headless`
+    renderer: Option<TextRenderer>,
+    // None if the font is drawn with bitmaps
+    sdf: Option<SdfEffect>,
 }
 
 impl Font {
     /// Creates a new `Font` from a `Vec` containing the contents of a `ttf` file.
+    ///
+    /// The font gets its own `TextRenderer`.
     pub fn new(context: &GlContext, data: Vec<u8>, size: u32) -> Self {
-        Self { inner: Rc::new(RefCell::new(FontInner::new(context, data, size))) }
+        Self::with_renderer(&TextRenderer::new(context), data, size)
+    }
+
+    /// Creates a new `Font` from a `Vec` containing the contents of a `ttf` file, which is rendered
+    /// by `renderer`.
+    pub fn with_renderer(renderer: &TextRenderer, data: Vec<u8>, size: u32) -> Self {
+        Self {
+            metrics: Rc::new(FontMetrics::new(data, size)),
+            renderer: Some(renderer.clone()),
+            sdf: None,
+        }
+    }
+
+    /// Creates a `Font` that can measure text but not draw it.
+    ///
+    /// This doesn't need a `GlContext`, so it can be used to compute layouts in tests. Any attempt
+    /// to draw with it will panic.
+    pub fn new_headless(data: Vec<u8>, size: u32) -> Self {
+        Self { metrics: Rc::new(FontMetrics::new(data, size)), renderer: None, sdf: None }
+    }
+
+    /// Creates a headless `Font` in which every character is `advance_x` pixels wide and lines are
+    /// `size` pixels apart. See `FontMetrics::new_fixed`.
+    ///
+    /// Layouts computed with it don't depend on any `ttf` file, so tests can assert exact rects.
+    pub fn new_fixed(advance_x: f32, size: u32) -> Self {
+        Self {
+            metrics: Rc::new(FontMetrics::new_fixed(advance_x, size)),
+            renderer: None,
+            sdf: None,
+        }
+    }
+
+    /// Returns the same font at a different size, using the same `TextRenderer`.
+    pub fn with_size(&self, size: u32) -> Self {
+        Self {
+            metrics: Rc::new(self.metrics.with_size(size)),
+            renderer: self.renderer.clone(),
+            sdf: self.sdf,
+        }
+    }
+
+    /// Returns the same font drawn with signed distance fields rather than bitmaps, using the same
+    /// `TextRenderer`.
+    ///
+    /// Distance field glyphs are generated once at a fixed size and scaled to the font's size,
+    /// and are shared with the font's other sizes. They stay sharp when scaled or rotated by the
+    /// matrix passed to `draw_string_f32` or `render_queued_custom_matrix`, and they can be drawn
+    /// with an `SdfEffect`. Small text is slightly softer than with bitmaps.
+    pub fn sdf(&self) -> Self {
+        self.with_sdf_effect(SdfEffect::default())
+    }
+
+    /// Returns the same font drawn with signed distance fields and the given outline and glow. See
+    /// `sdf`.
+    pub fn with_sdf_effect(&self, effect: SdfEffect) -> Self {
+        Self { metrics: self.metrics.clone(), renderer: self.renderer.clone(), sdf: Some(effect) }
+    }
+
+    /// Returns the effect this font is drawn with if it uses signed distance fields, or `None` if
+    /// it uses bitmaps.
+    pub fn sdf_effect(&self) -> Option<SdfEffect> {
+        self.sdf
+    }
+
+    /// Returns the renderer this font's glyphs are cached and rendered by. This panics if the font
+    /// is headless.
+    pub fn renderer(&self) -> &TextRenderer {
+        self.renderer.as_ref().expect("Can't draw with a headless Font")
     }
 
-    /// Renders all characters that have been drawn with `draw_string` or `draw_char`.
+    fn inner(&self) -> &RefCell<TextRendererInner> {
+        &self.renderer().inner
+    }
+
+    /// Returns the metrics used to measure text in this font.
+    pub fn metrics(&self) -> &FontMetrics {
+        &self.metrics
+    }
+
+    /// Converts a line of text to positioned glyphs, which can be used to place a caret or find the
+    /// character at a position. See `ShapedText`.
+    pub fn shape(&self, text: &str) -> ShapedText {
+        self.metrics.shape(text)
+    }
+
+    /// Renders all characters that have been drawn with `draw_string` or `draw_char`, along with
+    /// those drawn by other fonts that share this font's `TextRenderer`.
     ///
     /// This should typically be called once per frame to minimize the number of draw calls.
     pub fn render_queued(&self, surface: &impl Surface) {
-        self.inner.borrow_mut().render_queued_chars(surface);
+        self.renderer().render_queued(surface);
     }
 
-    /// Renders all characters that have been drawn with `draw_string` or `draw_char`.
+    /// Renders all characters that have been drawn with `draw_string` or `draw_char`, along with
+    /// those drawn by other fonts that share this font's `TextRenderer`.
     ///
     /// This allows a matrix to be specified which will be used instead of a standard orthographic
     /// projection. This can be useful for rendering text in a game world rather than as part of a
@@ -560,27 +1810,58 @@ impl Font {
     ///
     /// This should typically be called once per frame to minimize the number of draw calls.
     pub fn render_queued_custom_matrix(&self, surface: &impl Surface, matrix: Matrix4<f32>) {
-        self.inner.borrow_mut().render_queued_chars_custom_matrix(surface, matrix);
+        self.renderer().render_queued_custom_matrix(surface, matrix);
+    }
+
+    /// Returns statistics about the texture atlas this font's glyphs are cached in. The atlas is
+    /// shared by all fonts using the same `TextRenderer`.
+    pub fn atlas_stats(&self) -> AtlasStats {
+        self.renderer().atlas_stats()
+    }
+
+    /// Restricts all characters queued from now on to the given rect. This applies to all fonts
+    /// that share this font's `TextRenderer`; see `TextRenderer::push_clip_rect`.
+    pub fn push_clip_rect(&self, rect: Rect<i32>) {
+        self.renderer().push_clip_rect(rect);
+    }
+
+    /// Undoes the most recent call to `push_clip_rect`.
+    pub fn pop_clip_rect(&self) {
+        self.renderer().pop_clip_rect();
     }
 
     /// Queues a string for drawing. To render all queued characters, call `render_queued_chars`.
-    pub fn draw_string(&self, context: &GlContext, str: &str, loc: Point2<i32>, color: Color4) {
+    ///
+    /// The style can be a `Color4`, or a `TextStyle` to draw the text with an outline or shadow.
+    pub fn draw_string(
+        &self,
+        context: &GlContext,
+        str: &str,
+        loc: Point2<i32>,
+        style: impl Into<TextStyle>,
+    ) {
         self.draw_string_f32(
             context,
             str,
             point2(loc.x as f32, loc.y as f32),
-            color,
+            style,
             Matrix4::identity(),
         );
     }
 
     /// Queues a character to be drawn. To render all queued characters, call `render_queued_chars`.
-    pub fn draw_char(&self, context: &GlContext, c: char, loc: Point2<i32>, color: Color4) {
+    pub fn draw_char(
+        &self,
+        context: &GlContext,
+        c: char,
+        loc: Point2<i32>,
+        style: impl Into<TextStyle>,
+    ) {
         self.draw_char_f32(
             context,
             c,
             point2(loc.x as f32, loc.y as f32),
-            color,
+            style,
             Matrix4::identity(),
         );
     }
@@ -591,10 +1872,12 @@ impl Font {
         context: &GlContext,
         str: &str,
         loc: Point2<f32>,
-        color: Color4,
+        style: impl Into<TextStyle>,
         matrix: Matrix4<f32>,
     ) {
-        self.inner.borrow_mut().draw_string(context, str, loc, color, matrix);
+        let shaped = self.shape(str);
+        let style = style.into();
+        self.inner().borrow_mut().draw_glyphs(context, self, &shaped.glyphs, loc, &style, matrix);
     }
 
     /// Queues a character to be drawn. To render all queued characters, call `render_queued_chars`.
@@ -603,29 +1886,248 @@ impl Font {
         context: &GlContext,
         c: char,
         loc: Point2<f32>,
-        color: Color4,
+        style: impl Into<TextStyle>,
         matrix: Matrix4<f32>,
     ) {
-        self.inner.borrow_mut().draw_char(context, c, loc, color, matrix);
+        self.inner().borrow_mut().draw_char(context, self, c, loc, &style.into(), matrix);
+    }
+
+    /// Queues text that was shaped with this font for drawing, with the start of its line at
+    /// `loc`. To render all queued characters, call `render_queued_chars`.
+    pub fn draw_shaped(
+        &self,
+        context: &GlContext,
+        shaped: &ShapedText,
+        loc: Point2<f32>,
+        style: impl Into<TextStyle>,
+    ) {
+        self.inner().borrow_mut().draw_glyphs(
+            context,
+            self,
+            &shaped.glyphs,
+            loc,
+            &style.into(),
+            Matrix4::identity(),
+        );
+    }
+
+    /// Queues text that was laid out with this font for drawing, with the top-left of the layout
+    /// at `loc`. To render all queued characters, call `render_queued_chars`.
+    pub fn draw_layout(
+        &self,
+        context: &GlContext,
+        layout: &TextLayout,
+        loc: Point2<f32>,
+        style: impl Into<TextStyle>,
+    ) {
+        let style = style.into();
+        for line in layout.lines() {
+            self.draw_shaped(context, &line.shaped, loc + vec2(0.0, line.y), style);
+        }
     }
 
     /// Returns the width of a rendered string in pixels.
-    pub fn string_width(&self, context: &GlContext, str: &str) -> f32 {
-        self.inner.borrow_mut().string_width(context, str)
+    ///
+    /// Widths are cached, so measuring the same strings every frame is cheap. See
+    /// `width_cache_stats`.
+    pub fn string_width(&self, str: &str) -> f32 {
+        self.metrics.string_width(str)
+    }
+
+    /// Lays out text as with `TextLayout::new`, reusing the result if the same text was recently
+    /// laid out with the same maximum width. See `layout_cache_stats`.
+    pub fn layout(&self, text: &str, max_width: Option<f32>) -> Rc<TextLayout> {
+        let key = (text.to_owned(), max_width.map(f32::to_bits));
+        if let Some(layout) = self.metrics.layouts.borrow_mut().get(&key) {
+            return layout;
+        }
+        let layout = Rc::new(TextLayout::new(self, text, max_width));
+        self.metrics.layouts.borrow_mut().insert(key, layout.clone());
+        layout
+    }
+
+    /// Returns statistics about the cache of measured string widths. The cache is shared by all
+    /// clones of this font, but not by the same font at other sizes.
+    pub fn width_cache_stats(&self) -> TextCacheStats {
+        self.metrics.width_cache_stats()
+    }
+
+    /// Returns statistics about the cache of text laid out by `layout`. The cache is shared by all
+    /// clones of this font, but not by the same font at other sizes.
+    pub fn layout_cache_stats(&self) -> TextCacheStats {
+        self.metrics.layout_cache_stats()
+    }
+
+    /// Sets the number of string widths and the number of layouts this font caches. Defaults to
+    /// 1024 of each.
+    pub fn set_text_cache_capacity(&self, capacity: usize) {
+        self.metrics.set_text_cache_capacity(capacity);
     }
 
-    /// Returns the size of a rendered string in pixels.
-    pub fn string_size(&self, context: &GlContext, str: &str) -> Vector2<i32> {
-        self.inner.borrow_mut().string_size(context, str)
+    /// Returns the size of a rendered string in pixels, with the width rounded up so that the
+    /// string fits in it.
+    pub fn string_size(&self, str: &str) -> Vector2<i32> {
+        self.metrics.string_size(str)
     }
 
     /// Returns the font size.
     pub fn size(&self) -> u32 {
-        self.inner.borrow().size
+        self.metrics.size
+    }
+
+    /// Hashes everything about the font that affects the size of text: which font it is, its
+    /// size, and its tab width.
+    pub(crate) fn layout_hash(&self) -> u64 {
+        let mut hasher = FnvHasher::default();
+        (self.metrics.id, self.metrics.size, self.metrics.tab_width().to_bits()).hash(&mut hasher);
+        hasher.finish()
     }
 
     pub fn advance_y(&self) -> i32 {
-        self.inner.borrow().advance_y
+        self.metrics.advance_y
+    }
+
+    /// Returns the distance from the top of a line to its baseline.
+    pub fn ascent(&self) -> f32 {
+        self.metrics.ascent
+    }
+
+    /// Returns where a decoration is drawn, from the font's `post` and `OS/2` tables.
+    pub fn decoration_metrics(&self, decoration: TextDecoration) -> DecorationMetrics {
+        self.metrics.decoration_metrics(decoration)
+    }
+
+    /// Draws an underline or strikethrough `width` pixels long under or through text that was
+    /// drawn at `loc`.
+    pub fn draw_decoration(
+        &self,
+        draw_2d: &mut Draw2d,
+        decoration: TextDecoration,
+        loc: Point2<f32>,
+        width: f32,
+        color: Color4,
+    ) {
+        let metrics = self.decoration_metrics(decoration);
+        let y = loc.y + self.ascent() + metrics.offset;
+        draw_2d.draw_line(point2(loc.x, y), point2(loc.x + width, y), color, metrics.thickness);
+    }
+
+    /// Returns the distance between tab stops in pixels.
+    pub fn tab_width(&self) -> f32 {
+        self.metrics.tab_width()
+    }
+
+    /// Sets the distance between tab stops in pixels for this font and its clones. A '\t' advances
+    /// to the next multiple of this distance from the start of the line. Defaults to the width of
+    /// 8 spaces.
+    ///
+    /// Widths less than 1 pixel, including NaN, are treated as 1 pixel.
+    pub fn set_tab_width(&self, tab_width: f32) {
+        self.metrics.set_tab_width(tab_width);
+    }
+
+    /// Returns the position of the next tab stop after `x`.
+    pub(crate) fn next_tab_stop(&self, x: f32) -> f32 {
+        self.metrics.next_tab_stop(x)
+    }
+
+    /// Shortens a line of text so that it's at most `max_width` pixels wide, by replacing as many
+    /// grapheme clusters at its end as needed with an ellipsis. Text that already fits is returned
+    /// as is.
+    ///
+    /// If even the ellipsis doesn't fit, this returns an empty string.
+    pub fn truncate<'a>(&self, text: &'a str, max_width: f32) -> Cow<'a, str> {
+        if self.string_width(text) <= max_width {
+            return Cow::Borrowed(text);
+        }
+        let ellipsis = if self.metrics.has_glyph('…') { "…" } else { "..." };
+        let max_width = max_width - self.string_width(ellipsis);
+        if max_width < 0.0 {
+            return Cow::Borrowed("");
+        }
+        // Measuring every prefix would flush the width cache
+        let measurer = UncachedMeasurer(&self.metrics);
+        let boundaries: Vec<usize> =
+            text.grapheme_indices(true).map(|(i, _)| i).chain(Some(text.len())).collect();
+        // Binary search for the most clusters that fit. None of them always fit, and all of them
+        // don't.
+        let (mut fits, mut too_wide) = (0, boundaries.len() - 1);
+        while too_wide - fits > 1 {
+            let mid = (fits + too_wide) / 2;
+            if measurer.string_width(&text[..boundaries[mid]]) <= max_width {
+                fits = mid;
+            } else {
+                too_wide = mid;
+            }
+        }
+        Cow::Owned(format!("{}{}", text[..boundaries[fits]].trim_end(), ellipsis))
+    }
+}
+
+impl TextMeasurer for Font {
+    fn string_width(&self, str: &str) -> f32 {
+        self.metrics.string_width(str)
+    }
+
+    fn advance_y(&self) -> i32 {
+        self.metrics.advance_y
+    }
+}
+
+/// An ordered list of fonts that are drawn as one, such as a Latin font followed by fonts for
+/// emoji or CJK characters.
+///
+/// Each character is drawn from the first font that has a glyph for it. Everything else, including
+/// the size, the line metrics, the `TextRenderer`, and whether signed distance fields are used,
+/// comes from the first font.
+#[derive(Clone)]
+pub struct FontFamily {
+    fonts: Vec<Font>,
+    /// The fonts combined into one, which is built when a font is added so that every call to
+    /// `font` shares its glyphs and caches.
+    combined: Font,
+}
+
+impl FontFamily {
+    pub fn new(primary: &Font) -> Self {
+        Self { fonts: vec![primary.clone()], combined: primary.clone() }
+    }
+
+    /// Adds a font to take glyphs from when none of the previous fonts have them.
+    pub fn fallback(mut self, font: &Font) -> Self {
+        self.fonts.push(font.clone());
+        let primary = &self.fonts[0];
+        // A font with fixed metrics has every glyph, so it never falls back
+        if primary.metrics.fixed_advance.is_none() {
+            let faces =
+                self.fonts.iter().flat_map(|font| font.metrics.faces.iter().cloned()).collect();
+            let metrics = FontMetrics::from_faces(faces, primary.size());
+            metrics.tab_width.set(primary.tab_width());
+            self.combined = Font {
+                metrics: Rc::new(metrics),
+                renderer: primary.renderer.clone(),
+                sdf: primary.sdf,
+            };
+        }
+        self
+    }
+
+    pub fn fonts(&self) -> &[Font] {
+        &self.fonts
+    }
+
+    /// Returns the first font with a glyph for `c`, or the primary font if none of them have one.
+    pub fn font_for_char(&self, c: char) -> &Font {
+        self.fonts.iter().find(|font| font.metrics.has_glyph(c)).unwrap_or(&self.fonts[0])
+    }
+
+    /// Returns a `Font` that draws and measures text using the whole family. It can be used
+    /// anywhere a `Font` can, such as in a `Theme`, and `Font::with_size` keeps the fallbacks.
+    ///
+    /// Every call returns a clone of the same font, so they share cached glyphs, widths and
+    /// layouts.
+    pub fn font(&self) -> Font {
+        self.combined.clone()
     }
 }
 
@@ -633,3 +2135,50 @@ impl Font {
 fn to_vec2(vec: Point3<f32>) -> Vector2<f32> {
     vec2(vec.x, vec.y)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn font_family_returns_the_same_font() {
+        let family = FontFamily::new(&Font::new_fixed(8.0, 16)).fallback(&Font::new_fixed(4.0, 8));
+        let (a, b) = (family.font(), family.font());
+        assert!(Rc::ptr_eq(&a.metrics, &b.metrics));
+        a.string_width("abc");
+        assert_eq!(b.width_cache_stats().entries, 1);
+    }
+
+    #[test]
+    fn wrap_text_breaks_at_whitespace_and_inside_long_words() {
+        let font = Font::new_fixed(8.0, 16);
+        let text = "ab cd\tefghijk\nl";
+        let lines: Vec<_> = wrap_text(&font, text, 40.0).into_iter().map(|x| &text[x]).collect();
+        assert_eq!(lines, ["ab cd\t", "efghi", "jk", "l"]);
+        // The tab after "a" ends 8 spaces from the start of the line, which doesn't fit
+        assert_eq!(wrap_text(&font, "a\tb", 64.0), [0..2, 2..3]);
+    }
+
+    #[test]
+    fn invalid_tab_widths_are_clamped() {
+        let font = Font::new_fixed(8.0, 16);
+        font.set_tab_width(0.0);
+        assert_eq!(font.tab_width(), 1.0);
+        font.set_tab_width(f32::NAN);
+        assert_eq!(font.tab_width(), 1.0);
+        font.set_tab_width(32.0);
+        assert_eq!(font.string_width("a\tb"), 40.0);
+    }
+
+    #[test]
+    fn changing_the_tab_width_keeps_cache_statistics() {
+        let font = Font::new_fixed(8.0, 16);
+        font.string_width("a\tb");
+        font.string_width("a\tb");
+        font.set_tab_width(32.0);
+        let stats = font.width_cache_stats();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (1, 1, 0));
+        // The old width isn't reused
+        assert_eq!(font.string_width("a\tb"), 40.0);
+    }
+}
@@ -0,0 +1,192 @@
+use fnv::FnvHashMap;
+use std::borrow::Borrow;
+use std::hash::Hash;
+
+/// The number of entries each of a font's text caches holds by default.
+pub(crate) const DEFAULT_TEXT_CACHE_CAPACITY: usize = 1024;
+
+/// Statistics about one of the caches a font keeps of measured text.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct TextCacheStats {
+    /// The number of lookups that found a cached value.
+    pub hits: u64,
+    /// The number of lookups that had to measure or lay out the text.
+    pub misses: u64,
+    /// The number of values currently in the cache.
+    pub entries: usize,
+    /// The most values the cache holds before it starts evicting the least recently used ones.
+    pub capacity: usize,
+}
+
+impl TextCacheStats {
+    /// Returns the fraction of lookups that found a cached value, from 0 to 1.
+    pub fn hit_rate(&self) -> f32 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.hits as f32 / lookups as f32
+        }
+    }
+}
+
+/// A map that holds at most `capacity` values, evicting the least recently used one to make room
+/// for a new one.
+///
+/// When the cache is full, the least recently used quarter of its values are evicted at once, so
+/// that finding them is rare.
+pub(crate) struct LruCache<K, V> {
+    entries: FnvHashMap<K, (V, u64)>,
+    capacity: usize,
+    /// Incremented on every lookup and insertion, and stored with the value that was used.
+    clock: u64,
+    hits: u64,
+    misses: u64,
+}
+
+impl<K: Eq + Hash, V: Clone> LruCache<K, V> {
+    pub fn new(capacity: usize) -> Self {
+        Self { entries: FnvHashMap::default(), capacity, clock: 0, hits: 0, misses: 0 }
+    }
+
+    /// Returns a copy of the value for `key` and marks it as the most recently used, or returns
+    /// `None` if it isn't cached.
+    pub fn get<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.clock += 1;
+        match self.entries.get_mut(key) {
+            Some((value, last_used)) => {
+                *last_used = self.clock;
+                self.hits += 1;
+                Some(value.clone())
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    pub fn insert(&mut self, key: K, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() >= self.capacity {
+            // Evicting a quarter of the values at a time keeps insertion amortized constant time
+            self.evict_to(self.capacity - 1 - self.capacity / 4);
+        }
+        self.clock += 1;
+        self.entries.insert(key, (value, self.clock));
+    }
+
+    /// Changes the number of values the cache holds, evicting values if there are too many.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.evict_to(capacity);
+    }
+
+    /// Removes every value, but keeps counting hits and misses from where they were.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn stats(&self) -> TextCacheStats {
+        TextCacheStats {
+            hits: self.hits,
+            misses: self.misses,
+            entries: self.entries.len(),
+            capacity: self.capacity,
+        }
+    }
+
+    /// Evicts the least recently used values until at most `len` are left.
+    fn evict_to(&mut self, len: usize) {
+        if self.entries.len() <= len {
+            return;
+        }
+        let mut last_used: Vec<u64> =
+            self.entries.values().map(|(_, last_used)| *last_used).collect();
+        let num_evicted = self.entries.len() - len;
+        // Each lookup and insertion increments the clock, so no two values have the same timestamp
+        let (_, &mut newest_evicted, _) = last_used.select_nth_unstable(num_evicted - 1);
+        self.entries.retain(|_, (_, last_used)| *last_used > newest_evicted);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_hits_and_misses() {
+        let mut cache = LruCache::new(4);
+        assert_eq!(cache.get("a"), None);
+        cache.insert("a".to_owned(), 1);
+        assert_eq!(cache.get("a"), Some(1));
+        assert_eq!(cache.get("a"), Some(1));
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.entries, stats.capacity), (2, 1, 1, 4));
+        assert_eq!(stats.hit_rate(), 2.0 / 3.0);
+    }
+
+    #[test]
+    fn evicts_the_least_recently_used_quarter_when_full() {
+        let mut cache = LruCache::new(8);
+        for i in 0..8 {
+            cache.insert(i, i);
+        }
+        // Using the oldest values keeps them
+        assert_eq!(cache.get(&0), Some(0));
+        assert_eq!(cache.get(&1), Some(1));
+
+        // A quarter of the values are evicted, plus one to make room for the new value
+        cache.insert(8, 8);
+        assert_eq!(cache.stats().entries, 6);
+        for i in [0, 1, 5, 6, 7, 8].iter() {
+            assert_eq!(cache.get(i), Some(*i));
+        }
+        assert_eq!((cache.get(&2), cache.get(&3), cache.get(&4)), (None, None, None));
+    }
+
+    #[test]
+    fn shrinking_the_capacity_evicts_the_least_recently_used_values() {
+        let mut cache = LruCache::new(4);
+        for i in 0..4 {
+            cache.insert(i, i);
+        }
+        cache.get(&0);
+        cache.set_capacity(2);
+        assert_eq!(cache.stats().entries, 2);
+        assert_eq!((cache.get(&0), cache.get(&3)), (Some(0), Some(3)));
+        assert_eq!((cache.get(&1), cache.get(&2)), (None, None));
+    }
+
+    #[test]
+    fn zero_capacity_caches_nothing() {
+        let mut cache = LruCache::new(0);
+        cache.insert(1, 1);
+        assert_eq!(cache.get(&1), None);
+        assert_eq!(cache.stats().entries, 0);
+
+        let mut cache = LruCache::new(2);
+        cache.insert(1, 1);
+        cache.set_capacity(0);
+        assert_eq!(cache.get(&1), None);
+    }
+
+    #[test]
+    fn clearing_keeps_the_statistics() {
+        let mut cache = LruCache::new(4);
+        cache.insert(1, 1);
+        cache.get(&1);
+        cache.get(&2);
+        cache.clear();
+        assert_eq!(cache.get(&1), None);
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (1, 2, 0));
+    }
+}
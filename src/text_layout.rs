@@ -0,0 +1,237 @@
+use cgmath::*;
+use std::ops::Range;
+use webgl_wrapper::*;
+
+use crate::shaping::*;
+use crate::text::*;
+
+/// A line of a `TextLayout`.
+#[derive(Clone, Debug)]
+pub struct LayoutLine {
+    /// The byte range of the line in the text, not including the '\n' that ends it.
+    pub range: Range<usize>,
+    /// True if the line was wrapped, so the next line starts where this one ends.
+    pub wrapped: bool,
+    /// The shaped text of the line. Its byte indices are relative to the start of the line.
+    pub shaped: ShapedText,
+    /// The distance from the top of the layout to the top of the line.
+    pub y: f32,
+}
+
+/// Text that has been broken into lines and shaped, so that it can be drawn and hit-tested
+/// without measuring it again.
+///
+/// All positions are relative to the top-left of the layout, and all indices are byte indices of
+/// the text it was created from.
+#[derive(Clone, Debug)]
+pub struct TextLayout {
+    lines: Vec<LayoutLine>,
+    line_height: f32,
+}
+
+impl TextLayout {
+    /// Lays out text in the given font. Lines are broken at '\n', and if `max_width` is given,
+    /// they're wrapped to fit it as with `wrap_text`.
+    pub fn new(font: &Font, text: &str, max_width: Option<f32>) -> Self {
+        let ranges = match max_width {
+            // Wrapping measures every prefix of each line, which would flush the width cache
+            Some(max_width) => wrap_text(&UncachedMeasurer(font.metrics()), text, max_width),
+            None => {
+                let mut start = 0;
+                text.split('\n')
+                    .map(|line| {
+                        let range = start..start + line.len();
+                        start = range.end + 1;
+                        range
+                    })
+                    .collect()
+            }
+        };
+        let line_height = font.advance_y() as f32;
+        let lines = ranges
+            .iter()
+            .enumerate()
+            .map(|(i, range)| LayoutLine {
+                range: range.clone(),
+                wrapped: matches!(ranges.get(i + 1), Some(next) if next.start == range.end),
+                shaped: font.shape(&text[range.clone()]),
+                y: i as f32 * line_height,
+            })
+            .collect();
+        TextLayout { lines, line_height }
+    }
+
+    pub fn lines(&self) -> &[LayoutLine] {
+        &self.lines
+    }
+
+    pub fn line_height(&self) -> f32 {
+        self.line_height
+    }
+
+    /// Returns the width of the widest line and the total height of the lines.
+    pub fn size(&self) -> Vector2<f32> {
+        let width = self.lines.iter().map(|line| line.shaped.width).fold(0.0, f32::max);
+        vec2(width, self.lines.len() as f32 * self.line_height)
+    }
+
+    /// Returns the index of the line the caret is on when it's at `index`. At the end of a wrapped
+    /// line, the caret is shown at the start of the next line.
+    pub fn line_at(&self, index: usize) -> usize {
+        self.lines.iter().rposition(|line| line.range.start <= index).unwrap_or(0)
+    }
+
+    /// Returns the last position on a line that the caret can be placed at. This is the end of
+    /// the line unless it was wrapped, in which case it's the start of the last cluster.
+    pub fn line_end(&self, line: usize) -> usize {
+        let line = &self.lines[line];
+        match line.shaped.clusters.iter().map(|cluster| cluster.range.start).max() {
+            Some(last_start) if line.wrapped => line.range.start + last_start,
+            _ => line.range.end,
+        }
+    }
+
+    /// Returns the position closest to a point.
+    pub fn hit_test(&self, point: Point2<f32>) -> usize {
+        let line = (point.y / self.line_height).floor().max(0.0) as usize;
+        self.hit_test_line(line.min(self.lines.len() - 1), point.x)
+    }
+
+    /// Returns the position on the given line closest to `x`.
+    pub fn hit_test_line(&self, line: usize, x: f32) -> usize {
+        let layout_line = &self.lines[line];
+        let pos = layout_line.range.start + layout_line.shaped.pos_at_x(x);
+        pos.min(self.line_end(line))
+    }
+
+    /// Returns the position of the caret after moving it one cluster left or right on screen from
+    /// `index`, which moves backwards through right-to-left text. At either end of a line, it
+    /// moves to the neighboring line in logical order.
+    pub fn move_visually(&self, index: usize, right: bool) -> usize {
+        let line = &self.lines[self.line_at(index)];
+        match line.shaped.visual_neighbor(index - line.range.start, right) {
+            Some(pos) => line.range.start + pos,
+            None if right => self.next_boundary(index),
+            None => self.prev_boundary(index),
+        }
+    }
+
+    /// Returns the start of the cluster before `index` in logical order, where a line break counts
+    /// as a cluster. This is where Backspace deletes to.
+    pub fn prev_boundary(&self, index: usize) -> usize {
+        let line_index = self.line_at(index);
+        let line = &self.lines[line_index];
+        if index > line.range.start {
+            return line.range.start + line.shaped.prev_cluster_boundary(index - line.range.start);
+        }
+        match line_index.checked_sub(1).map(|i| &self.lines[i]) {
+            Some(prev) if prev.wrapped => {
+                prev.range.start + prev.shaped.prev_cluster_boundary(prev.range.len())
+            }
+            Some(prev) => prev.range.end,
+            None => 0,
+        }
+    }
+
+    /// Returns the end of the cluster after `index` in logical order, where a line break counts as
+    /// a cluster. This is where Delete deletes to.
+    pub fn next_boundary(&self, index: usize) -> usize {
+        let line_index = self.line_at(index);
+        let line = &self.lines[line_index];
+        if index < line.range.end {
+            line.range.start + line.shaped.next_cluster_boundary(index - line.range.start)
+        } else {
+            self.lines.get(line_index + 1).map_or(index, |next| next.range.start)
+        }
+    }
+
+    /// Returns the rect the caret is drawn in when it's at `index`. The rect has no width.
+    pub fn caret_rect(&self, index: usize) -> Rect<f32> {
+        let line = &self.lines[self.line_at(index)];
+        let x = line.shaped.caret_x(index - line.range.start);
+        Rect::new(point2(x, line.y), point2(x, line.y + self.line_height))
+    }
+
+    /// Returns the rects covered by the text in a byte range, from top to bottom.
+    pub fn selection_rects(&self, range: Range<usize>) -> Vec<Rect<f32>> {
+        let mut rects = vec![];
+        for line in &self.lines {
+            let start = range.start.max(line.range.start);
+            let end = range.end.min(line.range.end);
+            if start >= end {
+                continue;
+            }
+            let spans = line.shaped.range_spans(start - line.range.start..end - line.range.start);
+            for span in spans {
+                rects.push(Rect::new(
+                    point2(span.start, line.y),
+                    point2(span.end, line.y + self.line_height),
+                ));
+            }
+        }
+        rects
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lays out "ab cd\nef" with 8 pixel wide characters, wrapped after "ab ".
+    fn layout() -> TextLayout {
+        TextLayout::new(&Font::new_fixed(8.0, 16), "ab cd\nef", Some(24.0))
+    }
+
+    #[test]
+    fn lines_are_wrapped_and_broken() {
+        let layout = layout();
+        let lines: Vec<_> =
+            layout.lines().iter().map(|line| (line.range.clone(), line.wrapped, line.y)).collect();
+        assert_eq!(lines, [(0..3, true, 0.0), (3..5, false, 16.0), (6..8, false, 32.0)]);
+        assert_eq!(layout.size(), vec2(24.0, 48.0));
+    }
+
+    #[test]
+    fn hit_test_past_the_end_of_a_line() {
+        let layout = layout();
+        assert_eq!(layout.hit_test(point2(100.0, 20.0)), 5);
+        // The end of a wrapped line is before the space it was wrapped at
+        assert_eq!(layout.hit_test(point2(100.0, 4.0)), 2);
+        assert_eq!(layout.hit_test(point2(-5.0, 100.0)), 6);
+        assert_eq!(layout.hit_test(point2(11.0, 36.0)), 7);
+        assert_eq!(layout.hit_test(point2(13.0, 36.0)), 8);
+    }
+
+    #[test]
+    fn caret_at_the_end_of_a_wrapped_line() {
+        let layout = layout();
+        // The end of the wrapped line is the start of the next one
+        assert_eq!(layout.line_at(3), 1);
+        assert_eq!(layout.caret_rect(3), Rect::new(point2(0.0, 16.0), point2(0.0, 32.0)));
+        assert_eq!(layout.line_end(0), 2);
+        assert_eq!(layout.caret_rect(2), Rect::new(point2(16.0, 0.0), point2(16.0, 16.0)));
+        assert_eq!(layout.caret_rect(5), Rect::new(point2(16.0, 16.0), point2(16.0, 32.0)));
+    }
+
+    #[test]
+    fn selection_across_a_wrapped_line() {
+        let layout = layout();
+        assert_eq!(
+            layout.selection_rects(1..7),
+            [
+                Rect::new(point2(8.0, 0.0), point2(24.0, 16.0)),
+                Rect::new(point2(0.0, 16.0), point2(16.0, 32.0)),
+                Rect::new(point2(0.0, 32.0), point2(8.0, 48.0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn boundaries_cross_line_breaks() {
+        let layout = layout();
+        assert_eq!((layout.prev_boundary(3), layout.next_boundary(2)), (2, 3));
+        // The '\n' counts as a cluster
+        assert_eq!((layout.prev_boundary(6), layout.next_boundary(5)), (5, 6));
+        assert_eq!((layout.prev_boundary(0), layout.next_boundary(8)), (0, 8));
+    }
+}
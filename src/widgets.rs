@@ -1,22 +1,114 @@
 use cgmath::*;
 use fnv::*;
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
 use std::mem;
+use std::ops::Range;
+use std::rc::Rc;
+use unicode_segmentation::UnicodeSegmentation;
 use wasm_stopwatch::*;
 use webgl_wrapper::*;
 
+use crate::clipboard::*;
 use crate::color::*;
 use crate::draw_2d::*;
 use crate::event::*;
 use crate::gui::*;
+use crate::rich_text::*;
+use crate::text::*;
+use crate::text_layout::*;
+
+/// Rounds a size in pixels up, so that text measured with fractional advances isn't clipped.
+fn ceil_size(size: Vector2<f32>) -> Vector2<i32> {
+    vec2(size.x.ceil() as i32, size.y.ceil() as i32)
+}
 
 pub struct Label {
     id: WidgetId,
     text: String,
+    ellipsis: bool,
+    max_width: Option<i32>,
+    underline: bool,
+    strikethrough: bool,
+    outline: Option<TextOutline>,
+    shadow: Option<TextShadow>,
 }
 
 impl Label {
     pub fn new(text: &str) -> Box<Self> {
-        Box::new(Label { id: WidgetId::new(), text: text.to_owned() })
+        Box::new(Label {
+            id: WidgetId::new(),
+            text: text.to_owned(),
+            ellipsis: false,
+            max_width: None,
+            underline: false,
+            strikethrough: false,
+            outline: None,
+            shadow: None,
+        })
+    }
+
+    /// Draws an outline around the text. Defaults to `theme.label_outline`.
+    pub fn outline(mut self: Box<Self>, width: f32, color: Color4) -> Box<Self> {
+        self.outline = Some(TextOutline { width, color });
+        self
+    }
+
+    /// Draws a drop shadow behind the text. Defaults to `theme.label_shadow`.
+    pub fn shadow(mut self: Box<Self>, offset: Vector2<f32>, color: Color4) -> Box<Self> {
+        self.shadow = Some(TextShadow { offset, color });
+        self
+    }
+
+    /// Truncates lines that are too wide for the label's rect with an ellipsis, rather than
+    /// clipping them.
+    pub fn ellipsis(mut self: Box<Self>) -> Box<Self> {
+        self.ellipsis = true;
+        self
+    }
+
+    /// Truncates lines that are wider than `max_width` with an ellipsis, so the label's text is
+    /// never wider than that. Its outline and shadow can extend past it. This implies `ellipsis`.
+    pub fn max_width(mut self: Box<Self>, max_width: i32) -> Box<Self> {
+        self.ellipsis = true;
+        self.max_width = Some(max_width);
+        self
+    }
+
+    pub fn underline(mut self: Box<Self>) -> Box<Self> {
+        self.underline = true;
+        self
+    }
+
+    pub fn strikethrough(mut self: Box<Self>) -> Box<Self> {
+        self.strikethrough = true;
+        self
+    }
+
+    fn style(&self, theme: &Theme) -> TextStyle {
+        TextStyle {
+            color: theme.label_color,
+            outline: self.outline.or(theme.label_outline),
+            shadow: self.shadow.or(theme.label_shadow),
+        }
+    }
+
+    /// Returns the space the label's outline and shadow need before and after its text, in whole
+    /// pixels, so that they aren't clipped by its rect.
+    fn style_margins(&self, theme: &Theme) -> (Vector2<i32>, Vector2<i32>) {
+        let (before, after) = self.style(theme).extent();
+        (ceil_size(before), ceil_size(after))
+    }
+
+    /// Returns the text with each line truncated to fit in `width` if the label has an ellipsis.
+    fn drawn_text(&self, font: &Font, width: i32) -> Cow<'_, str> {
+        let max_width = self.max_width.map(|max_width| max_width.min(width)).unwrap_or(width);
+        if !self.ellipsis || font.layout(&self.text, None).size().x <= max_width as f32 {
+            return Cow::Borrowed(&self.text);
+        }
+        let lines: Vec<_> =
+            self.text.split('\n').map(|line| font.truncate(line, max_width as f32)).collect();
+        Cow::Owned(lines.join("\n"))
     }
 }
 
@@ -25,27 +117,66 @@ impl Widget for Label {
         self.id
     }
 
+    fn clips_to_rect(&self) -> bool {
+        true
+    }
+
     fn draw(
         &self,
         context: &GlContext,
         _surface: &dyn Surface,
         rect: Rect<i32>,
         theme: &Theme,
-        _draw_2d: &mut Draw2d,
+        draw_2d: &mut Draw2d,
         _cursor_pos: Option<Point2<i32>>,
         _is_active: bool,
     ) {
-        theme.font.draw_string(context, &self.text, rect.start, theme.label_color);
+        let (before, after) = self.style_margins(theme);
+        let text = self.drawn_text(&theme.font, rect.size().x - before.x - after.x);
+        let layout = theme.font.layout(&text, None);
+        let loc = (rect.start + before).cast().unwrap();
+        theme.font.draw_layout(context, &layout, loc, self.style(theme));
+
+        let mut decorations = vec![];
+        if self.underline {
+            decorations.push(TextDecoration::Underline);
+        }
+        if self.strikethrough {
+            decorations.push(TextDecoration::Strikethrough);
+        }
+        for line in layout.lines() {
+            let width = theme.font.string_width(text[line.range.clone()].trim_end());
+            for &decoration in &decorations {
+                let line_loc = loc + vec2(0.0, line.y);
+                theme.font.draw_decoration(draw_2d, decoration, line_loc, width, theme.label_color);
+            }
+        }
+    }
+
+    fn baseline(&self, theme: &Theme) -> Option<i32> {
+        Some(self.style_margins(theme).0.y + theme.font.ascent().round() as i32)
+    }
+
+    fn layout_hash(&self) -> Option<u64> {
+        let outline = self.outline.map(|outline| outline.width.to_bits());
+        let shadow =
+            self.shadow.map(|shadow| (shadow.offset.x.to_bits(), shadow.offset.y.to_bits()));
+        Some(hash_layout(&("Label", &self.text, self.ellipsis, self.max_width, outline, shadow)))
     }
 
     fn min_size(
         &self,
-        context: &GlContext,
         theme: &Theme,
         _min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
         _window_size: Vector2<i32>,
     ) -> Vector2<i32> {
-        theme.font.string_size(context, &self.text)
+        let size = ceil_size(theme.font.layout(&self.text, None).size());
+        let size = match self.max_width {
+            Some(max_width) => vec2(size.x.min(max_width), size.y),
+            None => size,
+        };
+        let (before, after) = self.style_margins(theme);
+        size + before + after
     }
 }
 
@@ -87,11 +218,9 @@ impl Component for Button {
                     pressed = true;
                     break;
                 }
-                Event::KeyDown(key) => {
-                    if key.key == "Enter" || key.key == " " {
-                        pressed = true;
-                        break;
-                    }
+                Event::KeyDown(key) if key.key == "Enter" || key.key == " " => {
+                    pressed = true;
+                    break;
                 }
                 _ => (),
             }
@@ -106,6 +235,10 @@ impl Widget for Button {
         self.id
     }
 
+    fn clips_to_rect(&self) -> bool {
+        true
+    }
+
     fn is_component(&self) -> bool {
         true
     }
@@ -138,14 +271,21 @@ impl Widget for Button {
         );
     }
 
+    fn baseline(&self, theme: &Theme) -> Option<i32> {
+        Some(theme.font.ascent().round() as i32 + 1)
+    }
+
+    fn layout_hash(&self) -> Option<u64> {
+        Some(hash_layout(&("Button", &self.text)))
+    }
+
     fn min_size(
         &self,
-        context: &GlContext,
         theme: &Theme,
         _min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
         _window_size: Vector2<i32>,
     ) -> Vector2<i32> {
-        theme.font.string_size(context, &self.text) + vec2(4, 2)
+        theme.font.string_size(&self.text) + vec2(4, 2)
     }
 }
 
@@ -179,14 +319,17 @@ impl Widget for NoFill {
     ) {
     }
 
+    fn layout_hash(&self) -> Option<u64> {
+        Some(hash_layout(&"NoFill"))
+    }
+
     fn min_size(
         &self,
-        _context: &GlContext,
         _theme: &Theme,
         min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
         _window_size: Vector2<i32>,
     ) -> Vector2<i32> {
-        min_sizes[&self.child.id()]
+        min_size_of(self.child.id(), min_sizes)
     }
 
     fn children(&self) -> Vec<&dyn Widget> {
@@ -198,11 +341,12 @@ impl Widget for NoFill {
         rect: Rect<i32>,
         theme: &Theme,
         min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
-        widget_rects: &mut FnvHashMap<WidgetId, Rect<i32>>,
+        widget_rects: &mut WidgetRects,
     ) {
-        let min_size = min_sizes[&self.id()];
+        let min_size = min_size_of(self.id(), min_sizes);
         widget_rects.insert(self.id(), Rect::new(rect.start, rect.start + min_size));
-        self.child.compute_rects(
+        widget_compute_rects(
+            &*self.child,
             Rect::new(rect.start, rect.start + min_size),
             theme,
             min_sizes,
@@ -211,14 +355,86 @@ impl Widget for NoFill {
     }
 }
 
-pub struct Col {
+/// The direction a `Flex` lays out its children in.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Axis {
+    Horizontal,
+    Vertical,
+}
+
+impl Axis {
+    /// Returns the index of the axis's component in a vector.
+    fn index(self) -> usize {
+        match self {
+            Axis::Horizontal => 0,
+            Axis::Vertical => 1,
+        }
+    }
+}
+
+/// How a `Flex` positions the children on a line along its axis when they don't fill the line.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Justify {
+    Start,
+    Center,
+    End,
+    /// The first and last children are at the ends of the line, and the space left over is
+    /// split evenly between the others.
+    SpaceBetween,
+    /// The space left over is split evenly around each child, so the ends of the line get half as
+    /// much space as there is between children.
+    SpaceAround,
+}
+
+/// How a `Flex` positions each child across its axis, within the line the child is on.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum CrossAlign {
+    Start,
+    Center,
+    End,
+    /// Children are stretched to fill the line.
+    Stretch,
+    /// Children are moved so that the baselines of their first lines of text line up. Children
+    /// without text are placed at the start of the line. In a vertical `Flex`, this is the same
+    /// as `Start`.
+    Baseline,
+}
+
+/// Lays out its children in a row or column.
+///
+/// Space left over on a line is given to the children in proportion to their flex factors. If
+/// none of them are flexible, the children are positioned according to the `Justify` mode
+/// instead. If there isn't enough space, the children are shrunk towards their min sizes, unless
+/// the `Flex` wraps, in which case children that don't fit are moved onto a new line.
+pub struct Flex {
     id: WidgetId,
+    axis: Axis,
     children: Vec<(Box<dyn Widget>, f32)>,
+    justify: Justify,
+    align: CrossAlign,
+    gap: i32,
+    line_gap: i32,
+    wrap: bool,
 }
 
-impl Col {
-    pub fn new() -> Box<Self> {
-        Box::new(Col { id: WidgetId::new(), children: vec![] })
+/// Where a container puts each of its children, relative to its top left corner.
+struct Arrangement {
+    size: Vector2<i32>,
+    child_rects: Vec<Rect<i32>>,
+}
+
+impl Flex {
+    pub fn new(axis: Axis) -> Box<Self> {
+        Box::new(Flex {
+            id: WidgetId::new(),
+            axis,
+            children: vec![],
+            justify: Justify::Start,
+            align: CrossAlign::Stretch,
+            gap: 0,
+            line_gap: 0,
+            wrap: false,
+        })
     }
 
     /// Flex controls how to distribute unused space.
@@ -231,9 +447,274 @@ impl Col {
         self.children.extend(children.into_iter().map(|(a, b)| (b, a)));
         self
     }
+
+    /// Defaults to `Justify::Start`.
+    pub fn justify(mut self: Box<Self>, justify: Justify) -> Box<Self> {
+        self.justify = justify;
+        self
+    }
+
+    /// Defaults to `CrossAlign::Stretch`.
+    pub fn align(mut self: Box<Self>, align: CrossAlign) -> Box<Self> {
+        self.align = align;
+        self
+    }
+
+    /// Sets the space between neighboring children on a line.
+    pub fn gap(mut self: Box<Self>, gap: i32) -> Box<Self> {
+        self.gap = gap;
+        self
+    }
+
+    /// Sets the space between lines, if the `Flex` wraps.
+    pub fn line_gap(mut self: Box<Self>, line_gap: i32) -> Box<Self> {
+        self.line_gap = line_gap;
+        self
+    }
+
+    /// Moves children that don't fit on a line onto a new line, rather than shrinking them.
+    pub fn wrap(mut self: Box<Self>) -> Box<Self> {
+        self.wrap = true;
+        self
+    }
+
+    /// Returns a vector with the given components along and across the `Flex`'s axis.
+    fn axis_vec(&self, main: i32, cross: i32) -> Vector2<i32> {
+        match self.axis {
+            Axis::Horizontal => vec2(main, cross),
+            Axis::Vertical => vec2(cross, main),
+        }
+    }
+
+    /// Returns the constraints a child is measured with. `main_size` fixes the child's size along
+    /// the axis; otherwise it can be as large as it wants.
+    fn child_constraints(
+        &self,
+        child: &dyn Widget,
+        main_size: Option<i32>,
+        cross_size: Range<i32>,
+        min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+    ) -> BoxConstraints {
+        let (min_main, max_main) = match main_size {
+            Some(main_size) => (main_size, main_size),
+            None => {
+                (min_size_of(child.id(), min_sizes)[self.axis.index()], BoxConstraints::UNBOUNDED)
+            }
+        };
+        BoxConstraints::new(
+            self.axis_vec(min_main, cross_size.start),
+            self.axis_vec(max_main, cross_size.end),
+        )
+    }
+
+    /// Returns the ranges of children on each line.
+    fn lines(&self, main_sizes: &[i32], max_main: i32) -> Vec<Range<usize>> {
+        let wraps = self.wrap && max_main != BoxConstraints::UNBOUNDED;
+        let mut lines = vec![];
+        let mut line_start = 0;
+        let mut line_main = 0;
+        for (i, &main_size) in main_sizes.iter().enumerate() {
+            if wraps && i > line_start && line_main + self.gap + main_size > max_main {
+                lines.push(line_start..i);
+                line_start = i;
+            }
+            line_main = if i == line_start { main_size } else { line_main + self.gap + main_size };
+        }
+        lines.push(line_start..main_sizes.len());
+        lines
+    }
+
+    /// Lays out the children within the constraints.
+    fn arrange(
+        &self,
+        constraints: BoxConstraints,
+        theme: &Theme,
+        min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+    ) -> Arrangement {
+        let main = self.axis.index();
+        let cross = 1 - main;
+        let max_main = constraints.max[main];
+        // Children that will be stretched across a fixed size are measured at that size
+        let cross_range = if self.align == CrossAlign::Stretch
+            && constraints.min[cross] == constraints.max[cross]
+        {
+            constraints.max[cross]..constraints.max[cross]
+        } else {
+            0..constraints.max[cross]
+        };
+        let baseline_aligned = self.align == CrossAlign::Baseline && self.axis == Axis::Horizontal;
+
+        // Each child's natural size, which may change once its size along the axis is known
+        let mut sizes: Vec<Vector2<i32>> = self
+            .children
+            .iter()
+            .map(|(child, _flex)| {
+                let child_constraints =
+                    self.child_constraints(&**child, None, cross_range.clone(), min_sizes);
+                widget_layout(&**child, child_constraints, theme, min_sizes)
+            })
+            .collect();
+        let natural_mains: Vec<i32> = sizes.iter().map(|size| size[main]).collect();
+        let lines = self.lines(&natural_mains, max_main);
+
+        let mut line_crosses = vec![];
+        let mut line_baselines = vec![];
+        for line in &lines {
+            let children = &self.children[line.clone()];
+            let gaps = self.gap * (line.len() as i32 - 1).max(0);
+            let used: i32 = sizes[line.clone()].iter().map(|size| size[main]).sum::<i32>() + gaps;
+            let available = if max_main == BoxConstraints::UNBOUNDED {
+                used.max(constraints.min[main])
+            } else {
+                max_main
+            };
+            let free = available - used;
+            let total_flex: f32 = children.iter().map(|&(ref _child, flex)| flex).sum();
+            let changes = if free > 0 && total_flex > 0.0 {
+                let flexes: Vec<f32> = children.iter().map(|&(ref _child, flex)| flex).collect();
+                distribute(free, &flexes)
+            } else if free < 0 {
+                let shrinkable: Vec<f32> = children
+                    .iter()
+                    .zip(&sizes[line.clone()])
+                    .map(|((child, _flex), size)| {
+                        (size[main] - min_size_of(child.id(), min_sizes)[main]).max(0) as f32
+                    })
+                    .collect();
+                let total_shrinkable = shrinkable.iter().sum::<f32>() as i32;
+                distribute(-free.min(total_shrinkable), &shrinkable)
+                    .into_iter()
+                    .map(|shrink| -shrink)
+                    .collect()
+            } else {
+                vec![0; line.len()]
+            };
+            // Changing a child's size along the axis can change its size across it, like
+            // wrapped text getting taller as it gets narrower
+            for ((child, _flex), (size, change)) in
+                children.iter().zip(sizes[line.clone()].iter_mut().zip(changes))
+            {
+                if change != 0 {
+                    let main_size = size[main] + change;
+                    let child_constraints = self.child_constraints(
+                        &**child,
+                        Some(main_size),
+                        cross_range.clone(),
+                        min_sizes,
+                    );
+                    *size = widget_layout(&**child, child_constraints, theme, min_sizes);
+                }
+            }
+
+            let baseline = if baseline_aligned {
+                children.iter().filter_map(|(child, _flex)| child.baseline(theme)).max()
+            } else {
+                None
+            };
+            let line_cross = children
+                .iter()
+                .zip(&sizes[line.clone()])
+                .map(|((child, _flex), size)| match (baseline, child.baseline(theme)) {
+                    (Some(line_baseline), Some(child_baseline)) => {
+                        line_baseline - child_baseline + size[cross]
+                    }
+                    _ => size[cross],
+                })
+                .max()
+                .unwrap_or(0);
+            line_crosses.push(line_cross);
+            line_baselines.push(baseline);
+        }
+
+        // A single line fills the `Flex`, so children can be aligned within all of it
+        let total_cross: i32 =
+            line_crosses.iter().sum::<i32>() + self.line_gap * (lines.len() as i32 - 1).max(0);
+        if lines.len() == 1 {
+            line_crosses[0] = constraints.constrain(self.axis_vec(0, total_cross))[cross];
+        }
+
+        let mut child_rects = vec![Rect::new(point2(0, 0), point2(0, 0)); self.children.len()];
+        let mut content_main = 0;
+        let mut line_start = 0;
+        for ((line, line_cross), line_baseline) in
+            lines.iter().zip(line_crosses).zip(line_baselines)
+        {
+            let line_sizes = &sizes[line.clone()];
+            let gaps = self.gap * (line.len() as i32 - 1).max(0);
+            let used: i32 = line_sizes.iter().map(|size| size[main]).sum::<i32>() + gaps;
+            content_main = content_main.max(used);
+            let free = if max_main == BoxConstraints::UNBOUNDED {
+                (constraints.min[main] - used).max(0)
+            } else {
+                (max_main - used).max(0)
+            } as f32;
+            let count = line.len() as f32;
+            let (leading, between) = match self.justify {
+                Justify::Start => (0.0, 0.0),
+                Justify::Center => (free / 2.0, 0.0),
+                Justify::End => (free, 0.0),
+                Justify::SpaceBetween if line.len() > 1 => (0.0, free / (count - 1.0)),
+                Justify::SpaceBetween => (0.0, 0.0),
+                Justify::SpaceAround => (free / count / 2.0, free / count),
+            };
+            let mut next_main = 0;
+            for (i, size) in line_sizes.iter().enumerate() {
+                let child = &self.children[line.start + i].0;
+                let main_pos = next_main + (leading + between * i as f32).round() as i32;
+                next_main += size[main] + self.gap;
+                let (cross_pos, cross_size) = match self.align {
+                    CrossAlign::Start => (0, size[cross]),
+                    CrossAlign::Center => ((line_cross - size[cross]) / 2, size[cross]),
+                    CrossAlign::End => (line_cross - size[cross], size[cross]),
+                    CrossAlign::Stretch => (0, line_cross),
+                    CrossAlign::Baseline => match (line_baseline, child.baseline(theme)) {
+                        (Some(line_baseline), Some(child_baseline)) => {
+                            (line_baseline - child_baseline, size[cross])
+                        }
+                        _ => (0, size[cross]),
+                    },
+                };
+                let start = Point2::from_vec(self.axis_vec(main_pos, line_start + cross_pos));
+                child_rects[line.start + i] =
+                    Rect::new(start, start + self.axis_vec(size[main], cross_size));
+            }
+            line_start += line_cross + self.line_gap;
+        }
+
+        let total_flex: f32 = self.children.iter().map(|&(ref _child, flex)| flex).sum();
+        // Flexible or justified children use all the space they're given
+        let main_size = if max_main != BoxConstraints::UNBOUNDED
+            && (total_flex > 0.0 || self.justify != Justify::Start)
+        {
+            max_main
+        } else {
+            content_main
+        };
+        let size = constraints.constrain(self.axis_vec(main_size, total_cross));
+        Arrangement { size, child_rects }
+    }
 }
 
-impl Widget for Col {
+/// Splits `amount` into parts proportional to `weights`, rounded so that they add up to exactly
+/// `amount`.
+fn distribute(amount: i32, weights: &[f32]) -> Vec<i32> {
+    let total: f32 = weights.iter().sum();
+    let mut cumulative = 0.0;
+    let mut given = 0;
+    weights
+        .iter()
+        .map(|weight| {
+            cumulative += weight;
+            let end =
+                if total > 0.0 { (amount as f32 * cumulative / total).round() as i32 } else { 0 };
+            let part = end - given;
+            given = end;
+            part
+        })
+        .collect()
+}
+
+impl Widget for Flex {
     fn id(&self) -> WidgetId {
         self.id
     }
@@ -250,80 +731,100 @@ impl Widget for Col {
     ) {
     }
 
+    fn layout_hash(&self) -> Option<u64> {
+        let flexes: Vec<u32> =
+            self.children.iter().map(|&(ref _child, flex)| flex.to_bits()).collect();
+        let spacing = (self.gap, self.line_gap, self.wrap);
+        Some(hash_layout(&("Flex", self.axis, self.justify, self.align, spacing, flexes)))
+    }
+
     fn min_size(
         &self,
-        _context: &GlContext,
         _theme: &Theme,
         min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
         _window_size: Vector2<i32>,
     ) -> Vector2<i32> {
-        let mut min_size: Vector2<i32> = Vector2::zero();
+        let main = self.axis.index();
+        let cross = 1 - main;
+        let mut min_main = 0;
+        let mut min_cross = 0;
         for &(ref child, _flex) in &self.children {
-            let child_min_size = min_sizes[&child.id()];
-            min_size.x = min_size.x.max(child_min_size.x);
-            min_size.y += child_min_size.y;
+            let child_min_size = min_size_of(child.id(), min_sizes);
+            // A wrapping `Flex` can put each child on its own line
+            min_main = if self.wrap {
+                min_main.max(child_min_size[main])
+            } else {
+                min_main + child_min_size[main]
+            };
+            min_cross = min_cross.max(child_min_size[cross]);
         }
-        min_size
+        if !self.wrap {
+            min_main += self.gap * (self.children.len() as i32 - 1).max(0);
+        }
+        self.axis_vec(min_main, min_cross)
     }
 
     fn children(&self) -> Vec<&dyn Widget> {
         self.children.iter().map(|(child, _)| &**child as &dyn Widget).collect()
     }
 
+    fn layout(
+        &self,
+        constraints: BoxConstraints,
+        theme: &Theme,
+        min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+    ) -> Vector2<i32> {
+        self.arrange(constraints, theme, min_sizes).size
+    }
+
     fn compute_rects(
         &self,
         rect: Rect<i32>,
         theme: &Theme,
         min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
-        widget_rects: &mut FnvHashMap<WidgetId, Rect<i32>>,
+        widget_rects: &mut WidgetRects,
     ) {
-        let total_flex = self.children.iter().map(|&(ref _child, flex)| flex).sum();
-        let min_size = min_sizes[&self.id()];
-        let own_rect = if total_flex == 0.0 {
-            Rect::new(rect.start, rect.start + vec2(rect.size().x, min_size.y))
-        } else {
-            Rect::new(rect.start, rect.start + vec2(rect.size().x, rect.size().y))
-        };
-        widget_rects.insert(self.id(), own_rect);
-        let mut next_pos = rect.start;
-        let total_flex = if total_flex == 0.0 { 1.0 } else { total_flex };
-        let extra_space = rect.size().y - min_size.y;
-        for &(ref child, flex) in &self.children {
-            let child_min_size = min_sizes[&child.id()];
-            let widget_extra_space = (extra_space as f32 * flex / total_flex) as i32;
-            let widget_height = child_min_size.y + widget_extra_space;
-            let widget_rect = Rect::new(next_pos, next_pos + vec2(rect.size().x, widget_height));
-            next_pos.y += widget_height;
-            child.compute_rects(widget_rect, theme, min_sizes, widget_rects);
+        // The `Flex` fills the space across its axis, but only takes up as much space along it
+        // as its children need
+        let cross_size = rect.size()[1 - self.axis.index()];
+        let constraints = BoxConstraints::new(self.axis_vec(0, cross_size), rect.size());
+        let arrangement = self.arrange(constraints, theme, min_sizes);
+        widget_rects.insert(self.id(), Rect::new(rect.start, rect.start + arrangement.size));
+        for ((child, _flex), child_rect) in self.children.iter().zip(arrangement.child_rects) {
+            let child_rect = Rect::new(
+                rect.start + child_rect.start.to_vec(),
+                rect.start + child_rect.end.to_vec(),
+            );
+            widget_compute_rects(&**child, child_rect, theme, min_sizes, widget_rects);
         }
     }
 }
 
-pub struct Row {
-    id: WidgetId,
-    children: Vec<(Box<dyn Widget>, f32)>,
+/// Lays out its children from top to bottom, stretched to its width. This is a vertical `Flex`.
+pub struct Col {
+    flex: Flex,
 }
 
-impl Row {
+impl Col {
     pub fn new() -> Box<Self> {
-        Box::new(Row { id: WidgetId::new(), children: vec![] })
+        Box::new(Col { flex: *Flex::new(Axis::Vertical) })
     }
 
     /// Flex controls how to distribute unused space.
     pub fn child(mut self: Box<Self>, flex: f32, child: Box<dyn Widget>) -> Box<Self> {
-        self.children.push((child, flex));
+        self.flex.children.push((child, flex));
         self
     }
 
     pub fn children(mut self: Box<Self>, children: Vec<(f32, Box<dyn Widget>)>) -> Box<Self> {
-        self.children.extend(children.into_iter().map(|(a, b)| (b, a)));
+        self.flex.children.extend(children.into_iter().map(|(a, b)| (b, a)));
         self
     }
 }
 
-impl Widget for Row {
+impl Widget for Col {
     fn id(&self) -> WidgetId {
-        self.id
+        self.flex.id()
     }
 
     fn draw(
@@ -338,24 +839,30 @@ impl Widget for Row {
     ) {
     }
 
+    fn layout_hash(&self) -> Option<u64> {
+        self.flex.layout_hash()
+    }
+
     fn min_size(
         &self,
-        _context: &GlContext,
-        _theme: &Theme,
+        theme: &Theme,
         min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
-        _window_size: Vector2<i32>,
+        window_size: Vector2<i32>,
     ) -> Vector2<i32> {
-        let mut min_size: Vector2<i32> = Vector2::zero();
-        for &(ref child, _flex) in &self.children {
-            let child_min_size = min_sizes[&child.id()];
-            min_size.y = min_size.y.max(child_min_size.y);
-            min_size.x += child_min_size.x;
-        }
-        min_size
+        self.flex.min_size(theme, min_sizes, window_size)
     }
 
     fn children(&self) -> Vec<&dyn Widget> {
-        self.children.iter().map(|(child, _)| &**child as &dyn Widget).collect()
+        Widget::children(&self.flex)
+    }
+
+    fn layout(
+        &self,
+        constraints: BoxConstraints,
+        theme: &Theme,
+        min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+    ) -> Vector2<i32> {
+        self.flex.layout(constraints, theme, min_sizes)
     }
 
     fn compute_rects(
@@ -363,193 +870,383 @@ impl Widget for Row {
         rect: Rect<i32>,
         theme: &Theme,
         min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
-        widget_rects: &mut FnvHashMap<WidgetId, Rect<i32>>,
+        widget_rects: &mut WidgetRects,
     ) {
-        let total_flex = self.children.iter().map(|&(ref _child, flex)| flex).sum();
-        let min_size = min_sizes[&self.id()];
-        let own_rect = if total_flex == 0.0 {
-            Rect::new(rect.start, rect.start + vec2(min_size.x, rect.size().y))
-        } else {
-            Rect::new(rect.start, rect.start + vec2(rect.size().x, rect.size().y))
-        };
-        widget_rects.insert(self.id(), own_rect);
-        let mut next_pos = rect.start;
-        let total_flex = if total_flex == 0.0 { 1.0 } else { total_flex };
-        let extra_space = rect.size().x - min_size.x;
-        for &(ref child, flex) in &self.children {
-            let child_min_size = min_sizes[&child.id()];
-            let widget_extra_space = (extra_space as f32 * flex / total_flex) as i32;
-            let widget_width = child_min_size.x + widget_extra_space;
-            let widget_rect = Rect::new(next_pos, next_pos + vec2(widget_width, rect.size().y));
-            next_pos.x += widget_width;
-            child.compute_rects(widget_rect, theme, min_sizes, widget_rects);
-        }
+        self.flex.compute_rects(rect, theme, min_sizes, widget_rects);
     }
 }
 
-#[derive(Clone)]
-pub struct TextBox {
-    text: String,
-    lines: Vec<String>,
-    text_color: Color4,
-    id: WidgetId,
+/// Lays out its children from left to right, stretched to its height. This is a horizontal `Flex`.
+pub struct Row {
+    flex: Flex,
 }
 
-impl TextBox {
-    pub fn new(text: &str) -> Box<Self> {
-        let mut res = Box::new(TextBox {
-            text: text.to_owned(),
-            lines: vec![],
-            text_color: Color4::BLACK,
-            id: WidgetId::new(),
-        });
-        res.update_lines();
-        res
+impl Row {
+    pub fn new() -> Box<Self> {
+        Box::new(Row { flex: *Flex::new(Axis::Horizontal) })
     }
 
-    pub fn text_color(mut self: Box<Self>, color: Color4) -> Box<Self> {
-        self.text_color = color;
+    /// Flex controls how to distribute unused space.
+    pub fn child(mut self: Box<Self>, flex: f32, child: Box<dyn Widget>) -> Box<Self> {
+        self.flex.children.push((child, flex));
         self
     }
 
-    fn update_lines(&mut self) {
-        self.lines = self.text.split('\n').map(|x| x.to_owned()).collect();
+    pub fn children(mut self: Box<Self>, children: Vec<(f32, Box<dyn Widget>)>) -> Box<Self> {
+        self.flex.children.extend(children.into_iter().map(|(a, b)| (b, a)));
+        self
     }
 }
 
-impl Widget for TextBox {
+impl Widget for Row {
     fn id(&self) -> WidgetId {
-        self.id
+        self.flex.id()
     }
 
     fn draw(
         &self,
-        context: &GlContext,
+        _context: &GlContext,
         _surface: &dyn Surface,
-        rect: Rect<i32>,
-        theme: &Theme,
+        _rect: Rect<i32>,
+        _theme: &Theme,
         _draw_2d: &mut Draw2d,
         _cursor_pos: Option<Point2<i32>>,
         _is_active: bool,
     ) {
-        let advance_y = theme.font.advance_y();
-        for (i, line) in self.lines.iter().enumerate() {
-            theme.font.draw_string(
-                context,
-                &line,
-                rect.start.cast().unwrap() + vec2(0, advance_y * i as i32),
-                self.text_color,
-            );
-        }
+    }
+
+    fn layout_hash(&self) -> Option<u64> {
+        self.flex.layout_hash()
     }
 
     fn min_size(
         &self,
-        context: &GlContext,
         theme: &Theme,
-        _min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
-        _window_size: Vector2<i32>,
+        min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+        window_size: Vector2<i32>,
     ) -> Vector2<i32> {
-        let max_width = self.lines.iter().map(|x| theme.font.string_width(context, x) as i32).max();
-        if let Some(max_width) = max_width {
-            vec2(max_width as i32, theme.font.advance_y() as i32 * self.lines.len() as i32)
-        } else {
-            vec2(0, 0)
-        }
+        self.flex.min_size(theme, min_sizes, window_size)
     }
-}
 
-// This is intended to be persistent, which is tricky since widgets have to own
-// their children, but you can clone it.
-#[derive(Clone)]
-pub struct MessageBox {
-    lines: Vec<(String, Color4)>,
-    max_lines: usize,
-    id: WidgetId,
-}
+    fn children(&self) -> Vec<&dyn Widget> {
+        Widget::children(&self.flex)
+    }
 
-impl MessageBox {
-    pub fn new(max_lines: usize) -> Box<Self> {
-        Box::new(MessageBox { lines: vec![], max_lines, id: WidgetId::new() })
-    }
-
-    pub fn add_line(&mut self, color: Color4, line: String) {
-        self.lines.push((line, color));
-        if self.lines.len() > self.max_lines {
-            self.lines.remove(0);
-        }
-    }
-}
-
-impl Widget for MessageBox {
-    fn id(&self) -> WidgetId {
-        self.id
+    fn layout(
+        &self,
+        constraints: BoxConstraints,
+        theme: &Theme,
+        min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+    ) -> Vector2<i32> {
+        self.flex.layout(constraints, theme, min_sizes)
     }
 
-    fn draw(
+    fn compute_rects(
         &self,
-        context: &GlContext,
-        _surface: &dyn Surface,
         rect: Rect<i32>,
         theme: &Theme,
-        _draw_2d: &mut Draw2d,
-        _cursor_pos: Option<Point2<i32>>,
-        _is_active: bool,
+        min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+        widget_rects: &mut WidgetRects,
     ) {
-        let advance_y = theme.font.advance_y();
-        for (i, &(ref line, color)) in self.lines.iter().enumerate() {
-            theme.font.draw_string(
-                context,
-                &line,
-                rect.start.cast().unwrap() + vec2(0, advance_y * i as i32),
-                color,
-            );
-        }
+        self.flex.compute_rects(rect, theme, min_sizes, widget_rects);
     }
+}
 
-    fn min_size(
-        &self,
-        context: &GlContext,
-        theme: &Theme,
-        _min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
-        _window_size: Vector2<i32>,
-    ) -> Vector2<i32> {
-        let max_width =
-            self.lines.iter().map(|x| theme.font.string_width(context, &x.0) as i32).max();
-        if let Some(max_width) = max_width {
-            vec2(max_width as i32, theme.font.advance_y() as i32 * self.lines.len() as i32)
-        } else {
-            vec2(0, 0)
+/// How a `Grid` sizes one of its rows or columns.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Track {
+    /// A fixed size in pixels.
+    Fixed(i32),
+    /// Large enough for the min size of each cell in the track.
+    Auto,
+    /// Large enough for each cell in the track, like `Auto`, plus a share of the space left over
+    /// in the grid, in proportion to the fractions of the other fractional tracks. A cell that
+    /// spans both fractional and other tracks only makes the fractional ones larger.
+    Fraction(f32),
+}
+
+/// Where a `Grid` puts a widget within its cell, along one axis.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum CellAlign {
+    Start,
+    Center,
+    End,
+    /// The widget fills the cell.
+    Stretch,
+}
+
+/// Where a widget goes in a `Grid`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct GridCell {
+    pub row: usize,
+    pub column: usize,
+    pub row_span: usize,
+    pub column_span: usize,
+    pub align_x: CellAlign,
+    pub align_y: CellAlign,
+}
+
+impl GridCell {
+    /// A cell in a single row and column that its widget is stretched to fill.
+    pub fn new(row: usize, column: usize) -> Self {
+        GridCell {
+            row,
+            column,
+            row_span: 1,
+            column_span: 1,
+            align_x: CellAlign::Stretch,
+            align_y: CellAlign::Stretch,
         }
     }
+
+    /// Makes the cell cover several rows and columns, starting at its row and column.
+    pub fn span(self, row_span: usize, column_span: usize) -> Self {
+        GridCell { row_span: row_span.max(1), column_span: column_span.max(1), ..self }
+    }
+
+    pub fn align(self, align_x: CellAlign, align_y: CellAlign) -> Self {
+        GridCell { align_x, align_y, ..self }
+    }
 }
 
-pub struct Overlap {
+/// Lays out its children in rows and columns, so that the columns line up across every row.
+///
+/// Rows and columns that aren't given a `Track` are `Track::Auto`. If the fixed tracks don't fit in
+/// the space the grid is given, the cells past its edges are clipped.
+pub struct Grid {
     id: WidgetId,
-    children: Vec<Box<dyn Widget>>,
+    columns: Vec<Track>,
+    rows: Vec<Track>,
+    children: Vec<(Box<dyn Widget>, GridCell)>,
+    column_gap: i32,
+    row_gap: i32,
 }
 
-impl Overlap {
-    pub fn new() -> Box<Self> {
-        Box::new(Overlap { id: WidgetId::new(), children: vec![] })
+impl Grid {
+    pub fn new(columns: Vec<Track>) -> Box<Self> {
+        Box::new(Grid {
+            id: WidgetId::new(),
+            columns,
+            rows: vec![],
+            children: vec![],
+            column_gap: 0,
+            row_gap: 0,
+        })
     }
 
-    pub fn child(mut self: Box<Self>, child: Box<dyn Widget>) -> Box<Self> {
-        self.children.push(child);
+    pub fn rows(mut self: Box<Self>, rows: Vec<Track>) -> Box<Self> {
+        self.rows = rows;
         self
     }
 
-    pub fn children(mut self: Box<Self>, children: Vec<Box<dyn Widget>>) -> Box<Self> {
-        self.children.extend(children.into_iter());
+    /// Adds a widget that fills a single cell.
+    pub fn child(self: Box<Self>, row: usize, column: usize, child: Box<dyn Widget>) -> Box<Self> {
+        self.cell(GridCell::new(row, column), child)
+    }
+
+    pub fn cell(mut self: Box<Self>, cell: GridCell, child: Box<dyn Widget>) -> Box<Self> {
+        self.children.push((child, cell));
+        self
+    }
+
+    /// Sets the space between neighboring columns and between neighboring rows.
+    pub fn gap(mut self: Box<Self>, column_gap: i32, row_gap: i32) -> Box<Self> {
+        self.column_gap = column_gap;
+        self.row_gap = row_gap;
         self
     }
+
+    /// Returns the given tracks, followed by `Track::Auto` for each track that a cell covers
+    /// beyond them.
+    fn tracks(&self, tracks: &[Track], cell_end: impl Fn(&GridCell) -> usize) -> Vec<Track> {
+        let count = self.children.iter().map(|(_child, cell)| cell_end(cell)).max().unwrap_or(0);
+        let mut tracks = tracks.to_vec();
+        if tracks.len() < count {
+            tracks.resize(count, Track::Auto);
+        }
+        tracks
+    }
+
+    fn column_tracks(&self) -> Vec<Track> {
+        self.tracks(&self.columns, |cell| cell.column + cell.column_span)
+    }
+
+    fn row_tracks(&self) -> Vec<Track> {
+        self.tracks(&self.rows, |cell| cell.row + cell.row_span)
+    }
+
+    /// Lays out the children within the constraints.
+    fn arrange(
+        &self,
+        constraints: BoxConstraints,
+        theme: &Theme,
+        min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+    ) -> Arrangement {
+        let column_spans: Vec<TrackSpan> = self
+            .children
+            .iter()
+            .map(|(child, cell)| {
+                (cell.column, cell.column_span, min_size_of(child.id(), min_sizes).x)
+            })
+            .collect();
+        let widths =
+            track_sizes(&self.column_tracks(), &column_spans, self.column_gap, constraints.max.x);
+        let column_starts = track_starts(&widths, self.column_gap);
+
+        // The height of a cell's widget can depend on the width of the cell
+        let row_spans: Vec<TrackSpan> = self
+            .children
+            .iter()
+            .map(|(child, cell)| {
+                let width = span_size(&widths, cell.column, cell.column_span, self.column_gap);
+                let area = vec2(width, BoxConstraints::UNBOUNDED);
+                let child_constraints =
+                    cell_constraints(cell, area, min_size_of(child.id(), min_sizes));
+                let height = widget_layout(&**child, child_constraints, theme, min_sizes).y;
+                (cell.row, cell.row_span, height)
+            })
+            .collect();
+        let heights = track_sizes(&self.row_tracks(), &row_spans, self.row_gap, constraints.max.y);
+        let row_starts = track_starts(&heights, self.row_gap);
+
+        let child_rects = self
+            .children
+            .iter()
+            .map(|(child, cell)| {
+                let start = vec2(column_starts[cell.column], row_starts[cell.row]);
+                let area = vec2(
+                    span_size(&widths, cell.column, cell.column_span, self.column_gap),
+                    span_size(&heights, cell.row, cell.row_span, self.row_gap),
+                );
+                let child_constraints =
+                    cell_constraints(cell, area, min_size_of(child.id(), min_sizes));
+                let size = widget_layout(&**child, child_constraints, theme, min_sizes);
+                let offset = |align: CellAlign, area: i32, size: i32| match align {
+                    CellAlign::Start | CellAlign::Stretch => 0,
+                    CellAlign::Center => (area - size) / 2,
+                    CellAlign::End => area - size,
+                };
+                let start = Point2::from_vec(
+                    start
+                        + vec2(
+                            offset(cell.align_x, area.x, size.x),
+                            offset(cell.align_y, area.y, size.y),
+                        ),
+                );
+                Rect::new(start, start + size)
+            })
+            .collect();
+
+        let size = vec2(
+            span_size(&widths, 0, widths.len(), self.column_gap),
+            span_size(&heights, 0, heights.len(), self.row_gap),
+        );
+        Arrangement { size: constraints.constrain(size), child_rects }
+    }
 }
 
-impl Widget for Overlap {
+/// A cell's first track, number of tracks, and the size its widget needs across them.
+type TrackSpan = (usize, usize, i32);
+
+/// Returns the size of each of a grid's tracks along one axis, given the size the widget in each
+/// cell needs. Fractional tracks share whatever is left of `available`, if it's bounded.
+fn track_sizes(tracks: &[Track], spans: &[TrackSpan], gap: i32, available: i32) -> Vec<i32> {
+    let mut sizes: Vec<i32> = tracks
+        .iter()
+        .map(|&track| match track {
+            Track::Fixed(size) => size,
+            Track::Auto | Track::Fraction(_) => 0,
+        })
+        .collect();
+    // Sizing cells in single tracks first means cells spanning several tracks only make them
+    // larger if they don't already fit
+    let mut spans = spans.to_vec();
+    spans.sort_by_key(|&(_start, span, _size)| span);
+    for (start, span, size) in spans {
+        let tracks_size = span_size(&sizes, start, span, gap);
+        // Fractional tracks take the growth in proportion to their fractions, since they'd grow
+        // into any space left over anyway
+        let fractions: Vec<(usize, f32)> = (start..start + span)
+            .filter_map(|i| match tracks[i] {
+                Track::Fraction(fraction) if fraction > 0.0 => Some((i, fraction)),
+                _ => None,
+            })
+            .collect();
+        let growable: Vec<(usize, f32)> = if fractions.is_empty() {
+            let is_growable = |i: &usize| !matches!(tracks[*i], Track::Fixed(_));
+            (start..start + span).filter(is_growable).map(|i| (i, 1.0)).collect()
+        } else {
+            fractions
+        };
+        if size > tracks_size && !growable.is_empty() {
+            let weights: Vec<f32> = growable.iter().map(|&(_i, weight)| weight).collect();
+            let growth = distribute(size - tracks_size, &weights);
+            for ((i, _weight), growth) in growable.into_iter().zip(growth) {
+                sizes[i] += growth;
+            }
+        }
+    }
+
+    if available != BoxConstraints::UNBOUNDED {
+        let free = available - span_size(&sizes, 0, sizes.len(), gap);
+        if free > 0 {
+            let fractions: Vec<f32> = tracks
+                .iter()
+                .map(|&track| match track {
+                    Track::Fraction(fraction) => fraction,
+                    Track::Fixed(_) | Track::Auto => 0.0,
+                })
+                .collect();
+            for (size, growth) in sizes.iter_mut().zip(distribute(free, &fractions)) {
+                *size += growth;
+            }
+        }
+    }
+    sizes
+}
+
+/// Returns the total size of `span` tracks starting at `start`, including the gaps between them.
+fn span_size(sizes: &[i32], start: usize, span: usize, gap: i32) -> i32 {
+    sizes[start..start + span].iter().sum::<i32>() + gap * (span as i32 - 1).max(0)
+}
+
+/// Returns the position of the start of each track.
+fn track_starts(sizes: &[i32], gap: i32) -> Vec<i32> {
+    let mut next_start = 0;
+    sizes
+        .iter()
+        .map(|size| {
+            let start = next_start;
+            next_start += size + gap;
+            start
+        })
+        .collect()
+}
+
+/// Returns the constraints for the widget in a cell with the given size. A size of
+/// `BoxConstraints::UNBOUNDED` is used to measure how large the widget wants to be.
+fn cell_constraints(cell: &GridCell, area: Vector2<i32>, min_size: Vector2<i32>) -> BoxConstraints {
+    let axis = |align: CellAlign, area: i32, min_size: i32| {
+        if align == CellAlign::Stretch && area != BoxConstraints::UNBOUNDED {
+            (area, area)
+        } else {
+            (min_size.min(area), area)
+        }
+    };
+    let (min_x, max_x) = axis(cell.align_x, area.x, min_size.x);
+    let (min_y, max_y) = axis(cell.align_y, area.y, min_size.y);
+    BoxConstraints::new(vec2(min_x, min_y), vec2(max_x, max_y))
+}
+
+impl Widget for Grid {
     fn id(&self) -> WidgetId {
         self.id
     }
 
+    fn clips_to_rect(&self) -> bool {
+        true
+    }
+
     fn draw(
         &self,
         _context: &GlContext,
@@ -562,24 +1259,61 @@ impl Widget for Overlap {
     ) {
     }
 
+    fn layout_hash(&self) -> Option<u64> {
+        let track_keys = |tracks: &[Track]| -> Vec<(u8, u32)> {
+            tracks
+                .iter()
+                .map(|&track| match track {
+                    Track::Fixed(size) => (0, size as u32),
+                    Track::Auto => (1, 0),
+                    Track::Fraction(fraction) => (2, fraction.to_bits()),
+                })
+                .collect()
+        };
+        let cells: Vec<GridCell> = self.children.iter().map(|&(ref _child, cell)| cell).collect();
+        let tracks = (track_keys(&self.columns), track_keys(&self.rows));
+        Some(hash_layout(&("Grid", tracks, cells, self.column_gap, self.row_gap)))
+    }
+
     fn min_size(
         &self,
-        _context: &GlContext,
         _theme: &Theme,
         min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
         _window_size: Vector2<i32>,
     ) -> Vector2<i32> {
-        let mut min_size: Vector2<i32> = Vector2::zero();
-        for child in &self.children {
-            let child_min_size = min_sizes[&child.id()];
-            min_size.x = min_size.x.max(child_min_size.x);
-            min_size.y = min_size.y.max(child_min_size.y);
-        }
-        min_size
+        // Each column is as wide as the widest min size in it across every row
+        let column_spans: Vec<TrackSpan> = self
+            .children
+            .iter()
+            .map(|(child, cell)| {
+                (cell.column, cell.column_span, min_size_of(child.id(), min_sizes).x)
+            })
+            .collect();
+        let row_spans: Vec<TrackSpan> = self
+            .children
+            .iter()
+            .map(|(child, cell)| (cell.row, cell.row_span, min_size_of(child.id(), min_sizes).y))
+            .collect();
+        let unbounded = BoxConstraints::UNBOUNDED;
+        let widths = track_sizes(&self.column_tracks(), &column_spans, self.column_gap, unbounded);
+        let heights = track_sizes(&self.row_tracks(), &row_spans, self.row_gap, unbounded);
+        vec2(
+            span_size(&widths, 0, widths.len(), self.column_gap),
+            span_size(&heights, 0, heights.len(), self.row_gap),
+        )
     }
 
     fn children(&self) -> Vec<&dyn Widget> {
-        self.children.iter().map(|child| &**child as &dyn Widget).collect()
+        self.children.iter().map(|(child, _)| &**child as &dyn Widget).collect()
+    }
+
+    fn layout(
+        &self,
+        constraints: BoxConstraints,
+        theme: &Theme,
+        min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+    ) -> Vector2<i32> {
+        self.arrange(constraints, theme, min_sizes).size
     }
 
     fn compute_rects(
@@ -587,207 +1321,414 @@ impl Widget for Overlap {
         rect: Rect<i32>,
         theme: &Theme,
         min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
-        widget_rects: &mut FnvHashMap<WidgetId, Rect<i32>>,
+        widget_rects: &mut WidgetRects,
     ) {
-        let own_rect = rect;
-        widget_rects.insert(self.id(), own_rect);
-        for child in &self.children {
-            child.compute_rects(own_rect, theme, min_sizes, widget_rects);
+        let arrangement = self.arrange(BoxConstraints::loose(rect.size()), theme, min_sizes);
+        widget_rects.insert(self.id(), Rect::new(rect.start, rect.start + arrangement.size));
+        for ((child, _cell), child_rect) in self.children.iter().zip(arrangement.child_rects) {
+            let child_rect = Rect::new(
+                rect.start + child_rect.start.to_vec(),
+                rect.start + child_rect.end.to_vec(),
+            );
+            widget_compute_rects(&**child, child_rect, theme, min_sizes, widget_rects);
         }
     }
 }
 
 #[derive(Clone)]
-pub struct EmptyWidget {
+pub struct TextBox {
+    text: String,
+    text_color: Color4,
     id: WidgetId,
-    size: Vector2<i32>,
 }
 
-impl EmptyWidget {
-    pub fn new() -> Box<Self> {
-        Self::with_size(Vector2::zero())
+impl TextBox {
+    pub fn new(text: &str) -> Box<Self> {
+        Box::new(TextBox { text: text.to_owned(), text_color: Color4::BLACK, id: WidgetId::new() })
     }
 
-    pub fn with_size(size: Vector2<i32>) -> Box<Self> {
-        Box::new(EmptyWidget { id: WidgetId::new(), size })
+    pub fn text_color(mut self: Box<Self>, color: Color4) -> Box<Self> {
+        self.text_color = color;
+        self
     }
 }
 
-impl Widget for EmptyWidget {
+impl Widget for TextBox {
     fn id(&self) -> WidgetId {
         self.id
     }
 
+    fn clips_to_rect(&self) -> bool {
+        true
+    }
+
     fn draw(
         &self,
-        _context: &GlContext,
+        context: &GlContext,
         _surface: &dyn Surface,
-        _rect: Rect<i32>,
-        _theme: &Theme,
+        rect: Rect<i32>,
+        theme: &Theme,
         _draw_2d: &mut Draw2d,
         _cursor_pos: Option<Point2<i32>>,
         _is_active: bool,
     ) {
+        let layout = theme.font.layout(&self.text, None);
+        theme.font.draw_layout(context, &layout, rect.start.cast().unwrap(), self.text_color);
+    }
+
+    fn baseline(&self, theme: &Theme) -> Option<i32> {
+        Some(theme.font.ascent().round() as i32)
+    }
+
+    fn layout_hash(&self) -> Option<u64> {
+        Some(hash_layout(&("TextBox", &self.text)))
     }
 
     fn min_size(
         &self,
-        _context: &GlContext,
-        _theme: &Theme,
+        theme: &Theme,
         _min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
         _window_size: Vector2<i32>,
     ) -> Vector2<i32> {
-        self.size
+        ceil_size(theme.font.layout(&self.text, None).size())
     }
 }
 
-pub struct Padding {
+/// Returns the byte ranges of the words in `text`, which are separated by whitespace.
+fn word_ranges(text: &str) -> Vec<Range<usize>> {
+    let mut words = vec![];
+    let mut word_start = None;
+    for (i, c) in text.char_indices() {
+        match (c.is_whitespace(), word_start) {
+            (true, Some(start)) => {
+                words.push(start..i);
+                word_start = None;
+            }
+            (false, None) => word_start = Some(i),
+            _ => (),
+        }
+    }
+    if let Some(start) = word_start {
+        words.push(start..text.len());
+    }
+    words
+}
+
+/// A block of text that's wrapped to fit the width it's given.
+///
+/// Its min width is that of its widest word, and its min height is that of the text without any
+/// wrapping. When `layout` is given constraints with a bounded max width, lines longer than that
+/// width are wrapped, and it takes up the height of all of its wrapped lines.
+#[derive(Clone)]
+pub struct Paragraph {
     id: WidgetId,
+    text: String,
+    text_color: Color4,
+    align: TextAlign,
+    line_spacing: f32,
 }
 
-impl Padding {
-    pub fn new() -> Box<Self> {
-        Box::new(Padding { id: WidgetId::new() })
+impl Paragraph {
+    pub fn new(text: &str) -> Box<Self> {
+        Box::new(Paragraph {
+            id: WidgetId::new(),
+            text: text.to_owned(),
+            text_color: Color4::BLACK,
+            align: TextAlign::Left,
+            line_spacing: 1.0,
+        })
+    }
+
+    pub fn text_color(mut self: Box<Self>, color: Color4) -> Box<Self> {
+        self.text_color = color;
+        self
+    }
+
+    pub fn align(mut self: Box<Self>, align: TextAlign) -> Box<Self> {
+        self.align = align;
+        self
+    }
+
+    /// Sets the distance between lines, as a multiple of the font's line height. Defaults to 1.
+    pub fn line_spacing(mut self: Box<Self>, line_spacing: f32) -> Box<Self> {
+        self.line_spacing = line_spacing;
+        self
+    }
+
+    fn line_height(&self, theme: &Theme) -> i32 {
+        (theme.font.advance_y() as f32 * self.line_spacing).round() as i32
     }
 }
 
-impl Widget for Padding {
+impl Widget for Paragraph {
     fn id(&self) -> WidgetId {
         self.id
     }
 
+    fn clips_to_rect(&self) -> bool {
+        true
+    }
+
     fn draw(
         &self,
-        _context: &GlContext,
+        context: &GlContext,
         _surface: &dyn Surface,
-        _rect: Rect<i32>,
-        _theme: &Theme,
+        rect: Rect<i32>,
+        theme: &Theme,
         _draw_2d: &mut Draw2d,
         _cursor_pos: Option<Point2<i32>>,
         _is_active: bool,
     ) {
+        let width = rect.size().x as f32;
+        let line_height = self.line_height(theme);
+        let layout = theme.font.layout(&self.text, Some(width));
+        for (i, line) in layout.lines().iter().enumerate() {
+            let line_y = rect.start.y + line_height * i as i32;
+            if line_y > rect.end.y {
+                break;
+            }
+            let line_pos = point2(rect.start.x as f32, line_y as f32);
+            // Whitespace at the end of the line isn't counted when aligning it
+            let line_text = &self.text[line.range.clone()];
+            let text_len = line_text.trim_end().len();
+            let text_width = line
+                .shaped
+                .clusters
+                .iter()
+                .filter(|cluster| cluster.range.start < text_len)
+                .map(|cluster| cluster.x + cluster.width)
+                .fold(0.0, f32::max);
+            let extra_space = width - text_width;
+            let offset = match self.align {
+                TextAlign::Justify if line.wrapped => {
+                    let words = word_ranges(&line_text[..text_len]);
+                    let gap =
+                        if words.len() > 1 { extra_space / (words.len() - 1) as f32 } else { 0.0 };
+                    for (j, word) in words.into_iter().enumerate() {
+                        let x = line.shaped.caret_x(word.start) + gap * j as f32;
+                        theme.font.draw_string_f32(
+                            context,
+                            &line_text[word],
+                            line_pos + vec2(x, 0.0),
+                            self.text_color,
+                            Matrix4::identity(),
+                        );
+                    }
+                    continue;
+                }
+                TextAlign::Left | TextAlign::Justify => 0.0,
+                TextAlign::Center => extra_space / 2.0,
+                TextAlign::Right => extra_space,
+            };
+            theme.font.draw_shaped(
+                context,
+                &line.shaped,
+                line_pos + vec2(offset, 0.0),
+                self.text_color,
+            );
+        }
+    }
+
+    fn baseline(&self, theme: &Theme) -> Option<i32> {
+        Some(theme.font.ascent().round() as i32)
+    }
+
+    fn layout_hash(&self) -> Option<u64> {
+        Some(hash_layout(&("Paragraph", &self.text, self.line_spacing.to_bits())))
     }
 
     fn min_size(
         &self,
-        _context: &GlContext,
         theme: &Theme,
         _min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
         _window_size: Vector2<i32>,
     ) -> Vector2<i32> {
-        vec2(theme.padding, theme.padding)
+        let max_word_width =
+            self.text.split_whitespace().map(|x| theme.font.string_width(x).ceil() as i32).max();
+        let num_lines = self.text.split('\n').count() as i32;
+        vec2(max_word_width.unwrap_or(0), self.line_height(theme) * num_lines)
+    }
+
+    fn layout(
+        &self,
+        constraints: BoxConstraints,
+        theme: &Theme,
+        min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+    ) -> Vector2<i32> {
+        // The paragraph is as wide as its longest line, unless it has to be wrapped
+        let unwrapped_width = theme.font.layout(&self.text, None).size().x.ceil() as i32;
+        let width = constraints
+            .constrain(vec2(unwrapped_width.max(min_size_of(self.id(), min_sizes).x), 0))
+            .x;
+        let num_lines = theme.font.layout(&self.text, Some(width as f32)).lines().len() as i32;
+        constraints.constrain(vec2(width, self.line_height(theme) * num_lines))
     }
 }
 
-pub struct Inset {
+/// Displays `RichText`, wrapped to fit the width it's given. Every line's spans share a baseline.
+///
+/// Spans whose fonts don't share `theme.font`'s `TextRenderer` are queued on their own renderers,
+/// so those must be rendered along with `theme.font`.
+#[derive(Clone)]
+pub struct RichLabel {
     id: WidgetId,
-    child: Box<dyn Widget>,
+    text: RichText,
+    text_color: Option<Color4>,
 }
 
-impl Inset {
-    pub fn new(child: Box<dyn Widget>) -> Box<Self> {
-        Box::new(Inset { id: WidgetId::new(), child })
+impl RichLabel {
+    pub fn new(text: RichText) -> Box<Self> {
+        Box::new(RichLabel { id: WidgetId::new(), text, text_color: None })
+    }
+
+    /// Sets the color of spans that don't have their own. Defaults to `theme.label_color`.
+    pub fn text_color(mut self: Box<Self>, color: Color4) -> Box<Self> {
+        self.text_color = Some(color);
+        self
     }
 }
 
-impl Widget for Inset {
+impl Widget for RichLabel {
     fn id(&self) -> WidgetId {
         self.id
     }
 
+    fn clips_to_rect(&self) -> bool {
+        true
+    }
+
     fn draw(
         &self,
-        _context: &GlContext,
+        context: &GlContext,
         _surface: &dyn Surface,
-        _rect: Rect<i32>,
-        _theme: &Theme,
-        _draw_2d: &mut Draw2d,
+        rect: Rect<i32>,
+        theme: &Theme,
+        draw_2d: &mut Draw2d,
         _cursor_pos: Option<Point2<i32>>,
         _is_active: bool,
     ) {
-    }
-
+        let default_color = self.text_color.unwrap_or(theme.label_color);
+        // The GUI only clips `theme.font`'s renderer, so other renderers have to be clipped here
+        let clip_rect = draw_2d.clip_rect();
+        let mut line_y = rect.start.y;
+        for line in self.text.layout(&theme.font, Some(rect.size().x as f32)) {
+            if line_y > rect.end.y {
+                break;
+            }
+            let baseline = line_y as f32 + line.ascent;
+            for (i, fragment) in line.fragments.iter().enumerate() {
+                let span = &self.text.spans[fragment.span];
+                let font = self.text.span_font(fragment.span, &theme.font);
+                let color = span.color.unwrap_or(default_color);
+                let start_x = rect.start.x as f32 + fragment.x;
+                let pos = point2(start_x, baseline - font.ascent());
+                let clip_rect =
+                    clip_rect.filter(|_| !font.renderer().ptr_eq(theme.font.renderer()));
+                if let Some(clip_rect) = clip_rect {
+                    font.push_clip_rect(clip_rect);
+                }
+                let text = span.text[fragment.range.clone()].trim_end();
+                font.draw_string_f32(context, text, pos, color, Matrix4::identity());
+                if clip_rect.is_some() {
+                    font.pop_clip_rect();
+                }
+
+                // Decorations continue under the spaces between words of the same span
+                let end_x = match line.fragments.get(i + 1) {
+                    Some(next) if next.span == fragment.span => rect.start.x as f32 + next.x,
+                    _ => start_x + fragment.width,
+                };
+                if span.underline {
+                    let decoration = TextDecoration::Underline;
+                    font.draw_decoration(draw_2d, decoration, pos, end_x - start_x, color);
+                }
+                if span.strikethrough {
+                    let decoration = TextDecoration::Strikethrough;
+                    font.draw_decoration(draw_2d, decoration, pos, end_x - start_x, color);
+                }
+            }
+            line_y += line.height;
+        }
+    }
+
+    fn layout_hash(&self) -> Option<u64> {
+        let spans: Vec<_> = self
+            .text
+            .spans
+            .iter()
+            .map(|span| (&span.text, span.font.as_ref().map(|font| font.layout_hash())))
+            .collect();
+        Some(hash_layout(&("RichLabel", spans)))
+    }
+
     fn min_size(
         &self,
-        _context: &GlContext,
         theme: &Theme,
-        min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+        _min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
         _window_size: Vector2<i32>,
     ) -> Vector2<i32> {
-        min_sizes[&self.child.id()] + vec2(theme.padding * 2, theme.padding * 2)
-    }
-
-    fn children(&self) -> Vec<&dyn Widget> {
-        vec![&*self.child]
+        // Wrapping to a width of 0 breaks the text wherever possible
+        let min_width = self
+            .text
+            .layout(&theme.font, Some(0.0))
+            .iter()
+            .map(|line| line.width().ceil() as i32)
+            .max()
+            .unwrap_or(0);
+        let height = self.text.layout(&theme.font, None).iter().map(|line| line.height).sum();
+        vec2(min_width, height)
     }
 
-    fn compute_rects(
+    fn layout(
         &self,
-        rect: Rect<i32>,
+        constraints: BoxConstraints,
         theme: &Theme,
         min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
-        widget_rects: &mut FnvHashMap<WidgetId, Rect<i32>>,
-    ) {
-        widget_rects.insert(
-            self.id(),
-            Rect::new(rect.start, rect.end + vec2(theme.padding * 2, theme.padding * 2)),
-        );
-        self.child.compute_rects(
-            Rect::new(
-                rect.start + vec2(theme.padding, theme.padding),
-                rect.end - vec2(theme.padding, theme.padding),
-            ),
-            theme,
-            min_sizes,
-            widget_rects,
-        );
+    ) -> Vector2<i32> {
+        // The label is as wide as its longest line, unless it has to be wrapped
+        let unwrapped_width = self
+            .text
+            .layout(&theme.font, None)
+            .iter()
+            .map(|line| line.width().ceil() as i32)
+            .max()
+            .unwrap_or(0);
+        let width = constraints
+            .constrain(vec2(unwrapped_width.max(min_size_of(self.id(), min_sizes).x), 0))
+            .x;
+        let lines = self.text.layout(&theme.font, Some(width as f32));
+        constraints.constrain(vec2(width, lines.iter().map(|line| line.height).sum()))
     }
 }
 
-/// Lets the user select one of several options.
+// This is intended to be persistent, which is tricky since widgets have to own
+// their children, but you can clone it.
 #[derive(Clone)]
-pub struct Selector<T: Copy + PartialEq> {
-    options: Vec<(String, T)>,
-    selected_option: Option<usize>,
+pub struct MessageBox {
+    lines: Vec<(String, Color4)>,
+    max_lines: usize,
     id: WidgetId,
 }
 
-impl<T: Copy + PartialEq> Selector<T> {
-    pub fn new(options: Vec<(String, T)>, selected_option: Option<(String, T)>) -> Box<Self> {
-        Box::new(Self {
-            selected_option: selected_option
-                .map(|selected_option| options.iter().position(|x| &selected_option == x).unwrap()),
-            options,
-            id: WidgetId::new(),
-        })
-    }
-
-    pub fn selected_option(&self) -> Option<T> {
-        self.selected_option.map(|selected_option| self.options[selected_option].1)
-    }
-
-    pub fn add_option(&mut self, option: (String, T)) {
-        self.options.push(option);
+impl MessageBox {
+    pub fn new(max_lines: usize) -> Box<Self> {
+        Box::new(MessageBox { lines: vec![], max_lines, id: WidgetId::new() })
     }
 
-    pub fn remove_option(&mut self, i: usize) {
-        self.options.remove(i);
-        if let Some(selected_option) = &mut self.selected_option {
-            if *selected_option == i {
-                self.selected_option = None;
-            } else if *selected_option > i {
-                *selected_option -= 1;
-            }
+    pub fn add_line(&mut self, color: Color4, line: String) {
+        self.lines.push((line, color));
+        if self.lines.len() > self.max_lines {
+            self.lines.remove(0);
         }
     }
 }
 
-impl<T: Copy + PartialEq> Widget for Selector<T> {
+impl Widget for MessageBox {
     fn id(&self) -> WidgetId {
         self.id
     }
 
-    fn is_component(&self) -> bool {
+    fn clips_to_rect(&self) -> bool {
         true
     }
 
@@ -797,93 +1738,64 @@ impl<T: Copy + PartialEq> Widget for Selector<T> {
         _surface: &dyn Surface,
         rect: Rect<i32>,
         theme: &Theme,
-        draw_2d: &mut Draw2d,
-        cursor_pos: Option<Point2<i32>>,
+        _draw_2d: &mut Draw2d,
+        _cursor_pos: Option<Point2<i32>>,
         _is_active: bool,
     ) {
-        for (i, (line, _)) in self.options.iter().enumerate() {
-            let pos = rect.start.cast().unwrap() + vec2(0, theme.font.advance_y() * i as i32);
-            let rect = Rect::new(pos, pos + theme.font.string_size(context, &line));
-            let background_color = if Some(i) == self.selected_option {
-                Color4::WHITE.mul_srgb(0.5)
-            } else if cursor_pos.is_some()
-                && rect.contains_point(cursor_pos.unwrap().cast().unwrap())
-            {
-                Color4::WHITE.mul_srgb(0.75)
-            } else {
-                Color4::WHITE
-            };
-            draw_2d.fill_rect(rect, background_color);
-            theme.font.draw_string(context, &line, pos, Color4::BLACK);
+        let advance_y = theme.font.advance_y();
+        for (i, &(ref line, color)) in self.lines.iter().enumerate() {
+            theme.font.draw_string(
+                context,
+                line,
+                rect.start.cast().unwrap() + vec2(0, advance_y * i as i32),
+                color,
+            );
         }
     }
 
+    fn layout_hash(&self) -> Option<u64> {
+        let lines: Vec<&str> = self.lines.iter().map(|(line, _color)| line.as_str()).collect();
+        Some(hash_layout(&("MessageBox", lines)))
+    }
+
     fn min_size(
         &self,
-        context: &GlContext,
         theme: &Theme,
         _min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
         _window_size: Vector2<i32>,
     ) -> Vector2<i32> {
         let max_width =
-            self.options.iter().map(|(x, _)| theme.font.string_width(context, x) as i32).max();
+            self.lines.iter().map(|x| theme.font.string_width(&x.0).ceil() as i32).max();
         if let Some(max_width) = max_width {
-            vec2(max_width as i32, theme.font.advance_y() as i32 * self.options.len() as i32)
+            vec2(max_width, theme.font.advance_y() * self.lines.len() as i32)
         } else {
             vec2(0, 0)
         }
     }
 }
 
-pub struct SelectorResult<T: Copy + PartialEq> {
-    pub selected: Option<(String, T)>,
-    pub just_selected: bool,
+pub struct Overlap {
+    id: WidgetId,
+    children: Vec<Box<dyn Widget>>,
 }
 
-impl<T: Copy + PartialEq> Component for Selector<T> {
-    type Res = SelectorResult<T>;
-
-    fn update(&mut self, theme: &Theme, events: Vec<Event>) -> Self::Res {
-        let mut just_selected = false;
-        for event in events {
-            match event {
-                Event::MouseDown(MouseButton::Left, pos) => {
-                    let entry = pos.y / theme.font.advance_y() as i32;
-                    assert!(
-                        entry >= 0 && (entry as usize) < self.options.len(),
-                        "entry {} out of range (max={})",
-                        entry,
-                        self.options.len()
-                    );
-                    self.selected_option = Some(entry as usize);
-                    just_selected = true;
-                }
-                _ => (),
-            }
-        }
-
-        SelectorResult {
-            selected: self
-                .selected_option
-                .map(|selected_option| self.options[selected_option].clone()),
-            just_selected,
-        }
+impl Overlap {
+    pub fn new() -> Box<Self> {
+        Box::new(Overlap { id: WidgetId::new(), children: vec![] })
     }
-}
 
-pub struct Fill {
-    id: WidgetId,
-    child: Box<dyn Widget>,
-    fill_color: Color4,
-}
+    pub fn child(mut self: Box<Self>, child: Box<dyn Widget>) -> Box<Self> {
+        self.children.push(child);
+        self
+    }
 
-impl Fill {
-    pub fn new(fill_color: Color4, child: Box<dyn Widget>) -> Box<Self> {
-        Box::new(Fill { id: WidgetId::new(), child, fill_color })
+    pub fn children(mut self: Box<Self>, children: Vec<Box<dyn Widget>>) -> Box<Self> {
+        self.children.extend(children);
+        self
     }
 }
 
-impl Widget for Fill {
+impl Widget for Overlap {
     fn id(&self) -> WidgetId {
         self.id
     }
@@ -892,28 +1804,49 @@ impl Widget for Fill {
         &self,
         _context: &GlContext,
         _surface: &dyn Surface,
-        rect: Rect<i32>,
+        _rect: Rect<i32>,
         _theme: &Theme,
-        draw_2d: &mut Draw2d,
+        _draw_2d: &mut Draw2d,
         _cursor_pos: Option<Point2<i32>>,
         _is_active: bool,
     ) {
-        // TODO: should this subtract (1,1) from end, like Border does?
-        draw_2d.fill_rect(Rect::new(rect.start, rect.end), self.fill_color);
+    }
+
+    fn layout_hash(&self) -> Option<u64> {
+        Some(hash_layout(&"Overlap"))
     }
 
     fn min_size(
         &self,
-        _context: &GlContext,
         _theme: &Theme,
         min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
         _window_size: Vector2<i32>,
     ) -> Vector2<i32> {
-        min_sizes[&self.child.id()]
+        let mut min_size: Vector2<i32> = Vector2::zero();
+        for child in &self.children {
+            let child_min_size = min_size_of(child.id(), min_sizes);
+            min_size.x = min_size.x.max(child_min_size.x);
+            min_size.y = min_size.y.max(child_min_size.y);
+        }
+        min_size
     }
 
     fn children(&self) -> Vec<&dyn Widget> {
-        vec![&*self.child]
+        self.children.iter().map(|child| &**child as &dyn Widget).collect()
+    }
+
+    fn layout(
+        &self,
+        constraints: BoxConstraints,
+        theme: &Theme,
+        min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+    ) -> Vector2<i32> {
+        let mut size = constraints.min;
+        for child in &self.children {
+            let child_size = widget_layout(&**child, constraints, theme, min_sizes);
+            size = vec2(size.x.max(child_size.x), size.y.max(child_size.y));
+        }
+        size
     }
 
     fn compute_rects(
@@ -921,141 +1854,2296 @@ impl Widget for Fill {
         rect: Rect<i32>,
         theme: &Theme,
         min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
-        widget_rects: &mut FnvHashMap<WidgetId, Rect<i32>>,
+        widget_rects: &mut WidgetRects,
     ) {
-        widget_rects.insert(self.id(), Rect::new(rect.start, rect.end));
-        self.child.compute_rects(Rect::new(rect.start, rect.end), theme, min_sizes, widget_rects);
+        let own_rect = rect;
+        widget_rects.insert(self.id(), own_rect);
+        for child in &self.children {
+            widget_compute_rects(&**child, own_rect, theme, min_sizes, widget_rects);
+        }
     }
 }
 
-#[derive(Copy, Clone, Eq, PartialEq)]
-pub enum TextEntryEvent {
-    AddChar(char),
-    Backspace,
-    CaretLeft,
-    CaretRight,
-    EnterPressed,
-}
-
-pub struct TextEntryResult {
-    pub text: Option<String>,
+/// A side of a rectangle that a widget in a `Stack` can be put next to.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Side {
+    Above,
+    Below,
+    Left,
+    Right,
 }
 
-impl TextEntryResult {
-    pub fn text(&self) -> Option<&str> {
-        // https://stackoverflow.com/questions/31233938/converting-from-optionstring-to-optionstr
-        self.text.as_ref().map(|x| x.as_ref())
+impl Side {
+    fn opposite(self) -> Side {
+        match self {
+            Side::Above => Side::Below,
+            Side::Below => Side::Above,
+            Side::Left => Side::Right,
+            Side::Right => Side::Left,
+        }
     }
 }
 
-const CARET_BLINK_RATE: f64 = 1.0;
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Placement {
+    Anchored(Alignment),
+    Beside { target: Rect<i32>, side: Side, alignment: f32 },
+}
 
-#[derive(Clone)]
-pub struct TextEntry {
-    id: WidgetId,
-    pub text: String,
-    placeholder_text: String,
-    text_color: Color4,
-    caret_pos: i32,
-    // TODO: this should be a max length in pixels
-    max_len: usize,
-    stopwatch: Stopwatch,
-    use_placeholder_text_if_empty: bool,
-    continuous_updates: bool,
+/// Where a widget goes in a `Stack`, at its own size rather than filling the stack.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Positioned {
+    placement: Placement,
+    offset: Vector2<i32>,
 }
 
-impl TextEntry {
-    pub fn new(
-        start_text: &str,
-        placeholder_text: &str,
-        use_placeholder_text_if_empty: bool,
-        max_len: usize,
-        continuous_updates: bool,
-    ) -> Box<Self> {
-        assert!(placeholder_text.len() <= max_len);
-        Box::new(TextEntry {
-            id: WidgetId::new(),
-            text: start_text.to_string(),
-            placeholder_text: placeholder_text.to_string(),
-            text_color: Color4::BLACK,
-            caret_pos: 0,
-            max_len,
-            stopwatch: Stopwatch::new(),
-            use_placeholder_text_if_empty,
-            continuous_updates,
-        })
+impl Positioned {
+    /// Puts the point of the widget at `alignment` on the point of the stack at `alignment`,
+    /// such as the top right corner of the widget in the top right corner of the stack.
+    pub fn anchored(alignment: Alignment) -> Self {
+        Positioned { placement: Placement::Anchored(alignment), offset: Vector2::zero() }
     }
 
-    pub fn text_color(mut self: Box<Self>, color: Color4) -> Box<Self> {
-        self.text_color = color;
-        self
+    /// Puts the widget next to `target`, a rect in window coordinates such as one returned by
+    /// `Gui::widget_rect` for another widget. If the widget doesn't fit within the visible area on
+    /// that side, it's moved to the opposite side, and then it's moved along the side to stay
+    /// within the visible area. The visible area is the window, or the viewport of a `ScrollArea`
+    /// the stack is in. This lets a tooltip or menu in a small stack extend past the stack's
+    /// edges.
+    pub fn beside(target: Rect<i32>, side: Side) -> Self {
+        Positioned {
+            placement: Placement::Beside { target, side, alignment: 0.0 },
+            offset: Vector2::zero(),
+        }
     }
 
-    pub fn cur_text(&self) -> &str {
-        if self.text.is_empty() && self.use_placeholder_text_if_empty {
-            &self.placeholder_text
-        } else {
-            &self.text
-        }
+    /// Moves the widget from where it would be. If it's put beside a rect on the opposite side,
+    /// the offset away from the rect is reversed too.
+    pub fn offset(self, offset: Vector2<i32>) -> Self {
+        Positioned { offset, ..self }
     }
 
-    fn cur_text_mutable(&mut self) -> String {
-        if self.text.is_empty() && self.use_placeholder_text_if_empty {
-            self.placeholder_text.clone()
-        } else {
-            if self.continuous_updates {
-                self.text.clone()
-            } else {
-                mem::replace(&mut self.text, "".to_owned())
+    /// Sets where a widget put beside a rect goes along that side of it: 0 lines up the left or
+    /// top edges, 0.5 centers the widget, and 1 lines up the right or bottom edges. Defaults to 0.
+    pub fn align(self, alignment: f32) -> Self {
+        match self.placement {
+            Placement::Beside { target, side, .. } => {
+                Positioned { placement: Placement::Beside { target, side, alignment }, ..self }
             }
+            Placement::Anchored(_) => self,
         }
     }
-}
 
-impl Component for TextEntry {
-    type Res = TextEntryResult;
+    /// Hashes everything a `Stack` places the widget by.
+    fn layout_hash(&self) -> u64 {
+        match self.placement {
+            Placement::Anchored(alignment) => hash_layout(&(
+                "Anchored",
+                alignment.x.to_bits(),
+                alignment.y.to_bits(),
+                self.offset,
+            )),
+            Placement::Beside { target, side, alignment } => {
+                let target = (target.start, target.end);
+                hash_layout(&("Beside", target, side, alignment.to_bits(), self.offset))
+            }
+        }
+    }
 
-    fn update(&mut self, _theme: &Theme, events: Vec<Event>) -> TextEntryResult {
-        let mut res = None;
-        for event in events {
-            // TODO: handle unicode
-            match event {
-                Event::KeyDown(key) => match key.key.as_ref() {
-                    "Backspace" => {
-                        if self.caret_pos > 0 {
-                            self.text.remove(self.caret_pos as usize - 1);
-                            self.caret_pos -= 1;
-                        }
+    /// Returns the widget's rect in a stack that has the given rect, keeping a widget put beside a
+    /// rect within `bounds`.
+    fn rect(&self, stack_rect: Rect<i32>, bounds: Rect<i32>, size: Vector2<i32>) -> Rect<i32> {
+        match self.placement {
+            Placement::Anchored(alignment) => {
+                let start = alignment.position(stack_rect, size) + self.offset;
+                Rect::new(start, start + size)
+            }
+            Placement::Beside { target, side, alignment } => {
+                let fits = |rect: Rect<i32>| match side {
+                    Side::Above | Side::Below => {
+                        rect.start.y >= bounds.start.y && rect.end.y <= bounds.end.y
                     }
-                    "ArrowLeft" => self.caret_pos = (self.caret_pos - 1).max(0),
-                    "ArrowRight" => {
-                        self.caret_pos = (self.caret_pos + 1).min(self.text.len() as i32)
+                    Side::Left | Side::Right => {
+                        rect.start.x >= bounds.start.x && rect.end.x <= bounds.end.x
                     }
-                    "Enter" => {
-                        res = Some(self.cur_text_mutable());
-                        self.caret_pos = 0;
+                };
+                let mut rect = self.beside_rect(target, side, alignment, size);
+                if !fits(rect) {
+                    let flipped = self.beside_rect(target, side.opposite(), alignment, size);
+                    if fits(flipped) {
+                        rect = flipped;
                     }
-                    // TODO: find a better way to check if the char is printable
-                    _ if key.key.len() == 1 => {
-                        if self.text.len() < self.max_len {
-                            self.text
-                                .insert(self.caret_pos as usize, key.key.chars().next().unwrap());
-                            self.caret_pos += 1;
-                        }
+                }
+                // Stay within the bounds along the side, if there's room
+                let shift = |start: i32, end: i32, min: i32, max: i32| {
+                    if end > max {
+                        (max - end).max(min - start)
+                    } else if start < min {
+                        min - start
+                    } else {
+                        0
                     }
-                    _ => (),
-                },
-                _ => (),
+                };
+                let shift = match side {
+                    Side::Above | Side::Below => {
+                        vec2(shift(rect.start.x, rect.end.x, bounds.start.x, bounds.end.x), 0)
+                    }
+                    Side::Left | Side::Right => {
+                        vec2(0, shift(rect.start.y, rect.end.y, bounds.start.y, bounds.end.y))
+                    }
+                };
+                Rect::new(rect.start + shift, rect.end + shift)
+            }
+        }
+    }
+
+    /// Returns the widget's rect on the given side of `target`.
+    fn beside_rect(
+        &self,
+        target: Rect<i32>,
+        side: Side,
+        alignment: f32,
+        size: Vector2<i32>,
+    ) -> Rect<i32> {
+        let along = |start: i32, end: i32, size: i32| {
+            start + ((end - start - size) as f32 * alignment).round() as i32
+        };
+        let x = along(target.start.x, target.end.x, size.x);
+        let y = along(target.start.y, target.end.y, size.y);
+        let (start, offset) = match side {
+            Side::Above => {
+                (point2(x, target.start.y - size.y), vec2(self.offset.x, -self.offset.y))
+            }
+            Side::Below => (point2(x, target.end.y), self.offset),
+            Side::Left => (point2(target.start.x - size.x, y), vec2(-self.offset.x, self.offset.y)),
+            Side::Right => (point2(target.end.x, y), self.offset),
+        };
+        let start = start + offset;
+        Rect::new(start, start + size)
+    }
+}
+
+/// Draws its children on top of each other. Children added with `child` fill the stack, like in
+/// an `Overlap`, and children added with `positioned` are put where their `Positioned` says, at
+/// their own size.
+///
+/// Only the children that fill the stack affect its size.
+pub struct Stack {
+    id: WidgetId,
+    children: Vec<(Box<dyn Widget>, Option<Positioned>)>,
+}
+
+impl Stack {
+    pub fn new() -> Box<Self> {
+        Box::new(Stack { id: WidgetId::new(), children: vec![] })
+    }
+
+    pub fn child(mut self: Box<Self>, child: Box<dyn Widget>) -> Box<Self> {
+        self.children.push((child, None));
+        self
+    }
+
+    pub fn positioned(
+        mut self: Box<Self>,
+        positioned: Positioned,
+        child: Box<dyn Widget>,
+    ) -> Box<Self> {
+        self.children.push((child, Some(positioned)));
+        self
+    }
+
+    /// Returns the children that fill the stack.
+    fn filling_children(&self) -> impl Iterator<Item = &dyn Widget> {
+        self.children
+            .iter()
+            .filter(|(_child, positioned)| positioned.is_none())
+            .map(|(child, _positioned)| &**child as &dyn Widget)
+    }
+}
+
+impl Widget for Stack {
+    fn id(&self) -> WidgetId {
+        self.id
+    }
+
+    fn draw(
+        &self,
+        _context: &GlContext,
+        _surface: &dyn Surface,
+        _rect: Rect<i32>,
+        _theme: &Theme,
+        _draw_2d: &mut Draw2d,
+        _cursor_pos: Option<Point2<i32>>,
+        _is_active: bool,
+    ) {
+    }
+
+    fn layout_hash(&self) -> Option<u64> {
+        let placements: Vec<_> = self
+            .children
+            .iter()
+            .map(|(_child, positioned)| positioned.map(|positioned| positioned.layout_hash()))
+            .collect();
+        Some(hash_layout(&("Stack", placements)))
+    }
+
+    fn min_size(
+        &self,
+        _theme: &Theme,
+        min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+        _window_size: Vector2<i32>,
+    ) -> Vector2<i32> {
+        let mut min_size: Vector2<i32> = Vector2::zero();
+        for child in self.filling_children() {
+            let child_min_size = min_size_of(child.id(), min_sizes);
+            min_size.x = min_size.x.max(child_min_size.x);
+            min_size.y = min_size.y.max(child_min_size.y);
+        }
+        min_size
+    }
+
+    fn children(&self) -> Vec<&dyn Widget> {
+        self.children.iter().map(|(child, _)| &**child as &dyn Widget).collect()
+    }
+
+    fn is_overlay(&self, child: WidgetId) -> bool {
+        self.children
+            .iter()
+            .any(|(widget, positioned)| widget.id() == child && positioned.is_some())
+    }
+
+    fn layout(
+        &self,
+        constraints: BoxConstraints,
+        theme: &Theme,
+        min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+    ) -> Vector2<i32> {
+        let mut size = constraints.min;
+        for child in self.filling_children() {
+            let child_size = widget_layout(child, constraints, theme, min_sizes);
+            size = vec2(size.x.max(child_size.x), size.y.max(child_size.y));
+        }
+        size
+    }
+
+    fn compute_rects(
+        &self,
+        rect: Rect<i32>,
+        theme: &Theme,
+        min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+        widget_rects: &mut WidgetRects,
+    ) {
+        widget_rects.insert(self.id(), rect);
+        let bounds = widget_rects.bounds();
+        for (child, positioned) in &self.children {
+            let child_rect = match positioned {
+                Some(positioned) => {
+                    let constraints = BoxConstraints::loose(rect.size());
+                    let size = widget_layout(&**child, constraints, theme, min_sizes);
+                    positioned.rect(rect, bounds, size)
+                }
+                None => rect,
+            };
+            widget_compute_rects(&**child, child_rect, theme, min_sizes, widget_rects);
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct EmptyWidget {
+    id: WidgetId,
+    size: Vector2<i32>,
+}
+
+impl EmptyWidget {
+    pub fn new() -> Box<Self> {
+        Self::with_size(Vector2::zero())
+    }
+
+    pub fn with_size(size: Vector2<i32>) -> Box<Self> {
+        Box::new(EmptyWidget { id: WidgetId::new(), size })
+    }
+}
+
+impl Widget for EmptyWidget {
+    fn id(&self) -> WidgetId {
+        self.id
+    }
+
+    fn draw(
+        &self,
+        _context: &GlContext,
+        _surface: &dyn Surface,
+        _rect: Rect<i32>,
+        _theme: &Theme,
+        _draw_2d: &mut Draw2d,
+        _cursor_pos: Option<Point2<i32>>,
+        _is_active: bool,
+    ) {
+    }
+
+    fn layout_hash(&self) -> Option<u64> {
+        Some(hash_layout(&("EmptyWidget", self.size)))
+    }
+
+    fn min_size(
+        &self,
+        _theme: &Theme,
+        _min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+        _window_size: Vector2<i32>,
+    ) -> Vector2<i32> {
+        self.size
+    }
+}
+
+pub struct Padding {
+    id: WidgetId,
+}
+
+impl Padding {
+    pub fn new() -> Box<Self> {
+        Box::new(Padding { id: WidgetId::new() })
+    }
+}
+
+impl Widget for Padding {
+    fn id(&self) -> WidgetId {
+        self.id
+    }
+
+    fn draw(
+        &self,
+        _context: &GlContext,
+        _surface: &dyn Surface,
+        _rect: Rect<i32>,
+        _theme: &Theme,
+        _draw_2d: &mut Draw2d,
+        _cursor_pos: Option<Point2<i32>>,
+        _is_active: bool,
+    ) {
+    }
+
+    fn layout_hash(&self) -> Option<u64> {
+        Some(hash_layout(&"Padding"))
+    }
+
+    fn min_size(
+        &self,
+        theme: &Theme,
+        _min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+        _window_size: Vector2<i32>,
+    ) -> Vector2<i32> {
+        vec2(theme.padding, theme.padding)
+    }
+}
+
+pub struct Inset {
+    id: WidgetId,
+    child: Box<dyn Widget>,
+}
+
+impl Inset {
+    pub fn new(child: Box<dyn Widget>) -> Box<Self> {
+        Box::new(Inset { id: WidgetId::new(), child })
+    }
+}
+
+impl Widget for Inset {
+    fn id(&self) -> WidgetId {
+        self.id
+    }
+
+    fn draw(
+        &self,
+        _context: &GlContext,
+        _surface: &dyn Surface,
+        _rect: Rect<i32>,
+        _theme: &Theme,
+        _draw_2d: &mut Draw2d,
+        _cursor_pos: Option<Point2<i32>>,
+        _is_active: bool,
+    ) {
+    }
+
+    fn layout_hash(&self) -> Option<u64> {
+        Some(hash_layout(&"Inset"))
+    }
+
+    fn min_size(
+        &self,
+        theme: &Theme,
+        min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+        _window_size: Vector2<i32>,
+    ) -> Vector2<i32> {
+        min_size_of(self.child.id(), min_sizes) + vec2(theme.padding * 2, theme.padding * 2)
+    }
+
+    fn baseline(&self, theme: &Theme) -> Option<i32> {
+        self.child.baseline(theme).map(|baseline| baseline + theme.padding)
+    }
+
+    fn children(&self) -> Vec<&dyn Widget> {
+        vec![&*self.child]
+    }
+
+    fn layout(
+        &self,
+        constraints: BoxConstraints,
+        theme: &Theme,
+        min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+    ) -> Vector2<i32> {
+        let padding = vec2(theme.padding * 2, theme.padding * 2);
+        let child_constraints = constraints.deflate(padding);
+        widget_layout(&*self.child, child_constraints, theme, min_sizes) + padding
+    }
+
+    fn compute_rects(
+        &self,
+        rect: Rect<i32>,
+        theme: &Theme,
+        min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+        widget_rects: &mut WidgetRects,
+    ) {
+        widget_rects.insert(self.id(), rect);
+        let padding = vec2(theme.padding, theme.padding);
+        let child_start = rect.start + padding;
+        let child_start = point2(child_start.x.min(rect.end.x), child_start.y.min(rect.end.y));
+        let child_end = rect.end - padding;
+        // The child is never given a negative size or put outside the inset, even if the padding
+        // doesn't fit
+        let child_end = point2(child_end.x.max(child_start.x), child_end.y.max(child_start.y));
+        widget_compute_rects(
+            &*self.child,
+            Rect::new(child_start, child_end),
+            theme,
+            min_sizes,
+            widget_rects,
+        );
+    }
+}
+
+/// Where a widget goes within a larger space, as a fraction of the space left over on each
+/// axis: 0 is the left or top, and 1 is the right or bottom.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Alignment {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Alignment {
+    pub const TOP_LEFT: Alignment = Alignment { x: 0.0, y: 0.0 };
+    pub const TOP: Alignment = Alignment { x: 0.5, y: 0.0 };
+    pub const TOP_RIGHT: Alignment = Alignment { x: 1.0, y: 0.0 };
+    pub const LEFT: Alignment = Alignment { x: 0.0, y: 0.5 };
+    pub const CENTER: Alignment = Alignment { x: 0.5, y: 0.5 };
+    pub const RIGHT: Alignment = Alignment { x: 1.0, y: 0.5 };
+    pub const BOTTOM_LEFT: Alignment = Alignment { x: 0.0, y: 1.0 };
+    pub const BOTTOM: Alignment = Alignment { x: 0.5, y: 1.0 };
+    pub const BOTTOM_RIGHT: Alignment = Alignment { x: 1.0, y: 1.0 };
+
+    pub fn new(x: f32, y: f32) -> Self {
+        Alignment { x, y }
+    }
+
+    /// Returns where something of size `size` goes within `rect`.
+    fn position(&self, rect: Rect<i32>, size: Vector2<i32>) -> Point2<i32> {
+        let space = rect.size() - size;
+        rect.start
+            + vec2(
+                (space.x as f32 * self.x).round() as i32,
+                (space.y as f32 * self.y).round() as i32,
+            )
+    }
+}
+
+/// Fills the space it's given, and puts its child in it at the child's own size.
+pub struct Align {
+    id: WidgetId,
+    child: Box<dyn Widget>,
+    alignment: Alignment,
+}
+
+impl Align {
+    pub fn new(alignment: Alignment, child: Box<dyn Widget>) -> Box<Self> {
+        Box::new(Align { id: WidgetId::new(), child, alignment })
+    }
+}
+
+impl Widget for Align {
+    fn id(&self) -> WidgetId {
+        self.id
+    }
+
+    fn draw(
+        &self,
+        _context: &GlContext,
+        _surface: &dyn Surface,
+        _rect: Rect<i32>,
+        _theme: &Theme,
+        _draw_2d: &mut Draw2d,
+        _cursor_pos: Option<Point2<i32>>,
+        _is_active: bool,
+    ) {
+    }
+
+    fn layout_hash(&self) -> Option<u64> {
+        Some(hash_layout(&("Align", self.alignment.x.to_bits(), self.alignment.y.to_bits())))
+    }
+
+    fn min_size(
+        &self,
+        _theme: &Theme,
+        min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+        _window_size: Vector2<i32>,
+    ) -> Vector2<i32> {
+        min_size_of(self.child.id(), min_sizes)
+    }
+
+    fn children(&self) -> Vec<&dyn Widget> {
+        vec![&*self.child]
+    }
+
+    fn layout(
+        &self,
+        constraints: BoxConstraints,
+        theme: &Theme,
+        min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+    ) -> Vector2<i32> {
+        let child_size = widget_layout(&*self.child, constraints.loosen(), theme, min_sizes);
+        let fill = |max: i32, child_size: i32| {
+            if max == BoxConstraints::UNBOUNDED {
+                child_size
+            } else {
+                max
+            }
+        };
+        vec2(fill(constraints.max.x, child_size.x), fill(constraints.max.y, child_size.y))
+    }
+
+    fn compute_rects(
+        &self,
+        rect: Rect<i32>,
+        theme: &Theme,
+        min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+        widget_rects: &mut WidgetRects,
+    ) {
+        widget_rects.insert(self.id(), rect);
+        let constraints = BoxConstraints::loose(rect.size());
+        let child_size = widget_layout(&*self.child, constraints, theme, min_sizes);
+        let child_start = self.alignment.position(rect, child_size);
+        widget_compute_rects(
+            &*self.child,
+            Rect::new(child_start, child_start + child_size),
+            theme,
+            min_sizes,
+            widget_rects,
+        );
+    }
+}
+
+/// Fills the space it's given, and puts its child in the middle of it. This is an `Align` with
+/// `Alignment::CENTER`.
+pub struct Center {
+    align: Align,
+}
+
+impl Center {
+    pub fn new(child: Box<dyn Widget>) -> Box<Self> {
+        Box::new(Center { align: *Align::new(Alignment::CENTER, child) })
+    }
+}
+
+impl Widget for Center {
+    fn id(&self) -> WidgetId {
+        self.align.id()
+    }
+
+    fn draw(
+        &self,
+        _context: &GlContext,
+        _surface: &dyn Surface,
+        _rect: Rect<i32>,
+        _theme: &Theme,
+        _draw_2d: &mut Draw2d,
+        _cursor_pos: Option<Point2<i32>>,
+        _is_active: bool,
+    ) {
+    }
+
+    fn layout_hash(&self) -> Option<u64> {
+        self.align.layout_hash()
+    }
+
+    fn min_size(
+        &self,
+        theme: &Theme,
+        min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+        window_size: Vector2<i32>,
+    ) -> Vector2<i32> {
+        self.align.min_size(theme, min_sizes, window_size)
+    }
+
+    fn children(&self) -> Vec<&dyn Widget> {
+        self.align.children()
+    }
+
+    fn layout(
+        &self,
+        constraints: BoxConstraints,
+        theme: &Theme,
+        min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+    ) -> Vector2<i32> {
+        self.align.layout(constraints, theme, min_sizes)
+    }
+
+    fn compute_rects(
+        &self,
+        rect: Rect<i32>,
+        theme: &Theme,
+        min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+        widget_rects: &mut WidgetRects,
+    ) {
+        self.align.compute_rects(rect, theme, min_sizes, widget_rects);
+    }
+}
+
+/// Keeps its child's size within a minimum and maximum width and height, on top of the
+/// constraints its parent passes down. It sits at the top left of the space it's given, rather
+/// than filling it.
+pub struct ConstrainedBox {
+    id: WidgetId,
+    child: Option<Box<dyn Widget>>,
+    constraints: BoxConstraints,
+}
+
+impl ConstrainedBox {
+    pub fn new(child: Box<dyn Widget>) -> Box<Self> {
+        Box::new(ConstrainedBox {
+            id: WidgetId::new(),
+            child: Some(child),
+            constraints: BoxConstraints::unbounded(),
+        })
+    }
+
+    pub fn min_width(mut self: Box<Self>, min_width: i32) -> Box<Self> {
+        self.constraints.min.x = min_width;
+        self
+    }
+
+    pub fn max_width(mut self: Box<Self>, max_width: i32) -> Box<Self> {
+        self.constraints.max.x = max_width;
+        self
+    }
+
+    pub fn min_height(mut self: Box<Self>, min_height: i32) -> Box<Self> {
+        self.constraints.min.y = min_height;
+        self
+    }
+
+    pub fn max_height(mut self: Box<Self>, max_height: i32) -> Box<Self> {
+        self.constraints.max.y = max_height;
+        self
+    }
+
+    /// Returns the box's own constraints, kept within `constraints`.
+    fn enforce(&self, constraints: BoxConstraints) -> BoxConstraints {
+        BoxConstraints::new(
+            constraints.constrain(self.constraints.min),
+            constraints.constrain(self.constraints.max),
+        )
+    }
+}
+
+impl Widget for ConstrainedBox {
+    fn id(&self) -> WidgetId {
+        self.id
+    }
+
+    fn draw(
+        &self,
+        _context: &GlContext,
+        _surface: &dyn Surface,
+        _rect: Rect<i32>,
+        _theme: &Theme,
+        _draw_2d: &mut Draw2d,
+        _cursor_pos: Option<Point2<i32>>,
+        _is_active: bool,
+    ) {
+    }
+
+    fn layout_hash(&self) -> Option<u64> {
+        Some(hash_layout(&("ConstrainedBox", self.constraints.min, self.constraints.max)))
+    }
+
+    fn min_size(
+        &self,
+        _theme: &Theme,
+        min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+        _window_size: Vector2<i32>,
+    ) -> Vector2<i32> {
+        let child_min_size = match &self.child {
+            Some(child) => min_size_of(child.id(), min_sizes),
+            None => Vector2::zero(),
+        };
+        self.constraints.constrain(child_min_size)
+    }
+
+    fn children(&self) -> Vec<&dyn Widget> {
+        self.child.iter().map(|child| &**child as &dyn Widget).collect()
+    }
+
+    fn baseline(&self, theme: &Theme) -> Option<i32> {
+        self.child.as_ref().and_then(|child| child.baseline(theme))
+    }
+
+    fn layout(
+        &self,
+        constraints: BoxConstraints,
+        theme: &Theme,
+        min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+    ) -> Vector2<i32> {
+        let constraints = self.enforce(constraints);
+        match &self.child {
+            Some(child) => widget_layout(&**child, constraints, theme, min_sizes),
+            None => constraints.min,
+        }
+    }
+
+    fn compute_rects(
+        &self,
+        rect: Rect<i32>,
+        theme: &Theme,
+        min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+        widget_rects: &mut WidgetRects,
+    ) {
+        let size = widget_layout(self, BoxConstraints::loose(rect.size()), theme, min_sizes);
+        let own_rect = Rect::new(rect.start, rect.start + size);
+        widget_rects.insert(self.id(), own_rect);
+        if let Some(child) = &self.child {
+            widget_compute_rects(&**child, own_rect, theme, min_sizes, widget_rects);
+        }
+    }
+}
+
+/// Gives its child a fixed size, or takes up a fixed amount of space if it has no child. This is
+/// a `ConstrainedBox` with the same min and max size.
+pub struct SizedBox {
+    constrained_box: ConstrainedBox,
+}
+
+impl SizedBox {
+    pub fn new(size: Vector2<i32>) -> Box<Self> {
+        let constrained_box = ConstrainedBox {
+            id: WidgetId::new(),
+            child: None,
+            constraints: BoxConstraints::tight(size),
+        };
+        Box::new(SizedBox { constrained_box })
+    }
+
+    pub fn child(mut self: Box<Self>, child: Box<dyn Widget>) -> Box<Self> {
+        self.constrained_box.child = Some(child);
+        self
+    }
+}
+
+impl Widget for SizedBox {
+    fn id(&self) -> WidgetId {
+        self.constrained_box.id()
+    }
+
+    fn draw(
+        &self,
+        _context: &GlContext,
+        _surface: &dyn Surface,
+        _rect: Rect<i32>,
+        _theme: &Theme,
+        _draw_2d: &mut Draw2d,
+        _cursor_pos: Option<Point2<i32>>,
+        _is_active: bool,
+    ) {
+    }
+
+    fn layout_hash(&self) -> Option<u64> {
+        self.constrained_box.layout_hash()
+    }
+
+    fn min_size(
+        &self,
+        theme: &Theme,
+        min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+        window_size: Vector2<i32>,
+    ) -> Vector2<i32> {
+        self.constrained_box.min_size(theme, min_sizes, window_size)
+    }
+
+    fn children(&self) -> Vec<&dyn Widget> {
+        self.constrained_box.children()
+    }
+
+    fn baseline(&self, theme: &Theme) -> Option<i32> {
+        self.constrained_box.baseline(theme)
+    }
+
+    fn layout(
+        &self,
+        constraints: BoxConstraints,
+        theme: &Theme,
+        min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+    ) -> Vector2<i32> {
+        self.constrained_box.layout(constraints, theme, min_sizes)
+    }
+
+    fn compute_rects(
+        &self,
+        rect: Rect<i32>,
+        theme: &Theme,
+        min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+        widget_rects: &mut WidgetRects,
+    ) {
+        self.constrained_box.compute_rects(rect, theme, min_sizes, widget_rects);
+    }
+}
+
+/// Makes its child as large as possible while keeping a fixed ratio of width to height. It sits
+/// at the top left of the space it's given.
+pub struct AspectRatio {
+    id: WidgetId,
+    child: Box<dyn Widget>,
+    ratio: f32,
+}
+
+impl AspectRatio {
+    /// `ratio` is the width divided by the height.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ratio` isn't a positive, finite number.
+    pub fn new(ratio: f32, child: Box<dyn Widget>) -> Box<Self> {
+        assert!(ratio > 0.0 && ratio.is_finite(), "invalid aspect ratio {}", ratio);
+        Box::new(AspectRatio { id: WidgetId::new(), child, ratio })
+    }
+
+    fn height_for_width(&self, width: i32) -> i32 {
+        (width as f32 / self.ratio).round() as i32
+    }
+
+    fn width_for_height(&self, height: i32) -> i32 {
+        (height as f32 * self.ratio).round() as i32
+    }
+}
+
+impl Widget for AspectRatio {
+    fn id(&self) -> WidgetId {
+        self.id
+    }
+
+    fn draw(
+        &self,
+        _context: &GlContext,
+        _surface: &dyn Surface,
+        _rect: Rect<i32>,
+        _theme: &Theme,
+        _draw_2d: &mut Draw2d,
+        _cursor_pos: Option<Point2<i32>>,
+        _is_active: bool,
+    ) {
+    }
+
+    fn layout_hash(&self) -> Option<u64> {
+        Some(hash_layout(&("AspectRatio", self.ratio.to_bits())))
+    }
+
+    fn min_size(
+        &self,
+        _theme: &Theme,
+        min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+        _window_size: Vector2<i32>,
+    ) -> Vector2<i32> {
+        // The smallest size with the right ratio that fits the child's min size
+        let child_min_size = min_size_of(self.child.id(), min_sizes);
+        let height = child_min_size.y.max(self.height_for_width(child_min_size.x));
+        vec2(self.width_for_height(height).max(child_min_size.x), height)
+    }
+
+    fn children(&self) -> Vec<&dyn Widget> {
+        vec![&*self.child]
+    }
+
+    fn layout(
+        &self,
+        constraints: BoxConstraints,
+        _theme: &Theme,
+        min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+    ) -> Vector2<i32> {
+        let size = if constraints.has_bounded_width() {
+            let width = constraints.max.x;
+            let height = self.height_for_width(width);
+            if height > constraints.max.y {
+                vec2(self.width_for_height(constraints.max.y), constraints.max.y)
+            } else {
+                vec2(width, height)
+            }
+        } else if constraints.has_bounded_height() {
+            vec2(self.width_for_height(constraints.max.y), constraints.max.y)
+        } else {
+            min_size_of(self.id(), min_sizes)
+        };
+        constraints.constrain(size)
+    }
+
+    fn compute_rects(
+        &self,
+        rect: Rect<i32>,
+        theme: &Theme,
+        min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+        widget_rects: &mut WidgetRects,
+    ) {
+        let size = widget_layout(self, BoxConstraints::loose(rect.size()), theme, min_sizes);
+        let own_rect = Rect::new(rect.start, rect.start + size);
+        widget_rects.insert(self.id(), own_rect);
+        widget_compute_rects(&*self.child, own_rect, theme, min_sizes, widget_rects);
+    }
+}
+
+/// The space on each side of a rectangle.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct Insets {
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+}
+
+impl Insets {
+    pub fn new(left: i32, top: i32, right: i32, bottom: i32) -> Self {
+        Insets { left, top, right, bottom }
+    }
+
+    /// The same space on every side.
+    pub fn all(inset: i32) -> Self {
+        Self::new(inset, inset, inset, inset)
+    }
+
+    /// `horizontal` on the left and right, and `vertical` on the top and bottom.
+    pub fn symmetric(horizontal: i32, vertical: i32) -> Self {
+        Self::new(horizontal, vertical, horizontal, vertical)
+    }
+
+    /// Returns the total space on both sides along each axis.
+    pub fn size(&self) -> Vector2<i32> {
+        vec2(self.left + self.right, self.top + self.bottom)
+    }
+}
+
+/// Leaves space around its child, like `Inset`, but with a size set for each side rather than
+/// `Theme::padding`.
+pub struct Margin {
+    id: WidgetId,
+    child: Box<dyn Widget>,
+    insets: Insets,
+}
+
+impl Margin {
+    pub fn new(insets: Insets, child: Box<dyn Widget>) -> Box<Self> {
+        Box::new(Margin { id: WidgetId::new(), child, insets })
+    }
+}
+
+impl Widget for Margin {
+    fn id(&self) -> WidgetId {
+        self.id
+    }
+
+    fn draw(
+        &self,
+        _context: &GlContext,
+        _surface: &dyn Surface,
+        _rect: Rect<i32>,
+        _theme: &Theme,
+        _draw_2d: &mut Draw2d,
+        _cursor_pos: Option<Point2<i32>>,
+        _is_active: bool,
+    ) {
+    }
+
+    fn layout_hash(&self) -> Option<u64> {
+        Some(hash_layout(&("Margin", self.insets)))
+    }
+
+    fn min_size(
+        &self,
+        _theme: &Theme,
+        min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+        _window_size: Vector2<i32>,
+    ) -> Vector2<i32> {
+        min_size_of(self.child.id(), min_sizes) + self.insets.size()
+    }
+
+    fn children(&self) -> Vec<&dyn Widget> {
+        vec![&*self.child]
+    }
+
+    fn baseline(&self, theme: &Theme) -> Option<i32> {
+        self.child.baseline(theme).map(|baseline| baseline + self.insets.top)
+    }
+
+    fn layout(
+        &self,
+        constraints: BoxConstraints,
+        theme: &Theme,
+        min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+    ) -> Vector2<i32> {
+        let child_constraints = constraints.deflate(self.insets.size());
+        widget_layout(&*self.child, child_constraints, theme, min_sizes) + self.insets.size()
+    }
+
+    fn compute_rects(
+        &self,
+        rect: Rect<i32>,
+        theme: &Theme,
+        min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+        widget_rects: &mut WidgetRects,
+    ) {
+        widget_rects.insert(self.id(), rect);
+        let child_start = rect.start + vec2(self.insets.left, self.insets.top);
+        let child_start = point2(child_start.x.min(rect.end.x), child_start.y.min(rect.end.y));
+        let child_end = rect.end - vec2(self.insets.right, self.insets.bottom);
+        // The child is never given a negative size or put outside the margin, even if the margin
+        // doesn't fit
+        let child_end = point2(child_end.x.max(child_start.x), child_end.y.max(child_start.y));
+        widget_compute_rects(
+            &*self.child,
+            Rect::new(child_start, child_end),
+            theme,
+            min_sizes,
+            widget_rects,
+        );
+    }
+}
+
+/// Lets the user select one of several options.
+#[derive(Clone)]
+pub struct Selector<T: Copy + PartialEq> {
+    options: Vec<(String, T)>,
+    selected_option: Option<usize>,
+    id: WidgetId,
+}
+
+impl<T: Copy + PartialEq> Selector<T> {
+    pub fn new(options: Vec<(String, T)>, selected_option: Option<(String, T)>) -> Box<Self> {
+        Box::new(Self {
+            selected_option: selected_option
+                .map(|selected_option| options.iter().position(|x| &selected_option == x).unwrap()),
+            options,
+            id: WidgetId::new(),
+        })
+    }
+
+    pub fn selected_option(&self) -> Option<T> {
+        self.selected_option.map(|selected_option| self.options[selected_option].1)
+    }
+
+    pub fn add_option(&mut self, option: (String, T)) {
+        self.options.push(option);
+    }
+
+    pub fn remove_option(&mut self, i: usize) {
+        self.options.remove(i);
+        if let Some(selected_option) = &mut self.selected_option {
+            if *selected_option == i {
+                self.selected_option = None;
+            } else if *selected_option > i {
+                *selected_option -= 1;
+            }
+        }
+    }
+}
+
+impl<T: Copy + PartialEq> Widget for Selector<T> {
+    fn id(&self) -> WidgetId {
+        self.id
+    }
+
+    fn clips_to_rect(&self) -> bool {
+        true
+    }
+
+    fn is_component(&self) -> bool {
+        true
+    }
+
+    fn draw(
+        &self,
+        context: &GlContext,
+        _surface: &dyn Surface,
+        rect: Rect<i32>,
+        theme: &Theme,
+        draw_2d: &mut Draw2d,
+        cursor_pos: Option<Point2<i32>>,
+        _is_active: bool,
+    ) {
+        for (i, (line, _)) in self.options.iter().enumerate() {
+            let pos = rect.start.cast().unwrap() + vec2(0, theme.font.advance_y() * i as i32);
+            let rect = Rect::new(pos, pos + theme.font.string_size(line));
+            let background_color = if Some(i) == self.selected_option {
+                Color4::WHITE.mul_srgb(0.5)
+            } else if cursor_pos.is_some()
+                && rect.contains_point(cursor_pos.unwrap().cast().unwrap())
+            {
+                Color4::WHITE.mul_srgb(0.75)
+            } else {
+                Color4::WHITE
+            };
+            draw_2d.fill_rect(rect, background_color);
+            theme.font.draw_string(context, line, pos, Color4::BLACK);
+        }
+    }
+
+    fn layout_hash(&self) -> Option<u64> {
+        let options: Vec<&str> = self.options.iter().map(|(option, _)| option.as_str()).collect();
+        Some(hash_layout(&("Selector", options)))
+    }
+
+    fn min_size(
+        &self,
+        theme: &Theme,
+        _min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+        _window_size: Vector2<i32>,
+    ) -> Vector2<i32> {
+        let max_width =
+            self.options.iter().map(|(x, _)| theme.font.string_width(x).ceil() as i32).max();
+        if let Some(max_width) = max_width {
+            vec2(max_width, theme.font.advance_y() * self.options.len() as i32)
+        } else {
+            vec2(0, 0)
+        }
+    }
+}
+
+pub struct SelectorResult<T: Copy + PartialEq> {
+    pub selected: Option<(String, T)>,
+    pub just_selected: bool,
+}
+
+impl<T: Copy + PartialEq> Component for Selector<T> {
+    type Res = SelectorResult<T>;
+
+    fn update(&mut self, theme: &Theme, events: Vec<Event>) -> Self::Res {
+        let mut just_selected = false;
+        for event in events {
+            if let Event::MouseDown(MouseButton::Left, pos) = event {
+                let entry = pos.y / theme.font.advance_y();
+                assert!(
+                    entry >= 0 && (entry as usize) < self.options.len(),
+                    "entry {} out of range (max={})",
+                    entry,
+                    self.options.len()
+                );
+                self.selected_option = Some(entry as usize);
+                just_selected = true;
+            }
+        }
+
+        SelectorResult {
+            selected: self
+                .selected_option
+                .map(|selected_option| self.options[selected_option].clone()),
+            just_selected,
+        }
+    }
+}
+
+pub struct Fill {
+    id: WidgetId,
+    child: Box<dyn Widget>,
+    fill_color: Color4,
+}
+
+impl Fill {
+    pub fn new(fill_color: Color4, child: Box<dyn Widget>) -> Box<Self> {
+        Box::new(Fill { id: WidgetId::new(), child, fill_color })
+    }
+}
+
+impl Widget for Fill {
+    fn id(&self) -> WidgetId {
+        self.id
+    }
+
+    fn draw(
+        &self,
+        _context: &GlContext,
+        _surface: &dyn Surface,
+        rect: Rect<i32>,
+        _theme: &Theme,
+        draw_2d: &mut Draw2d,
+        _cursor_pos: Option<Point2<i32>>,
+        _is_active: bool,
+    ) {
+        // TODO: should this subtract (1,1) from end, like Border does?
+        draw_2d.fill_rect(Rect::new(rect.start, rect.end), self.fill_color);
+    }
+
+    fn layout_hash(&self) -> Option<u64> {
+        Some(hash_layout(&"Fill"))
+    }
+
+    fn min_size(
+        &self,
+        _theme: &Theme,
+        min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+        _window_size: Vector2<i32>,
+    ) -> Vector2<i32> {
+        min_size_of(self.child.id(), min_sizes)
+    }
+
+    fn baseline(&self, theme: &Theme) -> Option<i32> {
+        self.child.baseline(theme)
+    }
+
+    fn children(&self) -> Vec<&dyn Widget> {
+        vec![&*self.child]
+    }
+
+    fn layout(
+        &self,
+        constraints: BoxConstraints,
+        theme: &Theme,
+        min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+    ) -> Vector2<i32> {
+        widget_layout(&*self.child, constraints, theme, min_sizes)
+    }
+
+    fn compute_rects(
+        &self,
+        rect: Rect<i32>,
+        theme: &Theme,
+        min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+        widget_rects: &mut WidgetRects,
+    ) {
+        widget_rects.insert(self.id(), Rect::new(rect.start, rect.end));
+        widget_compute_rects(
+            &*self.child,
+            Rect::new(rect.start, rect.end),
+            theme,
+            min_sizes,
+            widget_rects,
+        );
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub enum TextEntryEvent {
+    AddChar(char),
+    Backspace,
+    CaretLeft,
+    CaretRight,
+    EnterPressed,
+}
+
+pub struct TextEntryResult {
+    pub text: Option<String>,
+}
+
+impl TextEntryResult {
+    pub fn text(&self) -> Option<&str> {
+        // https://stackoverflow.com/questions/31233938/converting-from-optionstring-to-optionstr
+        self.text.as_ref().map(|x| x.as_ref())
+    }
+}
+
+const CARET_BLINK_RATE: f64 = 1.0;
+
+#[derive(Clone)]
+pub struct TextEntry {
+    id: WidgetId,
+    pub text: String,
+    placeholder_text: String,
+    text_color: Color4,
+    /// The byte index of the caret in `text`.
+    caret_pos: usize,
+    /// The other end of the selection. The text between this and the caret is selected.
+    selection_anchor: Option<usize>,
+    /// True while the left mouse button is held down after clicking on the text entry.
+    dragging: bool,
+    // TODO: this should be a max length in pixels
+    max_len: usize,
+    stopwatch: Stopwatch,
+    use_placeholder_text_if_empty: bool,
+    continuous_updates: bool,
+    clipboard: Rc<dyn Clipboard>,
+}
+
+impl TextEntry {
+    pub fn new(
+        start_text: &str,
+        placeholder_text: &str,
+        use_placeholder_text_if_empty: bool,
+        max_len: usize,
+        continuous_updates: bool,
+    ) -> Box<Self> {
+        assert!(placeholder_text.graphemes(true).count() <= max_len);
+        Box::new(TextEntry {
+            id: WidgetId::new(),
+            text: start_text.to_string(),
+            placeholder_text: placeholder_text.to_string(),
+            text_color: Color4::BLACK,
+            caret_pos: 0,
+            selection_anchor: None,
+            dragging: false,
+            max_len,
+            stopwatch: Stopwatch::new(),
+            use_placeholder_text_if_empty,
+            continuous_updates,
+            clipboard: default_clipboard(),
+        })
+    }
+
+    pub fn text_color(mut self: Box<Self>, color: Color4) -> Box<Self> {
+        self.text_color = color;
+        self
+    }
+
+    /// Sets the clipboard used for copying, cutting and pasting. Defaults to
+    /// `default_clipboard()`.
+    pub fn clipboard(mut self: Box<Self>, clipboard: Rc<dyn Clipboard>) -> Box<Self> {
+        self.clipboard = clipboard;
+        self
+    }
+
+    pub fn cur_text(&self) -> &str {
+        if self.text.is_empty() && self.use_placeholder_text_if_empty {
+            &self.placeholder_text
+        } else {
+            &self.text
+        }
+    }
+
+    /// Returns the byte range of the selected text, if any text is selected.
+    pub fn selection(&self) -> Option<Range<usize>> {
+        match self.selection_anchor {
+            Some(anchor) if anchor < self.caret_pos => Some(anchor..self.caret_pos),
+            Some(anchor) if anchor > self.caret_pos => Some(self.caret_pos..anchor),
+            _ => None,
+        }
+    }
+
+    fn cur_text_mutable(&mut self) -> String {
+        if self.text.is_empty() && self.use_placeholder_text_if_empty {
+            self.placeholder_text.clone()
+        } else {
+            if self.continuous_updates {
+                self.text.clone()
+            } else {
+                mem::replace(&mut self.text, "".to_owned())
+            }
+        }
+    }
+
+    /// `text` is public, so it may have changed since the caret was last moved, leaving the caret
+    /// or the selection anchor past the end or inside a grapheme cluster.
+    fn clamp_caret(&mut self) {
+        self.caret_pos = snap_to_grapheme_boundary(&self.text, self.caret_pos);
+        self.selection_anchor =
+            self.selection_anchor.map(|anchor| snap_to_grapheme_boundary(&self.text, anchor));
+    }
+
+    /// Returns the position closest to `x`, relative to the start of the text.
+    fn pos_at_x(&self, theme: &Theme, x: f32) -> usize {
+        theme.font.layout(&self.text, None).hit_test(point2(x, 0.0))
+    }
+
+    fn move_caret(&mut self, pos: usize, extend_selection: bool) {
+        if extend_selection {
+            self.selection_anchor.get_or_insert(self.caret_pos);
+        } else {
+            self.selection_anchor = None;
+        }
+        self.caret_pos = pos;
+    }
+
+    /// Replaces the selection, or inserts at the caret if nothing is selected. The replacement is
+    /// truncated so the text doesn't exceed `max_len` grapheme clusters.
+    fn replace_selection(&mut self, replacement: &str) {
+        let range = self.selection().unwrap_or(self.caret_pos..self.caret_pos);
+        let kept_len =
+            self.text.graphemes(true).count() - self.text[range.clone()].graphemes(true).count();
+        let replacement =
+            match replacement.grapheme_indices(true).nth(self.max_len.saturating_sub(kept_len)) {
+                Some((end, _)) => &replacement[..end],
+                None => replacement,
+            };
+        self.text.replace_range(range.clone(), replacement);
+        self.caret_pos = range.start + replacement.len();
+        self.selection_anchor = None;
+    }
+
+    /// Replaces the selection with pasted text, dropping line breaks since text entries are a
+    /// single line.
+    fn paste(&mut self, pasted: &str) {
+        let pasted: String = pasted.chars().filter(|c| !c.is_control()).collect();
+        self.replace_selection(&pasted);
+    }
+}
+
+/// Returns the start of the word before `pos`, skipping any whitespace in between.
+fn prev_word_boundary(text: &str, pos: usize) -> usize {
+    text[..pos].trim_end().trim_end_matches(|c: char| !c.is_whitespace()).len()
+}
+
+/// Returns the end of the word after `pos`, skipping any whitespace in between.
+fn next_word_boundary(text: &str, pos: usize) -> usize {
+    let rest = text[pos..].trim_start().trim_start_matches(|c: char| !c.is_whitespace());
+    text.len() - rest.len()
+}
+
+impl Component for TextEntry {
+    type Res = TextEntryResult;
+
+    fn update(&mut self, theme: &Theme, events: Vec<Event>) -> TextEntryResult {
+        let mut res = None;
+        self.clamp_caret();
+        for event in events {
+            match event {
+                Event::KeyDown(key) => {
+                    let shift = key.shift;
+                    let layout = theme.font.layout(&self.text, None);
+                    match key.key.as_ref() {
+                        "ArrowLeft" | "ArrowRight" => {
+                            let left = key.key == "ArrowLeft";
+                            let pos = match self.selection() {
+                                Some(selection) if !shift && !key.ctrl => {
+                                    if left {
+                                        selection.start
+                                    } else {
+                                        selection.end
+                                    }
+                                }
+                                _ => match (left, key.ctrl) {
+                                    (true, true) => prev_word_boundary(&self.text, self.caret_pos),
+                                    (true, false) => layout.move_visually(self.caret_pos, false),
+                                    (false, true) => next_word_boundary(&self.text, self.caret_pos),
+                                    (false, false) => layout.move_visually(self.caret_pos, true),
+                                },
+                            };
+                            self.move_caret(pos, shift);
+                        }
+                        "Home" => self.move_caret(0, shift),
+                        "End" => self.move_caret(self.text.len(), shift),
+                        "Backspace" => {
+                            if self.selection().is_none() {
+                                let start = if key.ctrl {
+                                    prev_word_boundary(&self.text, self.caret_pos)
+                                } else {
+                                    layout.prev_boundary(self.caret_pos)
+                                };
+                                self.selection_anchor = Some(start);
+                            }
+                            self.replace_selection("");
+                        }
+                        "Delete" => {
+                            if self.selection().is_none() {
+                                let end = if key.ctrl {
+                                    next_word_boundary(&self.text, self.caret_pos)
+                                } else {
+                                    layout.next_boundary(self.caret_pos)
+                                };
+                                self.selection_anchor = Some(end);
+                            }
+                            self.replace_selection("");
+                        }
+                        "Enter" => {
+                            res = Some(self.cur_text_mutable());
+                            self.caret_pos = 0;
+                            self.selection_anchor = None;
+                        }
+                        _ if key.ctrl && key.key.eq_ignore_ascii_case("a") => {
+                            self.selection_anchor = Some(0);
+                            self.caret_pos = self.text.len();
+                        }
+                        _ if key.ctrl && key.key.eq_ignore_ascii_case("c") => {
+                            if let Some(selection) = self.selection() {
+                                self.clipboard.set_text(&self.text[selection]);
+                            }
+                        }
+                        _ if key.ctrl && key.key.eq_ignore_ascii_case("x") => {
+                            if let Some(selection) = self.selection() {
+                                self.clipboard.set_text(&self.text[selection]);
+                                self.replace_selection("");
+                            }
+                        }
+                        _ if key.ctrl && key.key.eq_ignore_ascii_case("v") => {
+                            if let Some(pasted) = self.clipboard.get_text() {
+                                self.paste(&pasted);
+                            }
+                        }
+                        _ => {
+                            if let Some(text) = key.text() {
+                                self.replace_selection(text);
+                            }
+                        }
+                    }
+                }
+                Event::Paste(pasted) => self.paste(&pasted),
+                Event::MouseDown(MouseButton::Left, pos) => {
+                    let pos = self.pos_at_x(theme, (pos.x - 2) as f32);
+                    self.move_caret(pos, false);
+                    self.selection_anchor = Some(pos);
+                    self.dragging = true;
+                }
+                Event::MouseMove { pos, .. } if self.dragging => {
+                    self.caret_pos = self.pos_at_x(theme, (pos.x - 2) as f32);
+                }
+                Event::MouseUp(MouseButton::Left, _) | Event::FocusLost => {
+                    self.dragging = false;
+                    if self.selection().is_none() {
+                        self.selection_anchor = None;
+                    }
+                }
+                _ => (),
+            }
+        }
+        if self.continuous_updates {
+            res = Some(self.cur_text().to_owned());
+        }
+        TextEntryResult { text: res }
+    }
+}
+
+impl Widget for TextEntry {
+    fn id(&self) -> WidgetId {
+        self.id
+    }
+
+    fn clips_to_rect(&self) -> bool {
+        true
+    }
+
+    fn is_component(&self) -> bool {
+        true
+    }
+
+    fn draw(
+        &self,
+        context: &GlContext,
+        _surface: &dyn Surface,
+        rect: Rect<i32>,
+        theme: &Theme,
+        draw_2d: &mut Draw2d,
+        _cursor_pos: Option<Point2<i32>>,
+        is_active: bool,
+    ) {
+        let fill_color = theme.button_fill_color;
+        let (drawn_text, drawn_text_color) = if self.text.is_empty() {
+            (&self.placeholder_text, theme.button_text_color * 0.8)
+        } else {
+            (&self.text, theme.button_text_color)
+        };
+        draw_2d.fill_rect(rect, fill_color);
+        draw_2d.outline_rect(rect, theme.button_border_color, 1.0);
+        // The text may have been changed from outside since the last update
+        let caret_pos = if self.text.is_char_boundary(self.caret_pos) { self.caret_pos } else { 0 };
+        // The placeholder text is only drawn when the text is empty, so nothing can be selected
+        // and the caret is at its start
+        let layout = theme.font.layout(drawn_text, None);
+        if let Some(selection) = self.selection() {
+            if self.text.get(selection.clone()).is_some() {
+                // Bidirectional text can have a selection that isn't contiguous on screen
+                for selection_rect in layout.selection_rects(selection) {
+                    draw_2d.fill_rect(
+                        Rect::new(
+                            point2(
+                                rect.start.x + 2 + selection_rect.start.x as i32,
+                                rect.start.y + 1,
+                            ),
+                            point2(rect.start.x + 2 + selection_rect.end.x as i32, rect.end.y - 1),
+                        ),
+                        theme.selection_color,
+                    );
+                }
+            }
+        }
+        let text_start = rect.start + vec2(2, 1);
+        theme.font.draw_layout(context, &layout, text_start.cast().unwrap(), drawn_text_color);
+        if self.stopwatch.get_time().rem_euclid(CARET_BLINK_RATE) < CARET_BLINK_RATE * 0.5
+            && is_active
+        {
+            let caret_x_offset = layout.caret_rect(caret_pos).start.x + 2.0;
+            draw_2d.draw_line(
+                point2(caret_x_offset + rect.start.x as f32, rect.start.y as f32 + 2.0),
+                point2(caret_x_offset + rect.start.x as f32, rect.end.y as f32 - 2.0),
+                theme.button_text_color,
+                1.0,
+            );
+        }
+    }
+
+    fn baseline(&self, theme: &Theme) -> Option<i32> {
+        Some(theme.font.ascent().round() as i32 + 1)
+    }
+
+    fn layout_hash(&self) -> Option<u64> {
+        Some(hash_layout(&("TextEntry", &self.text, &self.placeholder_text)))
+    }
+
+    fn min_size(
+        &self,
+        theme: &Theme,
+        _min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+        _window_size: Vector2<i32>,
+    ) -> Vector2<i32> {
+        let drawn_text = if self.text.is_empty() { &self.placeholder_text } else { &self.text };
+        ceil_size(theme.font.layout(drawn_text, None).size()) + vec2(4, 2)
+    }
+}
+
+const SCROLLBAR_WIDTH: i32 = 12;
+/// The number of lines to scroll per mouse wheel step.
+const SCROLL_LINES: i32 = 3;
+
+struct ScrollbarDrag {
+    axis: usize,
+    start_pos: i32,
+    start_offset: i32,
+}
+
+/// The geometry of a scrollbar, relative to the top-left corner of its `ScrollArea`.
+struct Scrollbar {
+    axis: usize,
+    track: Rect<i32>,
+    thumb: Rect<i32>,
+}
+
+struct ScrollState {
+    offset: Vector2<i32>,
+    // These are from the most recent layout
+    size: Vector2<i32>,
+    content_size: Vector2<i32>,
+    drag: Option<ScrollbarDrag>,
+}
+
+impl ScrollState {
+    /// Returns the size of the area the child is visible through, which excludes the scrollbars.
+    fn viewport_size(&self) -> Vector2<i32> {
+        scroll_viewport_size(self.size, self.content_size)
+    }
+
+    fn max_offset(&self) -> Vector2<i32> {
+        max_scroll_offset(self.size, self.content_size)
+    }
+
+    fn clamp_offset(&mut self) {
+        self.offset = clamp_scroll_offset(self.offset, self.size, self.content_size);
+    }
+
+    fn scrollbars(&self) -> Vec<Scrollbar> {
+        let viewport_size = self.viewport_size();
+        let max_offset = self.max_offset();
+        let mut scrollbars = vec![];
+        for axis in 0..2 {
+            if max_offset[axis] == 0 {
+                continue;
+            }
+            let track_len = viewport_size[axis];
+            let thumb_len = (track_len * viewport_size[axis] / self.content_size[axis])
+                .max(SCROLLBAR_WIDTH)
+                .min(track_len);
+            let thumb_pos = (track_len - thumb_len) * self.offset[axis] / max_offset[axis];
+
+            let mut track_start = Point2::origin();
+            track_start[1 - axis] = viewport_size[1 - axis];
+            let mut track_size = vec2(SCROLLBAR_WIDTH, SCROLLBAR_WIDTH);
+            track_size[axis] = track_len;
+            let mut thumb_start = track_start;
+            thumb_start[axis] = thumb_pos;
+            let mut thumb_size = track_size;
+            thumb_size[axis] = thumb_len;
+
+            scrollbars.push(Scrollbar {
+                axis,
+                track: Rect::new(track_start, track_start + track_size),
+                thumb: Rect::new(thumb_start, thumb_start + thumb_size),
+            });
+        }
+        scrollbars
+    }
+}
+
+/// Returns the size of the viewport of a `ScrollArea`, after making room for any scrollbars
+/// that are needed to show content of the given size.
+fn scroll_viewport_size(size: Vector2<i32>, content_size: Vector2<i32>) -> Vector2<i32> {
+    let mut needs_vertical = content_size.y > size.y;
+    let needs_horizontal =
+        content_size.x > size.x - if needs_vertical { SCROLLBAR_WIDTH } else { 0 };
+    needs_vertical = needs_vertical
+        || content_size.y > size.y - if needs_horizontal { SCROLLBAR_WIDTH } else { 0 };
+    vec2(
+        size.x - if needs_vertical { SCROLLBAR_WIDTH } else { 0 },
+        size.y - if needs_horizontal { SCROLLBAR_WIDTH } else { 0 },
+    )
+}
+
+/// Returns how far content of the given size can be scrolled in a `ScrollArea`.
+fn max_scroll_offset(size: Vector2<i32>, content_size: Vector2<i32>) -> Vector2<i32> {
+    let viewport_size = scroll_viewport_size(size, content_size);
+    vec2((content_size.x - viewport_size.x).max(0), (content_size.y - viewport_size.y).max(0))
+}
+
+fn clamp_scroll_offset(
+    offset: Vector2<i32>,
+    size: Vector2<i32>,
+    content_size: Vector2<i32>,
+) -> Vector2<i32> {
+    let max_offset = max_scroll_offset(size, content_size);
+    vec2(offset.x.max(0).min(max_offset.x), offset.y.max(0).min(max_offset.y))
+}
+
+pub struct ScrollAreaResult {
+    offset: Vector2<i32>,
+}
+
+impl ScrollAreaResult {
+    /// How far the child is scrolled, in pixels.
+    pub fn offset(&self) -> Vector2<i32> {
+        self.offset
+    }
+}
+
+/// Shows a child that may be larger than the space available, through a viewport that can be
+/// scrolled with the mouse wheel or by dragging the scrollbars.
+///
+/// Like other components, this should be kept between frames. Its child is laid out at its min
+/// size, so each frame call `view` with the child to get a widget to add to the GUI.
+pub struct ScrollArea {
+    id: WidgetId,
+    // Shared with the widgets returned by `view`, so that they can record the layout
+    state: Rc<RefCell<ScrollState>>,
+    min_size: Vector2<i32>,
+    child: Option<Box<dyn Widget>>,
+}
+
+impl ScrollArea {
+    pub fn new() -> Box<Self> {
+        Box::new(ScrollArea {
+            id: WidgetId::new(),
+            state: Rc::new(RefCell::new(ScrollState {
+                offset: Vector2::zero(),
+                size: Vector2::zero(),
+                content_size: Vector2::zero(),
+                drag: None,
+            })),
+            min_size: vec2(SCROLLBAR_WIDTH, SCROLLBAR_WIDTH),
+            child: None,
+        })
+    }
+
+    /// Sets the smallest size the viewport can be shrunk to.
+    pub fn min_size(mut self: Box<Self>, min_size: Vector2<i32>) -> Box<Self> {
+        self.min_size = min_size;
+        self
+    }
+
+    /// Returns a widget that shows the given child through this `ScrollArea`.
+    pub fn view(&self, child: Box<dyn Widget>) -> Box<Self> {
+        Box::new(ScrollArea {
+            id: self.id,
+            state: self.state.clone(),
+            min_size: self.min_size,
+            child: Some(child),
+        })
+    }
+
+    /// How far the child is scrolled, in pixels.
+    pub fn offset(&self) -> Vector2<i32> {
+        self.state.borrow().offset
+    }
+
+    /// Scrolls to the given offset. It's clamped to the scrollable range the next time the
+    /// `ScrollArea` is laid out.
+    pub fn set_offset(&mut self, offset: Vector2<i32>) {
+        self.state.borrow_mut().offset = offset;
+    }
+}
+
+impl Component for ScrollArea {
+    type Res = ScrollAreaResult;
+
+    fn update(&mut self, theme: &Theme, events: Vec<Event>) -> ScrollAreaResult {
+        let mut state = self.state.borrow_mut();
+        let line_height = theme.font.advance_y();
+        let viewport_size = state.viewport_size();
+        for event in events {
+            match event {
+                Event::Scroll(delta) => {
+                    state.offset.y += delta as i32 * line_height * SCROLL_LINES;
+                }
+                Event::MouseDown(MouseButton::Left, pos) => {
+                    let scrollbars = state.scrollbars();
+                    let scrollbar = scrollbars.iter().find(|x| x.track.contains_point(pos));
+                    if let Some(scrollbar) = scrollbar {
+                        let axis = scrollbar.axis;
+                        if scrollbar.thumb.contains_point(pos) {
+                            state.drag = Some(ScrollbarDrag {
+                                axis,
+                                start_pos: pos[axis],
+                                start_offset: state.offset[axis],
+                            });
+                        } else if pos[axis] < scrollbar.thumb.start[axis] {
+                            state.offset[axis] -= viewport_size[axis];
+                        } else {
+                            state.offset[axis] += viewport_size[axis];
+                        }
+                    }
+                }
+                Event::MouseMove { pos, .. } => {
+                    let max_offset = state.max_offset();
+                    let scrollbars = state.scrollbars();
+                    if let Some(drag) = &state.drag {
+                        let axis = drag.axis;
+                        if let Some(scrollbar) = scrollbars.iter().find(|x| x.axis == axis) {
+                            let free_len =
+                                scrollbar.track.size()[axis] - scrollbar.thumb.size()[axis];
+                            if free_len > 0 {
+                                let offset = drag.start_offset
+                                    + (pos[axis] - drag.start_pos) * max_offset[axis] / free_len;
+                                state.offset[axis] = offset;
+                            }
+                        }
+                    }
+                }
+                Event::MouseUp(MouseButton::Left, _) | Event::FocusLost => state.drag = None,
+                Event::KeyDown(key) => match key.key.as_ref() {
+                    "ArrowUp" => state.offset.y -= line_height,
+                    "ArrowDown" => state.offset.y += line_height,
+                    "PageUp" => state.offset.y -= viewport_size.y,
+                    "PageDown" => state.offset.y += viewport_size.y,
+                    "Home" => state.offset.y = 0,
+                    "End" => state.offset.y = state.max_offset().y,
+                    _ => (),
+                },
+                _ => (),
+            }
+            state.clamp_offset();
+        }
+
+        ScrollAreaResult { offset: state.offset }
+    }
+}
+
+impl Widget for ScrollArea {
+    fn id(&self) -> WidgetId {
+        self.id
+    }
+
+    fn is_component(&self) -> bool {
+        true
+    }
+
+    fn draw(
+        &self,
+        _context: &GlContext,
+        _surface: &dyn Surface,
+        rect: Rect<i32>,
+        theme: &Theme,
+        draw_2d: &mut Draw2d,
+        cursor_pos: Option<Point2<i32>>,
+        _is_active: bool,
+    ) {
+        let state = self.state.borrow();
+        let offset = rect.start.to_vec();
+        for scrollbar in state.scrollbars() {
+            let track = Rect::new(scrollbar.track.start + offset, scrollbar.track.end + offset);
+            let thumb = Rect::new(scrollbar.thumb.start + offset, scrollbar.thumb.end + offset);
+            let thumb_color = if state.drag.as_ref().map(|x| x.axis) == Some(scrollbar.axis) {
+                theme.button_active_fill_color
+            } else if cursor_pos.is_some() && thumb.contains_point(cursor_pos.unwrap()) {
+                theme.button_selected_fill_color
+            } else {
+                theme.button_fill_color
+            };
+            draw_2d.fill_rect(track, theme.button_fill_color.mul_srgb(0.8));
+            draw_2d.fill_rect(thumb, thumb_color);
+            draw_2d.outline_rect(thumb, theme.button_border_color, 1.0);
+        }
+    }
+
+    // The offset is hashed because the child is laid out at it
+    fn layout_hash(&self) -> Option<u64> {
+        let offset = self.state.borrow().offset;
+        Some(hash_layout(&("ScrollArea", self.min_size, offset)))
+    }
+
+    fn min_size(
+        &self,
+        _theme: &Theme,
+        _min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+        _window_size: Vector2<i32>,
+    ) -> Vector2<i32> {
+        self.min_size
+    }
+
+    fn children(&self) -> Vec<&dyn Widget> {
+        self.child.iter().map(|child| &**child as &dyn Widget).collect()
+    }
+
+    fn child_clip_rect(&self, rect: Rect<i32>) -> Option<Rect<i32>> {
+        Some(Rect::new(rect.start, rect.start + self.state.borrow().viewport_size()))
+    }
+
+    fn compute_rects(
+        &self,
+        rect: Rect<i32>,
+        theme: &Theme,
+        min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+        widget_rects: &mut WidgetRects,
+    ) {
+        widget_rects.insert(self.id(), rect);
+        if let Some(child) = &self.child {
+            let child_min_size = min_size_of(child.id(), min_sizes);
+            // The child's height can depend on its width, which depends on whether there's a
+            // vertical scrollbar, which depends on the child's height. Two passes are enough to
+            // settle this in practice.
+            let mut content_size = child_min_size;
+            for _ in 0..2 {
+                let viewport_size = scroll_viewport_size(rect.size(), content_size);
+                let width = child_min_size.x.max(viewport_size.x);
+                content_size =
+                    vec2(width, widget_height_for_width(&**child, width, theme, min_sizes));
+            }
+            // The child fills the viewport if it's smaller than it
+            let viewport_size = scroll_viewport_size(rect.size(), content_size);
+            let content_size =
+                vec2(content_size.x.max(viewport_size.x), content_size.y.max(viewport_size.y));
+            // The clamped offset is recorded by `laid_out`
+            let offset = clamp_scroll_offset(self.state.borrow().offset, rect.size(), content_size);
+
+            let child_start = rect.start - offset;
+            let child_rect = Rect::new(child_start, child_start + content_size);
+            let viewport =
+                Rect::new(rect.start, rect.start + scroll_viewport_size(rect.size(), content_size));
+            widget_rects.with_bounds(viewport, |widget_rects| {
+                widget_compute_rects(&**child, child_rect, theme, min_sizes, widget_rects)
+            });
+        }
+    }
+
+    fn laid_out(&self, widget_rects: &FnvHashMap<WidgetId, Rect<i32>>) {
+        let child_rect = self.child.as_ref().and_then(|child| widget_rects.get(&child.id()));
+        if let (Some(&rect), Some(&child_rect)) = (widget_rects.get(&self.id), child_rect) {
+            let mut state = self.state.borrow_mut();
+            state.size = rect.size();
+            state.content_size = child_rect.size();
+            state.offset = rect.start - child_rect.start;
+        }
+    }
+}
+
+/// Returns `pos` if it's between grapheme clusters, or else the start of the cluster it's in.
+/// Positions past the end of the text are moved to the end.
+fn snap_to_grapheme_boundary(text: &str, pos: usize) -> usize {
+    if pos >= text.len() {
+        return text.len();
+    }
+    text.grapheme_indices(true).map(|(i, _)| i).take_while(|&i| i <= pos).last().unwrap_or(0)
+}
+
+/// A change to the text of a `TextArea`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TextEdit {
+    /// The byte range of the old text that was replaced.
+    pub range: Range<usize>,
+    /// The text it was replaced with.
+    pub replacement: String,
+}
+
+pub struct TextAreaResult {
+    /// The edits made since the last update, in the order they were made.
+    pub edits: Vec<TextEdit>,
+    pub text: String,
+}
+
+impl TextAreaResult {
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns true if the text was changed since the last update.
+    pub fn edited(&self) -> bool {
+        !self.edits.is_empty()
+    }
+}
+
+/// A multi-line text editor. Lines are wrapped to the width of the widget, and the text scrolls
+/// to keep the caret visible.
+#[derive(Clone)]
+pub struct TextArea {
+    id: WidgetId,
+    text: String,
+    text_color: Color4,
+    rows: i32,
+    caret_pos: usize,
+    // The other end of the selection from the caret, if any text is selected
+    selection_anchor: Option<usize>,
+    // The x position the caret stays near when moving up and down
+    preferred_x: Option<f32>,
+    dragging: bool,
+    scroll_y: i32,
+    stopwatch: Stopwatch,
+    // Shared with clones in the GUI, so that they can record the size they're laid out at
+    size: Rc<Cell<Vector2<i32>>>,
+}
+
+impl TextArea {
+    /// Creates a `TextArea` that's at least `rows` lines tall.
+    pub fn new(start_text: &str, rows: i32) -> Box<Self> {
+        Box::new(TextArea {
+            id: WidgetId::new(),
+            text: start_text.to_owned(),
+            text_color: Color4::BLACK,
+            rows,
+            caret_pos: 0,
+            selection_anchor: None,
+            preferred_x: None,
+            dragging: false,
+            scroll_y: 0,
+            stopwatch: Stopwatch::new(),
+            size: Rc::new(Cell::new(Vector2::zero())),
+        })
+    }
+
+    pub fn text_color(mut self: Box<Self>, color: Color4) -> Box<Self> {
+        self.text_color = color;
+        self
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Replaces the text, and moves the caret to the end of it.
+    pub fn set_text(&mut self, text: &str) {
+        self.text = text.to_owned();
+        self.caret_pos = self.text.len();
+        self.selection_anchor = None;
+        self.preferred_x = None;
+    }
+
+    /// Returns the byte range of the selected text, if any.
+    pub fn selection(&self) -> Option<Range<usize>> {
+        match self.selection_anchor {
+            Some(anchor) if anchor < self.caret_pos => Some(anchor..self.caret_pos),
+            Some(anchor) if anchor > self.caret_pos => Some(self.caret_pos..anchor),
+            _ => None,
+        }
+    }
+
+    fn wrap_width(width: i32) -> f32 {
+        (width - 4).max(0) as f32
+    }
+
+    /// Lays out the text at the size it was last laid out at. Before the first layout the size
+    /// isn't known, so the text isn't wrapped.
+    fn layout(&self, theme: &Theme) -> Rc<TextLayout> {
+        let max_width = match self.size.get().x {
+            0 => None,
+            width => Some(Self::wrap_width(width)),
+        };
+        theme.font.layout(&self.text, max_width)
+    }
+
+    /// Returns the position closest to a point relative to the top-left of the widget.
+    fn pos_at_point(&self, layout: &TextLayout, point: Point2<i32>) -> usize {
+        layout.hit_test(point2(point.x - 2, point.y - 1 + self.scroll_y).cast().unwrap())
+    }
+
+    /// Moves the caret vertically by the given number of lines.
+    fn pos_after_moving_lines(&mut self, layout: &TextLayout, delta: i32) -> usize {
+        let line = layout.line_at(self.caret_pos) as i32 + delta;
+        if line < 0 {
+            0
+        } else if line >= layout.lines().len() as i32 {
+            self.text.len()
+        } else {
+            let x = match self.preferred_x {
+                Some(x) => x,
+                None => layout.caret_rect(self.caret_pos).start.x,
+            };
+            self.preferred_x = Some(x);
+            layout.hit_test_line(line as usize, x)
+        }
+    }
+
+    fn move_caret(&mut self, pos: usize, extend_selection: bool) {
+        if extend_selection {
+            self.selection_anchor.get_or_insert(self.caret_pos);
+        } else {
+            self.selection_anchor = None;
+        }
+        self.caret_pos = pos;
+    }
+
+    /// Replaces the selection, or inserts at the caret if nothing is selected.
+    fn replace_selection(&mut self, replacement: &str, edits: &mut Vec<TextEdit>) {
+        let range = self.selection().unwrap_or(self.caret_pos..self.caret_pos);
+        self.replace_range(range, replacement, edits);
+    }
+
+    fn replace_range(&mut self, range: Range<usize>, replacement: &str, edits: &mut Vec<TextEdit>) {
+        self.selection_anchor = None;
+        self.preferred_x = None;
+        if range.is_empty() && replacement.is_empty() {
+            return;
+        }
+        self.text.replace_range(range.clone(), replacement);
+        self.caret_pos = range.start + replacement.len();
+        edits.push(TextEdit { range, replacement: replacement.to_owned() });
+    }
+
+    fn visible_rows(&self, theme: &Theme) -> i32 {
+        ((self.size.get().y - 2) / theme.font.advance_y()).max(1)
+    }
+
+    fn max_scroll_y(&self, theme: &Theme, num_lines: usize) -> i32 {
+        (num_lines as i32 * theme.font.advance_y() - (self.size.get().y - 2)).max(0)
+    }
+}
+
+impl Component for TextArea {
+    type Res = TextAreaResult;
+
+    fn update(&mut self, theme: &Theme, events: Vec<Event>) -> TextAreaResult {
+        let mut edits = vec![];
+        let mut caret_moved = false;
+        let mut layout = self.layout(theme);
+        // The number of edits that `layout` includes, since the text only has to be wrapped again
+        // after it's edited
+        let mut laid_out_edits = 0;
+        for event in events {
+            if laid_out_edits != edits.len() {
+                layout = self.layout(theme);
+                laid_out_edits = edits.len();
+            }
+            match event {
+                Event::KeyDown(key) => {
+                    caret_moved = true;
+                    let shift = key.shift;
+                    match key.key.as_ref() {
+                        "ArrowLeft" | "ArrowRight" => {
+                            let pos = match (self.selection(), shift, key.key.as_ref()) {
+                                (Some(selection), false, "ArrowLeft") => selection.start,
+                                (Some(selection), false, _) => selection.end,
+                                (_, _, "ArrowLeft") => layout.move_visually(self.caret_pos, false),
+                                _ => layout.move_visually(self.caret_pos, true),
+                            };
+                            self.preferred_x = None;
+                            self.move_caret(pos, shift);
+                        }
+                        "ArrowUp" => {
+                            let pos = self.pos_after_moving_lines(&layout, -1);
+                            self.move_caret(pos, shift);
+                        }
+                        "ArrowDown" => {
+                            let pos = self.pos_after_moving_lines(&layout, 1);
+                            self.move_caret(pos, shift);
+                        }
+                        "PageUp" => {
+                            let rows = self.visible_rows(theme);
+                            let pos = self.pos_after_moving_lines(&layout, -rows);
+                            self.move_caret(pos, shift);
+                        }
+                        "PageDown" => {
+                            let rows = self.visible_rows(theme);
+                            let pos = self.pos_after_moving_lines(&layout, rows);
+                            self.move_caret(pos, shift);
+                        }
+                        "Home" => {
+                            let pos = if key.ctrl {
+                                0
+                            } else {
+                                layout.lines()[layout.line_at(self.caret_pos)].range.start
+                            };
+                            self.preferred_x = None;
+                            self.move_caret(pos, shift);
+                        }
+                        "End" => {
+                            let pos = if key.ctrl {
+                                self.text.len()
+                            } else {
+                                layout.line_end(layout.line_at(self.caret_pos))
+                            };
+                            self.preferred_x = None;
+                            self.move_caret(pos, shift);
+                        }
+                        "Backspace" => {
+                            let range = self.selection().unwrap_or_else(|| {
+                                layout.prev_boundary(self.caret_pos)..self.caret_pos
+                            });
+                            self.replace_range(range, "", &mut edits);
+                        }
+                        "Delete" => {
+                            let range = self.selection().unwrap_or_else(|| {
+                                self.caret_pos..layout.next_boundary(self.caret_pos)
+                            });
+                            self.replace_range(range, "", &mut edits);
+                        }
+                        "Enter" => self.replace_selection("\n", &mut edits),
+                        _ => match key.text() {
+                            Some(text) => self.replace_selection(text, &mut edits),
+                            None => caret_moved = false,
+                        },
+                    }
+                }
+                Event::Paste(pasted) => {
+                    self.replace_selection(&pasted.replace("\r\n", "\n"), &mut edits);
+                    caret_moved = true;
+                }
+                Event::MouseDown(MouseButton::Left, pos) => {
+                    let pos = self.pos_at_point(&layout, pos);
+                    self.preferred_x = None;
+                    self.move_caret(pos, false);
+                    self.selection_anchor = Some(pos);
+                    self.dragging = true;
+                    caret_moved = true;
+                }
+                Event::MouseMove { pos, .. } if self.dragging => {
+                    self.caret_pos = self.pos_at_point(&layout, pos);
+                    caret_moved = true;
+                }
+                Event::MouseUp(MouseButton::Left, _) | Event::FocusLost => {
+                    self.dragging = false;
+                    if self.selection().is_none() {
+                        self.selection_anchor = None;
+                    }
+                }
+                Event::Scroll(delta) => {
+                    self.scroll_y += delta as i32 * theme.font.advance_y() * SCROLL_LINES;
+                }
+                _ => (),
             }
         }
-        if self.continuous_updates {
-            res = Some(self.cur_text().to_owned());
+
+        // Keep the caret visible
+        if laid_out_edits != edits.len() {
+            layout = self.layout(theme);
         }
-        TextEntryResult { text: res }
+        let advance_y = theme.font.advance_y();
+        if caret_moved && self.size.get().y > 0 {
+            let caret_y = layout.line_at(self.caret_pos) as i32 * advance_y;
+            let visible_height = self.size.get().y - 2;
+            if caret_y < self.scroll_y {
+                self.scroll_y = caret_y;
+            } else if caret_y + advance_y > self.scroll_y + visible_height {
+                self.scroll_y = caret_y + advance_y - visible_height;
+            }
+        }
+        self.scroll_y = self.scroll_y.max(0).min(self.max_scroll_y(theme, layout.lines().len()));
+
+        TextAreaResult { edits, text: self.text.clone() }
     }
 }
 
-impl Widget for TextEntry {
+impl Widget for TextArea {
     fn id(&self) -> WidgetId {
         self.id
     }
@@ -1064,6 +4152,10 @@ impl Widget for TextEntry {
         true
     }
 
+    fn clips_to_rect(&self) -> bool {
+        true
+    }
+
     fn draw(
         &self,
         context: &GlContext,
@@ -1074,37 +4166,1069 @@ impl Widget for TextEntry {
         _cursor_pos: Option<Point2<i32>>,
         is_active: bool,
     ) {
-        let fill_color = theme.button_fill_color;
-        let (drawn_text, drawn_text_color) = if self.text.is_empty() {
-            (&self.placeholder_text, theme.button_text_color * 0.8)
-        } else {
-            (&self.text, theme.button_text_color)
-        };
-        draw_2d.fill_rect(rect, fill_color);
+        draw_2d.fill_rect(rect, theme.button_fill_color);
         draw_2d.outline_rect(rect, theme.button_border_color, 1.0);
-        theme.font.draw_string(context, &drawn_text, rect.start + vec2(2, 1), drawn_text_color);
+
+        let advance_y = theme.font.advance_y();
+        let layout = theme.font.layout(&self.text, Some(Self::wrap_width(rect.size().x)));
+        let text_start: Point2<f32> = (rect.start + vec2(2, 1 - self.scroll_y)).cast().unwrap();
+        if let Some(selection) = self.selection() {
+            for selection_rect in layout.selection_rects(selection) {
+                let selection_rect = Rect::new(
+                    text_start + selection_rect.start.to_vec(),
+                    text_start + selection_rect.end.to_vec(),
+                );
+                draw_2d.fill_rect(selection_rect.cast().unwrap(), theme.selection_color);
+            }
+        }
+        for line in layout.lines() {
+            let line_pos = text_start + vec2(0.0, line.y);
+            if line_pos.y + advance_y as f32 >= rect.start.y as f32
+                && line_pos.y <= rect.end.y as f32
+            {
+                theme.font.draw_shaped(context, &line.shaped, line_pos, self.text_color);
+            }
+        }
+
         if self.stopwatch.get_time().rem_euclid(CARET_BLINK_RATE) < CARET_BLINK_RATE * 0.5
             && is_active
         {
-            let caret_x_offset =
-                theme.font.string_width(context, &drawn_text[0..self.caret_pos as usize]) + 2.0;
+            let caret_rect = layout.caret_rect(self.caret_pos);
             draw_2d.draw_line(
-                point2(caret_x_offset + rect.start.x as f32, rect.start.y as f32 + 2.0),
-                point2(caret_x_offset + rect.start.x as f32, rect.end.y as f32 - 2.0),
-                theme.button_text_color,
+                text_start + caret_rect.start.to_vec() + vec2(0.0, 1.0),
+                text_start + caret_rect.end.to_vec() - vec2(0.0, 1.0),
+                self.text_color,
                 1.0,
             );
         }
     }
 
+    fn layout_hash(&self) -> Option<u64> {
+        Some(hash_layout(&("TextArea", self.rows)))
+    }
+
     fn min_size(
         &self,
-        context: &GlContext,
         theme: &Theme,
         _min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
         _window_size: Vector2<i32>,
     ) -> Vector2<i32> {
-        let drawn_text = if self.text.is_empty() { &self.placeholder_text } else { &self.text };
-        theme.font.string_size(context, drawn_text) + vec2(4, 2)
+        vec2(4, theme.font.advance_y() * self.rows + 2)
+    }
+
+    fn compute_rects(
+        &self,
+        rect: Rect<i32>,
+        _theme: &Theme,
+        _min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+        widget_rects: &mut WidgetRects,
+    ) {
+        widget_rects.insert(self.id(), rect);
+    }
+
+    fn laid_out(&self, widget_rects: &FnvHashMap<WidgetId, Rect<i32>>) {
+        if let Some(rect) = widget_rects.get(&self.id) {
+            self.size.set(rect.size());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A theme whose font makes every character 8 pixels wide and every line 16 pixels tall.
+    fn theme() -> Theme {
+        Theme::new(Font::new_fixed(8.0, 16))
+    }
+
+    fn rect(x: i32, y: i32, width: i32, height: i32) -> Rect<i32> {
+        Rect::new(point2(x, y), point2(x + width, y + height))
+    }
+
+    #[test]
+    fn col_stretches_flexible_children() {
+        let (a, b) = (Label::new("abc"), Label::new("defgh"));
+        let (a_id, b_id) = (a.id(), b.id());
+        let col = Col::new().child(0.0, a).child(1.0, b);
+        let col_id = col.id();
+        let layout = compute_layout(&*col, &theme(), vec2(200, 100));
+
+        assert_eq!(layout.min_sizes[&a_id], vec2(24, 16));
+        assert_eq!(layout.min_sizes[&col_id], vec2(40, 32));
+        assert_eq!(layout.widget_rects[&col_id], rect(0, 0, 200, 100));
+        assert_eq!(layout.widget_rects[&a_id], rect(0, 0, 200, 16));
+        assert_eq!(layout.widget_rects[&b_id], rect(0, 16, 200, 84));
+    }
+
+    #[test]
+    fn positioned_widgets_stay_within_the_window() {
+        let tip = Label::new("tip");
+        let tip_id = tip.id();
+        let target = rect(0, 0, 40, 16);
+        let stack = Stack::new()
+            .child(Label::new("abc"))
+            .positioned(Positioned::beside(target, Side::Above), tip);
+        let col = Col::new().child(0.0, stack).child(1.0, EmptyWidget::new());
+        let layout = compute_layout(&*col, &theme(), vec2(200, 100));
+
+        // There's no room above the target in the window, but there is below it outside the stack
+        assert_eq!(layout.widget_rects[&tip_id], rect(0, 16, 24, 16));
+        assert!(layout.validate(&*col).is_empty());
+    }
+
+    #[test]
+    fn positioned_widgets_stay_within_scroll_area_viewports() {
+        let tip = Label::new("tip");
+        let tip_id = tip.id();
+        let target = rect(0, 80, 40, 16);
+        let stack = Stack::new()
+            .child(EmptyWidget::with_size(vec2(50, 200)))
+            .positioned(Positioned::beside(target, Side::Below), tip);
+        let scroll_area = ScrollArea::new().view(stack);
+        let layout = compute_layout(&*scroll_area, &theme(), vec2(200, 100));
+
+        // The stack is taller than the window, but the tip is kept within the viewport
+        assert_eq!(layout.widget_rects[&tip_id], rect(0, 64, 24, 16));
+        assert!(layout.validate(&*scroll_area).is_empty());
+    }
+
+    #[test]
+    fn row_splits_space_by_flex() {
+        let (a, b, c) = (Label::new("ab"), Label::new("c"), Label::new("d"));
+        let (a_id, b_id, c_id) = (a.id(), b.id(), c.id());
+        let row = Row::new().child(0.0, a).child(1.0, b).child(3.0, c);
+        let layout = compute_layout(&*row, &theme(), vec2(100, 40));
+
+        assert_eq!(layout.widget_rects[&a_id], rect(0, 0, 16, 40));
+        // The 68 pixels left over are split 1:3
+        assert_eq!(layout.widget_rects[&b_id], rect(16, 0, 25, 40));
+        assert_eq!(layout.widget_rects[&c_id], rect(41, 0, 59, 40));
+    }
+
+    /// Lays out three 20x10 widgets in a 100x40 row with the given justification.
+    fn justified_rects(justify: Justify) -> Vec<Rect<i32>> {
+        let children: Vec<Box<dyn Widget>> =
+            (0..3).map(|_| EmptyWidget::with_size(vec2(20, 10)) as Box<dyn Widget>).collect();
+        let ids: Vec<WidgetId> = children.iter().map(|child| child.id()).collect();
+        let flex = Flex::new(Axis::Horizontal)
+            .children(children.into_iter().map(|child| (0.0, child)).collect())
+            .justify(justify)
+            .align(CrossAlign::Start);
+        let layout = compute_layout(&*flex, &theme(), vec2(100, 40));
+        ids.iter().map(|id| layout.widget_rects[id]).collect()
+    }
+
+    #[test]
+    fn flex_justifies_children() {
+        assert_eq!(
+            justified_rects(Justify::Start),
+            vec![rect(0, 0, 20, 10), rect(20, 0, 20, 10), rect(40, 0, 20, 10)],
+        );
+        assert_eq!(
+            justified_rects(Justify::Center),
+            vec![rect(20, 0, 20, 10), rect(40, 0, 20, 10), rect(60, 0, 20, 10)],
+        );
+        assert_eq!(
+            justified_rects(Justify::End),
+            vec![rect(40, 0, 20, 10), rect(60, 0, 20, 10), rect(80, 0, 20, 10)],
+        );
+        assert_eq!(
+            justified_rects(Justify::SpaceBetween),
+            vec![rect(0, 0, 20, 10), rect(40, 0, 20, 10), rect(80, 0, 20, 10)],
+        );
+        // The 40 pixels left over are 13.3 around each child, rounded
+        assert_eq!(
+            justified_rects(Justify::SpaceAround),
+            vec![rect(7, 0, 20, 10), rect(40, 0, 20, 10), rect(73, 0, 20, 10)],
+        );
+    }
+
+    #[test]
+    fn flex_wraps_children_onto_new_lines() {
+        let children: Vec<Box<dyn Widget>> =
+            (0..5).map(|_| EmptyWidget::with_size(vec2(30, 10)) as Box<dyn Widget>).collect();
+        let ids: Vec<WidgetId> = children.iter().map(|child| child.id()).collect();
+        let flex = Flex::new(Axis::Horizontal)
+            .children(children.into_iter().map(|child| (0.0, child)).collect())
+            .align(CrossAlign::Start)
+            .gap(4)
+            .line_gap(2)
+            .wrap();
+        let layout = compute_layout(&*flex, &theme(), vec2(100, 40));
+
+        let rects: Vec<Rect<i32>> = ids.iter().map(|id| layout.widget_rects[id]).collect();
+        assert_eq!(
+            rects,
+            vec![
+                rect(0, 0, 30, 10),
+                rect(34, 0, 30, 10),
+                rect(68, 0, 30, 10),
+                rect(0, 12, 30, 10),
+                rect(34, 12, 30, 10),
+            ],
+        );
+    }
+
+    #[test]
+    fn flex_aligns_baselines() {
+        // The fixed font's baseline is 13 pixels down, and the inset adds 4 pixels of padding
+        let (label, inset) = (Label::new("a"), Inset::new(Label::new("b")));
+        let empty = EmptyWidget::with_size(vec2(8, 30));
+        let ids = [label.id(), inset.id(), empty.id()];
+        let flex = Flex::new(Axis::Horizontal)
+            .child(0.0, label)
+            .child(0.0, inset)
+            .child(0.0, empty)
+            .align(CrossAlign::Baseline);
+        let layout = compute_layout(&*flex, &theme(), vec2(100, 40));
+
+        assert_eq!(layout.widget_rects[&ids[0]], rect(0, 4, 8, 16));
+        assert_eq!(layout.widget_rects[&ids[1]], rect(8, 0, 16, 24));
+        // Children without a baseline go at the start of the line
+        assert_eq!(layout.widget_rects[&ids[2]], rect(24, 0, 8, 30));
+    }
+
+    /// Lays out a grid in a 100x100 window, returning the rects of the given widgets.
+    fn grid_rects(grid: &Grid, ids: &[WidgetId]) -> Vec<Rect<i32>> {
+        let layout = compute_layout(grid, &theme(), vec2(100, 100));
+        ids.iter().map(|id| layout.widget_rects[id]).collect()
+    }
+
+    #[test]
+    fn grid_cells_span_tracks() {
+        let children = [vec2(50, 10), vec2(10, 10), vec2(10, 40), vec2(15, 10)]
+            .iter()
+            .map(|&size| EmptyWidget::with_size(size))
+            .collect::<Vec<_>>();
+        let ids: Vec<WidgetId> = children.iter().map(|child| child.id()).collect();
+        let mut children = children.into_iter();
+        let mut child = || children.next().unwrap() as Box<dyn Widget>;
+        let grid = Grid::new(vec![Track::Fixed(20), Track::Auto, Track::Auto])
+            .gap(2, 3)
+            .cell(GridCell::new(0, 0).span(1, 2), child())
+            .child(0, 2, child())
+            .cell(GridCell::new(1, 0).span(2, 1), child())
+            .child(1, 1, child());
+
+        // The first cell widens the second column to 28, and the third cell's 27 extra pixels
+        // are split between the second and third rows
+        assert_eq!(
+            grid_rects(&grid, &ids),
+            vec![
+                rect(0, 0, 50, 10),
+                rect(52, 0, 10, 10),
+                rect(0, 13, 20, 40),
+                rect(22, 13, 28, 24)
+            ],
+        );
+    }
+
+    #[test]
+    fn grid_splits_space_between_fractional_tracks() {
+        let children: Vec<Box<dyn Widget>> =
+            (0..3).map(|_| EmptyWidget::with_size(vec2(0, 10)) as Box<dyn Widget>).collect();
+        let ids: Vec<WidgetId> = children.iter().map(|child| child.id()).collect();
+        let grid = children.into_iter().enumerate().fold(
+            Grid::new(vec![Track::Fixed(20), Track::Fraction(1.0), Track::Fraction(3.0)]),
+            |grid, (column, child)| grid.child(0, column, child),
+        );
+
+        assert_eq!(
+            grid_rects(&grid, &ids),
+            vec![rect(0, 0, 20, 10), rect(20, 0, 20, 10), rect(40, 0, 60, 10)],
+        );
+    }
+
+    #[test]
+    fn grid_cell_spanning_a_fractional_track_only_grows_that_track() {
+        let (a, b) = (EmptyWidget::with_size(vec2(30, 10)), EmptyWidget::with_size(vec2(80, 10)));
+        let ids = [a.id(), b.id()];
+        let grid = Grid::new(vec![Track::Auto, Track::Fraction(1.0)])
+            .child(0, 0, a)
+            .cell(GridCell::new(1, 0).span(1, 2), b);
+
+        assert_eq!(grid_rects(&grid, &ids), vec![rect(0, 0, 30, 10), rect(0, 10, 100, 10)]);
+    }
+
+    #[test]
+    fn grid_narrower_than_its_fixed_tracks() {
+        let children: Vec<Box<dyn Widget>> =
+            (0..3).map(|_| EmptyWidget::with_size(vec2(10, 10)) as Box<dyn Widget>).collect();
+        let ids: Vec<WidgetId> = children.iter().map(|child| child.id()).collect();
+        let grid = children.into_iter().enumerate().fold(
+            Grid::new(vec![Track::Fixed(60), Track::Fixed(60), Track::Fraction(1.0)]),
+            |grid, (column, child)| grid.child(0, column, child),
+        );
+
+        // Fixed tracks keep their sizes, and there's no space left over for the fractional track.
+        // The cells past the grid's edge are clipped, so they're valid.
+        let layout = compute_layout(&*grid, &theme(), vec2(100, 100));
+        let rects: Vec<Rect<i32>> = ids.iter().map(|id| layout.widget_rects[id]).collect();
+        assert_eq!(rects, vec![rect(0, 0, 60, 10), rect(60, 0, 60, 10), rect(120, 0, 10, 10)]);
+        assert!(layout.validate(&*grid).is_empty());
+    }
+
+    #[test]
+    fn paragraph_wraps_to_its_max_width() {
+        let theme = theme();
+        let paragraph = Paragraph::new("one two three four");
+        let layout = compute_layout(&*paragraph, &theme, vec2(200, 100));
+        let wrapped = |width| {
+            let constraints =
+                BoxConstraints::new(vec2(0, 0), vec2(width, BoxConstraints::UNBOUNDED));
+            widget_layout(&*paragraph, constraints, &theme, &layout.min_sizes)
+        };
+
+        // "one two ", "three " and "four"
+        assert_eq!(wrap_text(&theme.font, "one two three four", 60.0).len(), 3);
+        assert_eq!(wrapped(60), vec2(60, 48));
+        // Unwrapped, it's only as wide as its text
+        assert_eq!(wrapped(200), vec2(144, 16));
+
+        // A column gives it the height of all of its lines
+        let (paragraph, label) = (Paragraph::new("one two three four"), Label::new("x"));
+        let label_id = label.id();
+        let col = Col::new().child(0.0, paragraph).child(0.0, label);
+        let layout = compute_layout(&*col, &theme, vec2(60, 100));
+        assert_eq!(layout.widget_rects[&label_id], rect(0, 48, 60, 16));
+    }
+
+    #[test]
+    fn text_sizes_round_up() {
+        let theme = Theme::new(Font::new_fixed(7.5, 16));
+        let label = Label::new("abc");
+        let label_id = label.id();
+        let layout = compute_layout(&*label, &theme, vec2(100, 100));
+
+        assert_eq!(layout.min_sizes[&label_id], vec2(23, 16));
+        assert_eq!(theme.font.string_size("a"), vec2(8, 16));
+    }
+
+    #[test]
+    fn label_outline_and_shadow_grow_its_min_size() {
+        let min_size = |label: Box<Label>, theme: &Theme| {
+            let label_id = label.id();
+            compute_layout(&*label, theme, vec2(100, 100)).min_sizes[&label_id]
+        };
+        let theme = theme();
+        assert_eq!(min_size(Label::new("ab"), &theme), vec2(16, 16));
+        // Outlines are rounded up to whole pixels on every side
+        assert_eq!(min_size(Label::new("ab").outline(1.5, Color4::BLACK), &theme), vec2(20, 20));
+        // Shadows only need space on the side they're offset to
+        let shadow = Label::new("ab").shadow(vec2(2.0, -1.5), Color4::BLACK);
+        assert_eq!(min_size(shadow, &theme), vec2(18, 18));
+        let both =
+            Label::new("ab").outline(1.0, Color4::BLACK).shadow(vec2(2.0, 3.0), Color4::BLACK);
+        assert_eq!(both.baseline(&theme), Some(14));
+        assert_eq!(min_size(both, &theme), vec2(20, 21));
+
+        // The theme's outline applies to labels without their own
+        let mut outlined_theme = self::theme();
+        outlined_theme.label_outline = Some(TextOutline { width: 1.0, color: Color4::BLACK });
+        assert_eq!(min_size(Label::new("ab"), &outlined_theme), vec2(18, 18));
+    }
+
+    #[test]
+    fn label_outline_and_shadow_change_its_layout_hash() {
+        let hash = |label: Box<Label>| label.layout_hash().unwrap();
+        let plain = hash(Label::new("ab"));
+        let outlined = hash(Label::new("ab").outline(1.0, Color4::BLACK));
+        let shadowed = hash(Label::new("ab").shadow(vec2(1.0, 1.0), Color4::BLACK));
+        assert_ne!(plain, outlined);
+        assert_ne!(plain, shadowed);
+        assert_ne!(outlined, shadowed);
+        assert_ne!(outlined, hash(Label::new("ab").outline(2.0, Color4::BLACK)));
+        assert_ne!(shadowed, hash(Label::new("ab").shadow(vec2(1.0, 2.0), Color4::BLACK)));
+        // Colors don't affect the layout
+        assert_eq!(outlined, hash(Label::new("ab").outline(1.0, Color4::RED)));
+        assert_eq!(shadowed, hash(Label::new("ab").shadow(vec2(1.0, 1.0), Color4::RED)));
+    }
+
+    #[test]
+    fn inset_pads_its_child() {
+        let label = Label::new("abc");
+        let label_id = label.id();
+        let inset = Inset::new(label);
+        let inset_id = inset.id();
+        let layout = compute_layout(&*inset, &theme(), vec2(200, 100));
+
+        assert_eq!(layout.min_sizes[&inset_id], vec2(32, 24));
+        assert_eq!(layout.widget_rects[&inset_id], rect(0, 0, 200, 100));
+        assert_eq!(layout.widget_rects[&label_id], rect(4, 4, 192, 92));
+    }
+
+    #[test]
+    fn inset_smaller_than_its_padding() {
+        let label = Label::new("abc");
+        let label_id = label.id();
+        let inset = Inset::new(label);
+        let layout = compute_layout(&*inset, &theme(), vec2(6, 3));
+
+        assert_eq!(layout.widget_rects[&label_id], rect(4, 3, 0, 0));
+        assert!(layout.validate(&*inset).is_empty());
+    }
+
+    #[test]
+    fn margin_larger_than_its_rect() {
+        let label = Label::new("abc");
+        let label_id = label.id();
+        let margin = Margin::new(Insets::new(10, 20, 10, 20), label);
+        let layout = compute_layout(&*margin, &theme(), vec2(6, 12));
+
+        assert_eq!(layout.widget_rects[&label_id], rect(6, 12, 0, 0));
+        assert!(layout.validate(&*margin).is_empty());
+    }
+
+    #[test]
+    fn align_puts_its_child_at_the_alignment() {
+        let rect_at = |alignment| {
+            let child = EmptyWidget::with_size(vec2(20, 10));
+            let child_id = child.id();
+            let align = Align::new(alignment, child);
+            let layout = compute_layout(&*align, &theme(), vec2(100, 60));
+            assert_eq!(layout.widget_rects[&align.id()], rect(0, 0, 100, 60));
+            layout.widget_rects[&child_id]
+        };
+        let alignments = [
+            (Alignment::TOP_LEFT, rect(0, 0, 20, 10)),
+            (Alignment::TOP, rect(40, 0, 20, 10)),
+            (Alignment::TOP_RIGHT, rect(80, 0, 20, 10)),
+            (Alignment::LEFT, rect(0, 25, 20, 10)),
+            (Alignment::CENTER, rect(40, 25, 20, 10)),
+            (Alignment::RIGHT, rect(80, 25, 20, 10)),
+            (Alignment::BOTTOM_LEFT, rect(0, 50, 20, 10)),
+            (Alignment::BOTTOM, rect(40, 50, 20, 10)),
+            (Alignment::BOTTOM_RIGHT, rect(80, 50, 20, 10)),
+            (Alignment::new(0.25, 0.1), rect(20, 5, 20, 10)),
+        ];
+        for &(alignment, expected) in alignments.iter() {
+            assert_eq!(rect_at(alignment), expected);
+        }
+    }
+
+    #[test]
+    fn center_centers_its_child() {
+        let label = Label::new("abc");
+        let label_id = label.id();
+        let layout = compute_layout(&*Center::new(label), &theme(), vec2(100, 60));
+        assert_eq!(layout.widget_rects[&label_id], rect(38, 22, 24, 16));
+    }
+
+    #[test]
+    fn sized_box_gives_its_child_its_size() {
+        let label = Label::new("a");
+        let label_id = label.id();
+        let sized_box = SizedBox::new(vec2(30, 20)).child(label);
+        let layout = compute_layout(&*sized_box, &theme(), vec2(100, 60));
+        assert_eq!(layout.widget_rects[&sized_box.id()], rect(0, 0, 30, 20));
+        assert_eq!(layout.widget_rects[&label_id], rect(0, 0, 30, 20));
+
+        // It can't be larger than the space it's given
+        let sized_box = SizedBox::new(vec2(150, 20));
+        let layout = compute_layout(&*sized_box, &theme(), vec2(100, 60));
+        assert_eq!(layout.widget_rects[&sized_box.id()], rect(0, 0, 100, 20));
+    }
+
+    #[test]
+    fn constrained_box_keeps_its_child_within_its_min_and_max() {
+        let child = EmptyWidget::with_size(vec2(10, 10));
+        let child_id = child.id();
+        let constrained_box = ConstrainedBox::new(child).min_width(30).min_height(20);
+        let layout = compute_layout(&*constrained_box, &theme(), vec2(100, 60));
+        assert_eq!(layout.widget_rects[&child_id], rect(0, 0, 30, 20));
+
+        let child = EmptyWidget::with_size(vec2(80, 50));
+        let child_id = child.id();
+        let constrained_box = ConstrainedBox::new(child).max_width(40).max_height(30);
+        let layout = compute_layout(&*constrained_box, &theme(), vec2(100, 60));
+        assert_eq!(layout.widget_rects[&child_id], rect(0, 0, 40, 30));
+    }
+
+    #[test]
+    fn aspect_ratio_keeps_its_ratio() {
+        let theme = theme();
+        let aspect_ratio = AspectRatio::new(2.0, EmptyWidget::with_size(vec2(10, 4)));
+        let layout = compute_layout(&*aspect_ratio, &theme, vec2(100, 60));
+        assert_eq!(layout.min_sizes[&aspect_ratio.id()], vec2(10, 5));
+        // Within the window, it's as wide as possible
+        assert_eq!(layout.widget_rects[&aspect_ratio.id()], rect(0, 0, 100, 50));
+        let layout_within =
+            |constraints| widget_layout(&*aspect_ratio, constraints, &theme, &layout.min_sizes);
+
+        // Loose constraints shrink it to fit the height, and unbounded ones use the min size
+        assert_eq!(layout_within(BoxConstraints::loose(vec2(100, 40))), vec2(80, 40));
+        let unbounded_width = BoxConstraints::new(vec2(0, 0), vec2(BoxConstraints::UNBOUNDED, 30));
+        assert_eq!(layout_within(unbounded_width), vec2(60, 30));
+        assert_eq!(layout_within(BoxConstraints::unbounded()), vec2(10, 5));
+        // Tight constraints win over the ratio
+        assert_eq!(layout_within(BoxConstraints::tight(vec2(100, 60))), vec2(100, 60));
+    }
+
+    #[test]
+    #[should_panic]
+    fn aspect_ratio_must_be_positive() {
+        AspectRatio::new(0.0, EmptyWidget::with_size(vec2(10, 10)));
+    }
+
+    #[test]
+    fn overlap_gives_every_child_its_rect() {
+        let (a, b) = (Label::new("abc"), Label::new("defgh"));
+        let (a_id, b_id) = (a.id(), b.id());
+        let overlap = Overlap::new().child(a).child(b);
+        let overlap_id = overlap.id();
+        let layout = compute_layout(&*overlap, &theme(), vec2(200, 100));
+
+        assert_eq!(layout.min_sizes[&overlap_id], vec2(40, 16));
+        assert_eq!(layout.widget_rects[&a_id], rect(0, 0, 200, 100));
+        assert_eq!(layout.widget_rects[&b_id], rect(0, 0, 200, 100));
+    }
+
+    #[test]
+    fn nested_layout() {
+        let (a, b, c) = (Label::new("a"), Label::new("bb"), Label::new("ccc"));
+        let (a_id, b_id, c_id) = (a.id(), b.id(), c.id());
+        let row = Row::new().child(0.0, a).child(1.0, b);
+        let row_id = row.id();
+        let inset = Inset::new(row);
+        let col = Col::new().child(0.0, inset).child(1.0, Overlap::new().child(c));
+        let layout = compute_layout(&*col, &theme(), vec2(100, 80));
+
+        assert_eq!(layout.widget_rects[&row_id], rect(4, 4, 92, 16));
+        assert_eq!(layout.widget_rects[&a_id], rect(4, 4, 8, 16));
+        assert_eq!(layout.widget_rects[&b_id], rect(12, 4, 84, 16));
+        assert_eq!(layout.widget_rects[&c_id], rect(0, 24, 100, 56));
+        assert!(layout.validate(&*col).is_empty());
+    }
+
+    fn key(key: &str) -> Event {
+        modified_key(key, false, false)
+    }
+
+    fn modified_key(key: &str, shift: bool, ctrl: bool) -> Event {
+        Event::KeyDown(Key { key: key.to_owned(), code: String::new(), shift, ctrl, alt: false })
+    }
+
+    fn type_keys(entry: &mut TextEntry, keys: &[&str]) {
+        entry.update(&theme(), keys.iter().map(|k| key(k)).collect());
+    }
+
+    fn text_entry(text: &str, max_len: usize) -> Box<TextEntry> {
+        TextEntry::new(text, "", false, max_len, false)
+    }
+
+    #[test]
+    fn text_entry_edits_cjk_by_character() {
+        let mut entry = text_entry("", 10);
+        type_keys(&mut entry, &["日", "本", "語"]);
+        assert_eq!(entry.text, "日本語");
+        assert_eq!(entry.caret_pos, 9);
+
+        type_keys(&mut entry, &["ArrowLeft"]);
+        assert_eq!(entry.caret_pos, 6);
+        type_keys(&mut entry, &["Backspace"]);
+        assert_eq!((entry.text.as_str(), entry.caret_pos), ("日語", 3));
+        type_keys(&mut entry, &["Delete"]);
+        assert_eq!((entry.text.as_str(), entry.caret_pos), ("日", 3));
+    }
+
+    #[test]
+    fn text_entry_treats_combining_accents_as_one_character() {
+        let mut entry = text_entry("", 10);
+        // A precomposed "é", then an "e" followed by a combining acute accent
+        type_keys(&mut entry, &["é", "e\u{301}", "x"]);
+        assert_eq!(entry.text, "ée\u{301}x");
+
+        type_keys(&mut entry, &["ArrowLeft", "ArrowLeft"]);
+        assert_eq!(entry.caret_pos, "é".len());
+        type_keys(&mut entry, &["Delete"]);
+        assert_eq!(entry.text, "éx");
+        type_keys(&mut entry, &["Backspace"]);
+        assert_eq!((entry.text.as_str(), entry.caret_pos), ("x", 0));
+        type_keys(&mut entry, &["ArrowRight"]);
+        assert_eq!(entry.caret_pos, 1);
+    }
+
+    #[test]
+    fn text_entry_treats_emoji_sequences_as_one_character() {
+        let family = "👩\u{200d}👩\u{200d}👧";
+        let mut entry = text_entry(&format!("a{}b", family), 10);
+        type_keys(&mut entry, &["End", "ArrowLeft", "ArrowLeft"]);
+        assert_eq!(entry.caret_pos, 1);
+        type_keys(&mut entry, &["ArrowRight"]);
+        assert_eq!(entry.caret_pos, 1 + family.len());
+        type_keys(&mut entry, &["Backspace"]);
+        assert_eq!((entry.text.as_str(), entry.caret_pos), ("ab", 1));
+    }
+
+    #[test]
+    fn text_entry_max_len_counts_grapheme_clusters() {
+        let family = "👩\u{200d}👩\u{200d}👧";
+        let mut entry = text_entry("日", 3);
+        type_keys(&mut entry, &["End", family, "e\u{301}", "x"]);
+        assert_eq!(entry.text, format!("日{}e\u{301}", family));
+
+        // Text typed at once is truncated to whole clusters
+        let mut entry = text_entry("a", 3);
+        type_keys(&mut entry, &["End", "e\u{301}e\u{301}e\u{301}"]);
+        assert_eq!(entry.text, "ae\u{301}e\u{301}");
+    }
+
+    #[test]
+    fn text_entry_snaps_caret_out_of_clusters_when_text_changes() {
+        let family = "👩\u{200d}👩\u{200d}👧";
+        let mut entry = text_entry("abcdefgh", 10);
+        type_keys(&mut entry, &["End", "ArrowLeft"]);
+
+        // The caret is now inside the emoji, and the anchor is past the end of the text
+        entry.text = format!("é{}", family);
+        entry.selection_anchor = Some(30);
+        type_keys(&mut entry, &[]);
+        assert_eq!(entry.caret_pos, "é".len());
+        assert_eq!(entry.selection(), Some("é".len()..entry.text.len()));
+
+        type_keys(&mut entry, &["x"]);
+        assert_eq!(entry.text, "éx");
+    }
+
+    #[test]
+    fn text_entry_copy_cut_and_paste() {
+        let clipboard = Rc::new(MemoryClipboard::new());
+        let mut entry = text_entry("hello world", 20).clipboard(clipboard.clone());
+        let theme = theme();
+
+        entry.update(&theme, vec![modified_key("a", false, true), modified_key("c", false, true)]);
+        assert_eq!(entry.selection(), Some(0..11));
+        assert_eq!(clipboard.get_text().as_deref(), Some("hello world"));
+
+        // Select "world" and cut it
+        entry.update(
+            &theme,
+            vec![key("End"), modified_key("ArrowLeft", true, true), modified_key("x", false, true)],
+        );
+        assert_eq!(entry.text, "hello ");
+        assert_eq!(clipboard.get_text().as_deref(), Some("world"));
+
+        entry.update(&theme, vec![key("Home"), modified_key("V", false, true)]);
+        assert_eq!((entry.text.as_str(), entry.caret_pos), ("worldhello ", 5));
+
+        // Pasted text replaces the selection, and line breaks are dropped
+        clipboard.set_text("big\nblue ");
+        entry.update(
+            &theme,
+            vec![
+                modified_key("ArrowLeft", true, false),
+                modified_key("ArrowLeft", true, false),
+                modified_key("v", false, true),
+            ],
+        );
+        assert_eq!(entry.text, "worbigblue hello ");
+        assert_eq!(entry.selection(), None);
+
+        // Copying and cutting without a selection leave the clipboard alone
+        entry.update(&theme, vec![modified_key("c", false, true), modified_key("x", false, true)]);
+        assert_eq!(clipboard.get_text().as_deref(), Some("big\nblue "));
+        assert_eq!(entry.text, "worbigblue hello ");
+    }
+
+    #[test]
+    fn text_entry_pastes_from_paste_events() {
+        // Like a `BrowserClipboard`, this clipboard can't be read when Ctrl+V is pressed
+        let mut entry = text_entry("ab", 5).clipboard(Rc::new(BrowserClipboard::new()));
+        let theme = theme();
+        entry.update(
+            &theme,
+            vec![
+                key("ArrowRight"),
+                modified_key("v", false, true),
+                Event::Paste("x\r\ny".to_owned()),
+            ],
+        );
+        assert_eq!((entry.text.as_str(), entry.caret_pos), ("axyb", 3));
+        // Pastes are truncated to the maximum length
+        entry.update(&theme, vec![Event::Paste("123".to_owned())]);
+        assert_eq!(entry.text, "axy1b");
+    }
+
+    #[test]
+    fn text_area_pastes_from_paste_events() {
+        let mut area = TextArea::new("ab", 3);
+        let theme = theme();
+        compute_layout(&*area.clone(), &theme, vec2(100, 100));
+        area.update(&theme, vec![key("ArrowRight"), modified_key("ArrowRight", true, false)]);
+        let res = area.update(&theme, vec![Event::Paste("x\r\ny".to_owned())]);
+        assert_eq!((area.text(), area.caret_pos), ("ax\ny", 4));
+        assert_eq!(res.edits, vec![TextEdit { range: 1..2, replacement: "x\ny".to_owned() }]);
+    }
+
+    #[test]
+    fn text_entry_shift_arrow_selection() {
+        let mut entry = text_entry("abcdef", 20);
+        let theme = theme();
+        entry.update(&theme, vec![key("Home"), key("ArrowRight")]);
+        entry.update(
+            &theme,
+            vec![modified_key("ArrowRight", true, false), modified_key("ArrowRight", true, false)],
+        );
+        assert_eq!(entry.selection(), Some(1..3));
+
+        // Shrinking the selection back past the anchor selects the other way
+        entry.update(
+            &theme,
+            vec![
+                modified_key("ArrowLeft", true, false),
+                modified_key("ArrowLeft", true, false),
+                modified_key("ArrowLeft", true, false),
+            ],
+        );
+        assert_eq!(entry.selection(), Some(0..1));
+
+        // Without shift, an arrow collapses the selection to the side it points to
+        entry.update(&theme, vec![key("ArrowRight")]);
+        assert_eq!((entry.selection(), entry.caret_pos), (None, 1));
+
+        entry.update(&theme, vec![modified_key("End", true, false)]);
+        assert_eq!(entry.selection(), Some(1..6));
+        entry.update(&theme, vec![modified_key("Home", true, false)]);
+        assert_eq!(entry.selection(), Some(0..1));
+        entry.update(&theme, vec![key("Backspace")]);
+        assert_eq!((entry.text.as_str(), entry.caret_pos), ("bcdef", 0));
+    }
+
+    #[test]
+    fn text_entry_word_navigation() {
+        let mut entry = text_entry("one two  three", 20);
+        let theme = theme();
+        entry.update(&theme, vec![key("Home"), modified_key("ArrowRight", false, true)]);
+        assert_eq!(entry.caret_pos, 3);
+        entry.update(&theme, vec![modified_key("ArrowRight", false, true)]);
+        assert_eq!(entry.caret_pos, 7);
+        entry.update(&theme, vec![modified_key("ArrowRight", false, true)]);
+        assert_eq!(entry.caret_pos, 14);
+
+        entry.update(&theme, vec![modified_key("ArrowLeft", false, true)]);
+        assert_eq!(entry.caret_pos, 9);
+        entry.update(&theme, vec![modified_key("ArrowLeft", true, true)]);
+        assert_eq!(entry.selection(), Some(4..9));
+
+        entry.update(&theme, vec![key("End"), modified_key("Backspace", false, true)]);
+        assert_eq!(entry.text, "one two  ");
+        entry.update(&theme, vec![key("Home"), modified_key("Delete", false, true)]);
+        assert_eq!(entry.text, " two  ");
+    }
+
+    #[test]
+    fn layout_cache_is_cleared_when_theme_changes() {
+        let mut theme = theme();
+        let mut cache = LayoutCache::new();
+        let tree = || Inset::new(Label::new("a\tb"));
+        cache.layout(&*tree(), &theme, vec2(100, 100));
+        cache.layout(&*tree(), &theme, vec2(100, 100));
+        assert_eq!(cache.stats().relayout_count(), 0);
+
+        theme.padding = 8;
+        let layout = cache.layout(&*tree(), &theme, vec2(100, 100));
+        assert_eq!(cache.stats().min_sizes_computed, 2);
+        assert!(layout.min_sizes.values().any(|&size| size == vec2(88, 32)));
+
+        theme.font.set_tab_width(16.0);
+        let layout = cache.layout(&*tree(), &theme, vec2(100, 100));
+        assert_eq!(cache.stats().min_sizes_computed, 2);
+        assert!(layout.min_sizes.values().any(|&size| size == vec2(40, 32)));
+    }
+
+    /// A widget that only gives its second child a rect.
+    struct HidesFirstChild {
+        id: WidgetId,
+        children: Vec<Box<dyn Widget>>,
+    }
+
+    impl Widget for HidesFirstChild {
+        fn id(&self) -> WidgetId {
+            self.id
+        }
+
+        fn draw(
+            &self,
+            _context: &GlContext,
+            _surface: &dyn Surface,
+            _rect: Rect<i32>,
+            _theme: &Theme,
+            _draw_2d: &mut Draw2d,
+            _cursor_pos: Option<Point2<i32>>,
+            _is_active: bool,
+        ) {
+        }
+
+        fn layout_hash(&self) -> Option<u64> {
+            Some(hash_layout(&"HidesFirstChild"))
+        }
+
+        fn min_size(
+            &self,
+            _theme: &Theme,
+            _min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+            _window_size: Vector2<i32>,
+        ) -> Vector2<i32> {
+            Vector2::zero()
+        }
+
+        fn children(&self) -> Vec<&dyn Widget> {
+            self.children.iter().map(|child| &**child).collect()
+        }
+
+        fn compute_rects(
+            &self,
+            rect: Rect<i32>,
+            theme: &Theme,
+            min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+            widget_rects: &mut WidgetRects,
+        ) {
+            widget_rects.insert(self.id, rect);
+            widget_compute_rects(&*self.children[1], rect, theme, min_sizes, widget_rects);
+        }
+    }
+
+    #[test]
+    fn layout_cache_reuses_rects_of_trees_with_missing_rects() {
+        let theme = theme();
+        let mut cache = LayoutCache::new();
+        let tree = || {
+            let children: Vec<Box<dyn Widget>> = vec![Label::new("a"), Label::new("b")];
+            HidesFirstChild { id: WidgetId::new(), children }
+        };
+        cache.layout(&tree(), &theme, vec2(100, 100));
+
+        let tree = tree();
+        let layout = cache.layout(&tree, &theme, vec2(100, 100));
+        assert_eq!(cache.stats().rects_computed, 0);
+        assert_eq!(layout.widget_rects.get(&tree.children[0].id()), None);
+        assert_eq!(layout.widget_rects[&tree.children[1].id()], rect(0, 0, 100, 100));
+    }
+
+    #[test]
+    fn layout_cache_reuses_rects_of_unchanged_subtrees() {
+        let theme = theme();
+        let mut cache = LayoutCache::new();
+        let tree = |text| {
+            let inset = Inset::new(Label::new("abc"));
+            let ids = vec![inset.id(), inset.children()[0].id()];
+            (Col::new().child(0.0, inset).child(1.0, Label::new(text)), ids)
+        };
+        cache.layout(&*tree("x").0, &theme, vec2(100, 100));
+
+        // Only the changed label and the column it's in are laid out again
+        let (tree, ids) = tree("y");
+        let layout = cache.layout(&*tree, &theme, vec2(100, 100));
+        assert_eq!(cache.stats().min_sizes_computed, 2);
+        assert_eq!(cache.stats().rects_computed, 2);
+        assert_eq!(
+            layout.widget_rects,
+            compute_layout(&*tree, &theme, vec2(100, 100)).widget_rects
+        );
+        assert_eq!(layout.widget_rects[&ids[1]], rect(4, 4, 92, 16));
+    }
+
+    #[test]
+    fn layout_cache_reuses_scroll_areas_until_they_scroll() {
+        let theme = theme();
+        let mut cache = LayoutCache::new();
+        let mut scroll_area = ScrollArea::new();
+        let tree = |scroll_area: &ScrollArea| {
+            let text = TextBox::new("a\nb\nc\nd\ne\nf\ng\nh");
+            let text_id = text.id();
+            (scroll_area.view(text), text_id)
+        };
+        cache.layout(&*tree(&scroll_area).0, &theme, vec2(100, 50));
+        cache.layout(&*tree(&scroll_area).0, &theme, vec2(100, 50));
+        assert_eq!(cache.stats().relayout_count(), 0);
+
+        scroll_area.set_offset(vec2(0, 20));
+        let (tree, text_id) = tree(&scroll_area);
+        let layout = cache.layout(&*tree, &theme, vec2(100, 50));
+        assert_eq!(cache.stats().rects_computed, 2);
+        assert_eq!(layout.widget_rects[&text_id], rect(0, -20, 88, 128));
+    }
+
+    #[test]
+    fn layout_cache_clamps_scroll_offsets_when_the_window_is_resized() {
+        let theme = theme();
+        let mut cache = LayoutCache::new();
+        let mut scroll_area = ScrollArea::new();
+        let mut layout = |scroll_area: &ScrollArea, window_size| {
+            // 128 pixels tall
+            let text = TextBox::new("a\nb\nc\nd\ne\nf\ng\nh");
+            let text_id = text.id();
+            let layout = cache.layout(&*scroll_area.view(text), &theme, window_size);
+            layout.widget_rects[&text_id]
+        };
+        scroll_area.set_offset(vec2(0, 60));
+        assert_eq!(layout(&scroll_area, vec2(100, 50)).start, point2(0, -60));
+        assert_eq!(scroll_area.offset(), vec2(0, 60));
+
+        assert_eq!(layout(&scroll_area, vec2(100, 100)).start, point2(0, -28));
+        assert_eq!(scroll_area.offset(), vec2(0, 28));
+
+        // Scrolling back to where it was reuses the rects it was given last time, which still
+        // clamps the offset
+        scroll_area.set_offset(vec2(0, 60));
+        assert_eq!(layout(&scroll_area, vec2(100, 100)).start, point2(0, -28));
+        assert_eq!(cache.stats().rects_computed, 0);
+        assert_eq!(scroll_area.offset(), vec2(0, 28));
+    }
+
+    #[test]
+    fn text_area_arrows_cross_lines() {
+        let mut area = TextArea::new("ab\ncd", 3);
+        let theme = theme();
+        // Record the size it wraps to
+        compute_layout(&*area.clone(), &theme, vec2(100, 100));
+        area.update(&theme, vec![modified_key("Home", false, true), key("ArrowRight")]);
+        area.update(&theme, vec![key("ArrowRight"), key("ArrowRight")]);
+        assert_eq!(area.caret_pos, 3);
+        area.update(&theme, vec![key("ArrowLeft")]);
+        assert_eq!(area.caret_pos, 2);
+
+        area.update(&theme, vec![key("Delete")]);
+        assert_eq!(area.text(), "abcd");
+        area.update(&theme, vec![key("Backspace"), key("Backspace")]);
+        assert_eq!((area.text(), area.caret_pos), ("cd", 0));
+        area.update(&theme, vec![key("ArrowLeft")]);
+        assert_eq!(area.caret_pos, 0);
+    }
+
+    #[test]
+    fn text_area_vertical_arrows_keep_the_x_position() {
+        let mut area = TextArea::new("abcdef\nab\nabcdef", 3);
+        let theme = theme();
+        compute_layout(&*area.clone(), &theme, vec2(100, 100));
+        area.update(&theme, vec![modified_key("Home", false, true), key("End")]);
+        assert_eq!(area.caret_pos, 6);
+        area.update(&theme, vec![key("ArrowDown")]);
+        assert_eq!(area.caret_pos, 9);
+        area.update(&theme, vec![key("ArrowDown")]);
+        assert_eq!(area.caret_pos, 16);
+        area.update(&theme, vec![key("ArrowUp"), key("ArrowUp")]);
+        assert_eq!(area.caret_pos, 6);
+        // Moving horizontally forgets the x position
+        area.update(&theme, vec![key("ArrowLeft"), key("ArrowDown"), key("ArrowDown")]);
+        assert_eq!(area.caret_pos, 15);
+        area.update(&theme, vec![modified_key("ArrowUp", true, false)]);
+        assert_eq!(area.selection(), Some(9..15));
+    }
+
+    #[test]
+    fn text_area_pages_by_the_visible_rows() {
+        let mut area = TextArea::new("0\n1\n2\n3\n4\n5\n6", 3);
+        let theme = theme();
+        // 3 rows are visible
+        compute_layout(&*area.clone(), &theme, vec2(100, 50));
+        area.update(&theme, vec![modified_key("Home", false, true), key("PageDown")]);
+        assert_eq!((area.caret_pos, area.scroll_y), (6, 16));
+        area.update(&theme, vec![key("PageDown")]);
+        assert_eq!((area.caret_pos, area.scroll_y), (12, 64));
+        area.update(&theme, vec![key("PageDown")]);
+        assert_eq!(area.caret_pos, 13);
+        area.update(&theme, vec![key("PageUp")]);
+        assert_eq!((area.caret_pos, area.scroll_y), (6, 48));
+        area.update(&theme, vec![key("PageUp"), key("PageUp")]);
+        assert_eq!((area.caret_pos, area.scroll_y), (0, 0));
+    }
+
+    #[test]
+    fn text_area_home_and_end_use_the_visual_line() {
+        let mut area = TextArea::new("abcd efgh", 3);
+        let theme = theme();
+        // Wraps to "abcd " and "efgh"
+        compute_layout(&*area.clone(), &theme, vec2(44, 50));
+        area.update(&theme, vec![modified_key("End", false, true), key("Home")]);
+        assert_eq!(area.caret_pos, 5);
+        area.update(&theme, vec![key("ArrowUp"), key("End")]);
+        assert_eq!(area.caret_pos, 4);
+        area.update(&theme, vec![key("Home")]);
+        assert_eq!(area.caret_pos, 0);
+        area.update(&theme, vec![key("ArrowDown"), key("End")]);
+        assert_eq!(area.caret_pos, 9);
+    }
+
+    #[test]
+    fn text_area_selects_by_dragging() {
+        let mut area = TextArea::new("ab\ncd", 3);
+        let theme = theme();
+        compute_layout(&*area.clone(), &theme, vec2(100, 100));
+        // Text starts at (2, 1)
+        area.update(&theme, vec![Event::MouseDown(MouseButton::Left, point2(11, 19))]);
+        assert_eq!((area.caret_pos, area.selection()), (4, None));
+        area.update(&theme, vec![Event::MouseMove { pos: point2(3, 3), movement: vec2(-8, -16) }]);
+        area.update(&theme, vec![Event::MouseUp(MouseButton::Left, point2(3, 3))]);
+        assert_eq!((area.caret_pos, area.selection()), (0, Some(0..4)));
+        // Moving the mouse after releasing the button doesn't select
+        area.update(&theme, vec![Event::MouseMove { pos: point2(30, 3), movement: vec2(27, 0) }]);
+        assert_eq!(area.selection(), Some(0..4));
+
+        // Clicking without dragging clears the selection
+        area.update(
+            &theme,
+            vec![
+                Event::MouseDown(MouseButton::Left, point2(19, 3)),
+                Event::MouseUp(MouseButton::Left, point2(19, 3)),
+            ],
+        );
+        assert_eq!((area.caret_pos, area.selection()), (2, None));
+    }
+
+    #[test]
+    fn text_area_moves_by_lines_before_its_first_layout() {
+        let mut area = TextArea::new("abcdef\nab", 3);
+        let theme = theme();
+        area.update(&theme, vec![key("ArrowDown")]);
+        assert_eq!((area.caret_pos, area.scroll_y), (7, 0));
+        area.update(&theme, vec![key("ArrowUp"), key("End")]);
+        assert_eq!((area.caret_pos, area.scroll_y), (6, 0));
+        area.update(&theme, vec![key("PageDown")]);
+        assert_eq!((area.caret_pos, area.scroll_y), (9, 0));
+    }
+
+    /// A widget that only implements `layout`, and is 30x10 unless it's constrained.
+    struct LayoutOnly {
+        id: WidgetId,
+    }
+
+    impl Widget for LayoutOnly {
+        fn id(&self) -> WidgetId {
+            self.id
+        }
+
+        fn draw(
+            &self,
+            _context: &GlContext,
+            _surface: &dyn Surface,
+            _rect: Rect<i32>,
+            _theme: &Theme,
+            _draw_2d: &mut Draw2d,
+            _cursor_pos: Option<Point2<i32>>,
+            _is_active: bool,
+        ) {
+        }
+
+        fn layout(
+            &self,
+            constraints: BoxConstraints,
+            _theme: &Theme,
+            _min_sizes: &FnvHashMap<WidgetId, Vector2<i32>>,
+        ) -> Vector2<i32> {
+            constraints.constrain(vec2(30, 10))
+        }
+    }
+
+    #[test]
+    fn min_size_defaults_to_unconstrained_layout() {
+        let widget = LayoutOnly { id: WidgetId::new() };
+        let id = widget.id;
+        let row = Row::new().child(0.0, Box::new(widget)).child(1.0, EmptyWidget::new());
+        let layout = compute_layout(&*row, &theme(), vec2(100, 40));
+
+        assert_eq!(layout.min_sizes[&id], vec2(30, 10));
+        assert_eq!(layout.widget_rects[&id], rect(0, 0, 30, 40));
     }
 }